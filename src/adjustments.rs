@@ -0,0 +1,146 @@
+use adw::prelude::*;
+use relm4::prelude::*;
+
+use crate::decoder::VideoAdjustments;
+
+#[derive(Debug)]
+pub struct AdjustmentsPopover {
+    adjustments: VideoAdjustments,
+}
+
+#[derive(Debug)]
+pub enum AdjustmentsMsg {
+    /// Shows the adjustments of a newly loaded file
+    Set(VideoAdjustments),
+    Brightness(f64),
+    Contrast(f64),
+    Saturation(f64),
+    Gamma(f64),
+    Hue(f64),
+    Reset,
+}
+
+#[relm4::component(pub)]
+impl SimpleComponent for AdjustmentsPopover {
+    type Init = ();
+    type Input = AdjustmentsMsg;
+    type Output = VideoAdjustments;
+
+    view! {
+        gtk::Popover {
+            gtk::Box {
+                set_orientation: gtk::Orientation::Vertical,
+                set_spacing: 6,
+                set_margin_all: 6,
+                set_width_request: 280,
+
+                gtk::Label {
+                    set_label: "Brightness",
+                    set_halign: gtk::Align::Start,
+                },
+                gtk::Scale::with_range(gtk::Orientation::Horizontal, -1.0, 1.0, 0.01) {
+                    set_draw_value: true,
+                    #[watch]
+                    #[block_signal(brightness_handler)]
+                    set_value: model.adjustments.brightness,
+                    connect_value_changed[sender] => move |scale| {
+                        sender.input(AdjustmentsMsg::Brightness(scale.value()));
+                    } @brightness_handler,
+                },
+
+                gtk::Label {
+                    set_label: "Contrast",
+                    set_halign: gtk::Align::Start,
+                },
+                gtk::Scale::with_range(gtk::Orientation::Horizontal, 0.0, 2.0, 0.01) {
+                    set_draw_value: true,
+                    #[watch]
+                    #[block_signal(contrast_handler)]
+                    set_value: model.adjustments.contrast,
+                    connect_value_changed[sender] => move |scale| {
+                        sender.input(AdjustmentsMsg::Contrast(scale.value()));
+                    } @contrast_handler,
+                },
+
+                gtk::Label {
+                    set_label: "Saturation",
+                    set_halign: gtk::Align::Start,
+                },
+                gtk::Scale::with_range(gtk::Orientation::Horizontal, 0.0, 3.0, 0.01) {
+                    set_draw_value: true,
+                    #[watch]
+                    #[block_signal(saturation_handler)]
+                    set_value: model.adjustments.saturation,
+                    connect_value_changed[sender] => move |scale| {
+                        sender.input(AdjustmentsMsg::Saturation(scale.value()));
+                    } @saturation_handler,
+                },
+
+                gtk::Label {
+                    set_label: "Gamma",
+                    set_halign: gtk::Align::Start,
+                },
+                gtk::Scale::with_range(gtk::Orientation::Horizontal, 0.1, 3.0, 0.01) {
+                    set_draw_value: true,
+                    #[watch]
+                    #[block_signal(gamma_handler)]
+                    set_value: model.adjustments.gamma,
+                    connect_value_changed[sender] => move |scale| {
+                        sender.input(AdjustmentsMsg::Gamma(scale.value()));
+                    } @gamma_handler,
+                },
+
+                gtk::Label {
+                    set_label: "Hue",
+                    set_halign: gtk::Align::Start,
+                },
+                gtk::Scale::with_range(gtk::Orientation::Horizontal, -180.0, 180.0, 1.0) {
+                    set_draw_value: true,
+                    #[watch]
+                    #[block_signal(hue_handler)]
+                    set_value: model.adjustments.hue,
+                    connect_value_changed[sender] => move |scale| {
+                        sender.input(AdjustmentsMsg::Hue(scale.value()));
+                    } @hue_handler,
+                },
+
+                gtk::Button {
+                    set_label: "Reset",
+                    #[watch]
+                    set_sensitive: !model.adjustments.is_default(),
+                    connect_clicked => AdjustmentsMsg::Reset,
+                },
+            }
+        }
+    }
+
+    fn init(
+        _init: Self::Init,
+        root: Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let model = Self {
+            adjustments: VideoAdjustments::default(),
+        };
+        let widgets = view_output!();
+
+        ComponentParts { model, widgets }
+    }
+
+    fn update(&mut self, msg: Self::Input, sender: ComponentSender<Self>) {
+        let adjustments = &mut self.adjustments;
+        match msg {
+            AdjustmentsMsg::Set(set) => {
+                *adjustments = set;
+                return;
+            }
+            AdjustmentsMsg::Brightness(value) => adjustments.brightness = value,
+            AdjustmentsMsg::Contrast(value) => adjustments.contrast = value,
+            AdjustmentsMsg::Saturation(value) => adjustments.saturation = value,
+            AdjustmentsMsg::Gamma(value) => adjustments.gamma = value,
+            AdjustmentsMsg::Hue(value) => adjustments.hue = value,
+            AdjustmentsMsg::Reset => *adjustments = VideoAdjustments::default(),
+        }
+        let _ = sender.output(self.adjustments);
+    }
+}
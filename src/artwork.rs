@@ -0,0 +1,97 @@
+use std::path::Path;
+
+use ffmpeg_next::format;
+use gtk::{gdk, glib};
+
+use crate::decoder::is_cover_art;
+
+/// Image files looked for next to an audio file when it has no embedded cover.
+const SIDECAR_NAMES: &[&str] = &["cover.jpg", "cover.png", "folder.jpg", "front.jpg"];
+/// Attached pictures are queued before any audio, so only the first few packets are searched
+const COVER_PACKETS: usize = 64;
+
+/// Tags and cover art shown in place of the video for audio files.
+#[derive(Debug, Default)]
+pub struct AudioInfo {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub date: Option<String>,
+    pub cover: Option<gdk::Texture>,
+}
+
+impl AudioInfo {
+    /// Returns artist, album and year on one line, leaving out the ones not tagged.
+    pub fn subtitle(&self) -> String {
+        let mut line = [&self.artist, &self.album]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join(" — ");
+        if let Some(year) = self.date.as_deref().and_then(|d| d.get(..4)) {
+            line.push_str(&format!(" ({year})"));
+        }
+        line.trim().to_string()
+    }
+}
+
+/// Reads the tags and cover art of a local audio file. The cover is taken from an attached
+/// picture stream, or else from an image such as `cover.jpg` in the same folder.
+pub fn probe(location: &Path) -> AudioInfo {
+    let mut info = AudioInfo::default();
+    match format::input(location) {
+        Ok(mut input) => {
+            read_tags(&input, &mut info);
+            info.cover = embedded_cover(&mut input);
+        }
+        Err(e) => eprintln!("Error: Could not open {} ({e})", location.display()),
+    }
+    if info.cover.is_none() {
+        info.cover = sidecar_cover(location);
+    }
+    info
+}
+
+/// Reads tags from the container, or from the streams for formats like Ogg that keep them there.
+fn read_tags(input: &format::context::Input, info: &mut AudioInfo) {
+    let metadata = std::iter::once(input.metadata())
+        .chain(input.streams().map(|s| s.metadata()))
+        .collect::<Vec<_>>();
+    let tag = |name: &str| {
+        metadata
+            .iter()
+            .flat_map(|m| m.iter())
+            .find(|(key, value)| key.eq_ignore_ascii_case(name) && !value.trim().is_empty())
+            .map(|(_, value)| value.trim().to_string())
+    };
+    info.title = tag("title");
+    info.artist = tag("artist").or_else(|| tag("album_artist"));
+    info.album = tag("album");
+    info.date = tag("date");
+}
+
+fn embedded_cover(input: &mut format::context::Input) -> Option<gdk::Texture> {
+    let index = input.streams().find(is_cover_art)?.index();
+    let packet = input
+        .packets()
+        .take(COVER_PACKETS)
+        .find(|(stream, _)| stream.index() == index)
+        .map(|(_, packet)| packet)?;
+    gdk::Texture::from_bytes(&glib::Bytes::from(packet.data()?))
+        .inspect_err(|e| eprintln!("Error: Could not load cover art ({e})"))
+        .ok()
+}
+
+fn sidecar_cover(location: &Path) -> Option<gdk::Texture> {
+    let dir = location.parent()?;
+    SIDECAR_NAMES
+        .iter()
+        .map(|name| dir.join(name))
+        .find(|path| path.is_file())
+        .and_then(|path| {
+            gdk::Texture::from_filename(&path)
+                .inspect_err(|e| eprintln!("Error: Could not load {} ({e})", path.display()))
+                .ok()
+        })
+}
@@ -0,0 +1,267 @@
+use std::path::Path;
+
+use serde_json::{json, Value};
+
+use crate::csv;
+use crate::ffmetadata::{Chapter, Metadata};
+use crate::{storage, timecode};
+
+const FILE: &str = "bookmarks.ini";
+const CSV_COLUMNS: [&str; 3] = ["position", "name", "note"];
+
+/// A named position in a file, with an optional note.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bookmark {
+    /// In microseconds
+    pub position: i64,
+    pub name: String,
+    pub note: String,
+}
+
+/// File formats bookmarks can be exported to and imported from.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum BookmarkFormat {
+    #[default]
+    Json,
+    Csv,
+    /// Chapters in ffmpeg's metadata format, for muxing with `-map_metadata` or `-map_chapters`
+    FfMetadata,
+}
+
+impl BookmarkFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Csv => "csv",
+            Self::FfMetadata => "ffmeta",
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("csv") => Self::Csv,
+            Some("ffmeta" | "ffmetadata" | "txt") => Self::FfMetadata,
+            _ => Self::Json,
+        }
+    }
+}
+
+/// Returns the bookmarks saved for `location`, sorted by position.
+pub fn load(location: &Path) -> Vec<Bookmark> {
+    let file = storage::load(&storage::data_file(FILE));
+    let group = storage::key_for(location);
+    let list = |key| file.string_list(&group, key).unwrap_or_default();
+    let (positions, names, notes) = (list("positions"), list("names"), list("notes"));
+    let mut bookmarks = positions
+        .iter()
+        .zip(names.iter())
+        .zip(notes.iter())
+        .filter_map(|((position, name), note)| {
+            Some(Bookmark {
+                position: position.parse().ok()?,
+                name: name.to_string(),
+                note: note.to_string(),
+            })
+        })
+        .collect::<Vec<_>>();
+    bookmarks.sort_by_key(|b| b.position);
+    bookmarks
+}
+
+/// Saves the bookmarks of `location`, forgetting the file when there are none.
+pub fn store(location: &Path, bookmarks: &[Bookmark]) {
+    let path = storage::data_file(FILE);
+    let file = storage::load(&path);
+    let group = storage::key_for(location);
+    if bookmarks.is_empty() {
+        let _ = file.remove_group(&group);
+    } else {
+        let positions = bookmarks
+            .iter()
+            .map(|b| b.position.to_string())
+            .collect::<Vec<_>>();
+        let positions = positions.iter().map(String::as_str).collect::<Vec<_>>();
+        let names = bookmarks
+            .iter()
+            .map(|b| b.name.as_str())
+            .collect::<Vec<_>>();
+        let notes = bookmarks
+            .iter()
+            .map(|b| b.note.as_str())
+            .collect::<Vec<_>>();
+        file.set_string(&group, "location", &location.to_string_lossy());
+        file.set_string_list(&group, "positions", &positions);
+        file.set_string_list(&group, "names", &names);
+        file.set_string_list(&group, "notes", &notes);
+    }
+    storage::save(&file, &path);
+}
+
+/// Writes bookmarks in `format`. Chapters need an end, so each one lasts until the next
+/// bookmark, and the last one until `duration` if it is known.
+pub fn export(bookmarks: &[Bookmark], format: BookmarkFormat, duration: i64) -> String {
+    match format {
+        BookmarkFormat::Json => {
+            let bookmarks = bookmarks
+                .iter()
+                .map(|b| {
+                    json!({
+                        "position": b.position as f64 / 1_000_000.0,
+                        "timecode": timecode::format_precise(b.position),
+                        "name": b.name,
+                        "note": b.note,
+                    })
+                })
+                .collect::<Vec<_>>();
+            serde_json::to_string_pretty(&json!({ "bookmarks": bookmarks })).unwrap() + "\n"
+        }
+        BookmarkFormat::Csv => {
+            let mut text = CSV_COLUMNS.join(",") + "\n";
+            for bookmark in bookmarks {
+                text += &csv::row(&[
+                    timecode::format_precise(bookmark.position),
+                    bookmark.name.clone(),
+                    bookmark.note.clone(),
+                ]);
+                text.push('\n');
+            }
+            text
+        }
+        BookmarkFormat::FfMetadata => {
+            let chapters = bookmarks
+                .iter()
+                .enumerate()
+                .map(|(i, bookmark)| {
+                    let end = bookmarks.get(i + 1).map_or(duration, |b| b.position);
+                    let mut tags = vec![(String::from("title"), bookmark.name.clone())];
+                    if !bookmark.note.is_empty() {
+                        tags.push((String::from("comment"), bookmark.note.clone()));
+                    }
+                    Chapter {
+                        start: bookmark.position,
+                        end,
+                        tags,
+                    }
+                })
+                .collect();
+            Metadata {
+                tags: Vec::new(),
+                chapters,
+            }
+            .write()
+        }
+    }
+}
+
+fn from_json(text: &str) -> Result<Vec<Bookmark>, String> {
+    let value: Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    // A bare array is accepted too, as written by other tools
+    let items = value
+        .get("bookmarks")
+        .unwrap_or(&value)
+        .as_array()
+        .ok_or("Expected a list of bookmarks")?;
+    items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| {
+            let string = |key| item.get(key).and_then(Value::as_str).unwrap_or_default();
+            let position = match item.get("position") {
+                Some(Value::String(text)) => timecode::parse(text, None),
+                Some(value) => value
+                    .as_f64()
+                    .filter(|s| s.abs() * 1_000_000.0 <= timecode::MAX_POSITION as f64)
+                    .map(|s| (s * 1_000_000.0).round() as i64),
+                None => timecode::parse(string("timecode"), None),
+            };
+            Ok(Bookmark {
+                position: position.ok_or(format!("Bookmark {} has no valid position", i + 1))?,
+                name: string("name").to_string(),
+                note: string("note").to_string(),
+            })
+        })
+        .collect()
+}
+
+fn from_csv(text: &str) -> Result<Vec<Bookmark>, String> {
+    let mut records = csv::parse(text).into_iter().peekable();
+    // The header is optional, but when present it decides the column order
+    let mut columns = [0, 1, 2];
+    if let Some(header) = records.peek() {
+        let index = |name| {
+            header
+                .iter()
+                .position(|h| h.trim().eq_ignore_ascii_case(name))
+        };
+        if let Some(position) = index("position") {
+            columns = [
+                position,
+                index("name").unwrap_or(usize::MAX),
+                index("note").unwrap_or(usize::MAX),
+            ];
+            records.next();
+        }
+    }
+    records
+        .enumerate()
+        .map(|(i, record)| {
+            let field = |column: usize| record.get(column).map_or("", String::as_str);
+            Ok(Bookmark {
+                position: timecode::parse(field(columns[0]), None)
+                    .ok_or(format!("Row {} has no valid position", i + 1))?,
+                name: field(columns[1]).to_string(),
+                note: field(columns[2]).to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Reads bookmarks written by `export` or by other tools. Chapters become bookmarks at their
+/// start, named after their title. Files with positions before the start or absurdly far past
+/// it are rejected.
+pub fn import(text: &str, format: BookmarkFormat) -> Result<Vec<Bookmark>, String> {
+    let mut bookmarks = match format {
+        BookmarkFormat::Json => from_json(text)?,
+        BookmarkFormat::Csv => from_csv(text)?,
+        BookmarkFormat::FfMetadata => Metadata::parse(text)?
+            .chapters
+            .into_iter()
+            .map(|chapter| Bookmark {
+                position: chapter.start,
+                name: chapter.tag("title").unwrap_or_default().to_string(),
+                note: chapter.tag("comment").unwrap_or_default().to_string(),
+            })
+            .collect(),
+    };
+    if let Some(i) = bookmarks
+        .iter()
+        .position(|b| !(0..=timecode::MAX_POSITION).contains(&b.position))
+    {
+        return Err(format!("Bookmark {} is out of range", i + 1));
+    }
+    bookmarks.sort_by_key(|b| b.position);
+    Ok(bookmarks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_positions_out_of_range() {
+        let json = r#"{"bookmarks": [{"position": 1e300}]}"#;
+        assert!(import(json, BookmarkFormat::Json).is_err());
+        let json = r#"[{"position": -2.5}]"#;
+        assert!(import(json, BookmarkFormat::Json).is_err());
+        let chapters = ";FFMETADATA1\n[CHAPTER]\nTIMEBASE=1/1\nSTART=9223372036854775807\n";
+        assert!(import(chapters, BookmarkFormat::FfMetadata).is_err());
+
+        let json = r#"[{"position": 90.5, "name": "Intro"}]"#;
+        let imported = import(json, BookmarkFormat::Json).unwrap();
+        assert_eq!(imported[0].position, 90_500_000);
+        assert_eq!(
+            timecode::format_precise(imported[0].position),
+            "00:01:30.500"
+        );
+    }
+}
@@ -0,0 +1,368 @@
+use std::path::PathBuf;
+
+use adw::prelude::*;
+use gtk::glib::clone;
+use relm4::{
+    actions::{RelmAction, RelmActionGroup},
+    prelude::*,
+};
+
+use crate::bookmarks::{self, Bookmark, BookmarkFormat};
+use crate::timecode;
+
+relm4::new_action_group!(BookmarksActionGroup, "bookmarks");
+relm4::new_stateless_action!(ImportBookmarks, BookmarksActionGroup, "import");
+relm4::new_stateless_action!(ExportBookmarks, BookmarksActionGroup, "export");
+
+pub struct BookmarksWindow {
+    visible: bool,
+    /// File the bookmarks belong to
+    path: Option<PathBuf>,
+    duration: i64,
+    bookmarks: Vec<Bookmark>,
+    list: gtk::ListBox,
+}
+
+#[derive(Debug)]
+pub enum BookmarksMsg {
+    /// Shows the bookmarks of the file that started playing
+    SetFile(PathBuf),
+    SetDuration(i64),
+    /// Bookmarks a position of the current file
+    Add(i64),
+    Rename(usize, String),
+    SetNote(usize, String),
+    Remove(usize),
+    Jump(usize),
+    Import,
+    Export,
+    Show,
+    Hide,
+}
+
+#[derive(Debug)]
+pub enum BookmarksOutput {
+    /// Asks for the current position to be bookmarked
+    AddCurrent,
+    SeekTo(i64),
+    /// Positions and names of the current file's bookmarks, for the seek bar
+    Changed(Vec<(i64, String)>),
+}
+
+#[relm4::component(async, pub)]
+impl AsyncComponent for BookmarksWindow {
+    type Init = adw::Window;
+    type Input = BookmarksMsg;
+    type Output = BookmarksOutput;
+    type CommandOutput = ();
+
+    menu! {
+        file_menu: {
+            section! {
+                "Import…" => ImportBookmarks,
+                "Export…" => ExportBookmarks,
+            }
+        }
+    }
+
+    view! {
+        #[root]
+        window = adw::Window {
+            set_title: Some("Bookmarks"),
+            set_default_size: (420, 480),
+            #[watch]
+            set_visible: model.visible,
+            gtk::Box {
+                set_orientation: gtk::Orientation::Vertical,
+                adw::HeaderBar {
+                    pack_start = &gtk::Button {
+                        set_icon_name: "list-add-symbolic",
+                        set_tooltip_text: Some("Bookmark Current Position"),
+                        #[watch]
+                        set_sensitive: model.path.is_some(),
+                        connect_clicked[sender] => move |_| {
+                            let _ = sender.output(BookmarksOutput::AddCurrent);
+                        },
+                    },
+                    pack_end = &gtk::MenuButton {
+                        set_icon_name: "document-send-symbolic",
+                        set_tooltip_text: Some("Import or Export Bookmarks"),
+                        set_menu_model: Some(&file_menu),
+                        #[watch]
+                        set_sensitive: model.path.is_some(),
+                    },
+                },
+                adw::StatusPage {
+                    set_icon_name: Some("user-bookmarks-symbolic"),
+                    set_title: "No Bookmarks",
+                    set_description: Some("Press Ctrl+B to bookmark the current position"),
+                    set_vexpand: true,
+                    #[watch]
+                    set_visible: model.bookmarks.is_empty(),
+                },
+                gtk::ScrolledWindow {
+                    set_hscrollbar_policy: gtk::PolicyType::Never,
+                    set_vexpand: true,
+                    #[watch]
+                    set_visible: !model.bookmarks.is_empty(),
+                    adw::Clamp {
+                        set_margin_all: 12,
+                        #[local_ref]
+                        list -> gtk::ListBox {
+                            set_selection_mode: gtk::SelectionMode::None,
+                            set_valign: gtk::Align::Start,
+                            add_css_class: "boxed-list",
+                        },
+                    },
+                },
+            },
+            connect_close_request[sender] => move |_| {
+                sender.input(BookmarksMsg::Hide);
+                gtk::glib::Propagation::Proceed
+            }
+        }
+    }
+
+    async fn init(
+        init: Self::Init,
+        root: Self::Root,
+        sender: AsyncComponentSender<Self>,
+    ) -> AsyncComponentParts<Self> {
+        let model = Self {
+            visible: false,
+            path: None,
+            duration: 0,
+            bookmarks: Vec::new(),
+            list: gtk::ListBox::new(),
+        };
+
+        let list = &model.list;
+        let widgets = view_output!();
+        widgets.window.set_transient_for(Some(&init));
+
+        let mut group = RelmActionGroup::<BookmarksActionGroup>::new();
+        group.add_action::<ImportBookmarks>(RelmAction::new_stateless(clone!(
+            #[strong]
+            sender,
+            move |_| {
+                sender.input(BookmarksMsg::Import);
+            }
+        )));
+        group.add_action::<ExportBookmarks>(RelmAction::new_stateless(clone!(
+            #[strong]
+            sender,
+            move |_| {
+                sender.input(BookmarksMsg::Export);
+            }
+        )));
+        group.register_for_widget(&widgets.window);
+
+        AsyncComponentParts { model, widgets }
+    }
+
+    async fn update(
+        &mut self,
+        msg: Self::Input,
+        sender: AsyncComponentSender<Self>,
+        _root: &Self::Root,
+    ) {
+        match msg {
+            BookmarksMsg::SetFile(path) => {
+                self.bookmarks = bookmarks::load(&path);
+                self.path = Some(path);
+                self.duration = 0;
+                self.changed(&sender);
+                self.render(&sender);
+            }
+            BookmarksMsg::SetDuration(duration) => {
+                self.duration = duration;
+            }
+            BookmarksMsg::Add(position) => {
+                if self.path.is_none() || self.bookmarks.iter().any(|b| b.position == position) {
+                    return;
+                }
+                let index = self.bookmarks.partition_point(|b| b.position < position);
+                self.bookmarks.insert(
+                    index,
+                    Bookmark {
+                        position,
+                        name: format!("Bookmark {}", self.bookmarks.len() + 1),
+                        note: String::new(),
+                    },
+                );
+                self.save();
+                self.changed(&sender);
+                self.render(&sender);
+            }
+            // Edits keep the rows, so the entry being typed in keeps its focus
+            BookmarksMsg::Rename(index, name) => {
+                if let Some(bookmark) = self.bookmarks.get_mut(index) {
+                    bookmark.name = name;
+                    self.save();
+                    self.changed(&sender);
+                }
+            }
+            BookmarksMsg::SetNote(index, note) => {
+                if let Some(bookmark) = self.bookmarks.get_mut(index) {
+                    bookmark.note = note;
+                    self.save();
+                }
+            }
+            BookmarksMsg::Remove(index) => {
+                if index < self.bookmarks.len() {
+                    self.bookmarks.remove(index);
+                    self.save();
+                    self.changed(&sender);
+                    self.render(&sender);
+                }
+            }
+            BookmarksMsg::Jump(index) => {
+                if let Some(bookmark) = self.bookmarks.get(index) {
+                    let _ = sender.output(BookmarksOutput::SeekTo(bookmark.position));
+                }
+            }
+            BookmarksMsg::Import => {
+                let dialog = rfd::AsyncFileDialog::new()
+                    .set_title("Import Bookmarks")
+                    .add_filter("Bookmarks", &["json", "csv", "ffmeta", "ffmetadata", "txt"])
+                    .pick_file();
+                let Some(file) = dialog.await else {
+                    return;
+                };
+                let path = file.path();
+                let imported = std::fs::read_to_string(path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|text| bookmarks::import(&text, BookmarkFormat::from_path(path)));
+                match imported {
+                    Ok(imported) => {
+                        for bookmark in imported {
+                            if !self.bookmarks.contains(&bookmark) {
+                                self.bookmarks.push(bookmark);
+                            }
+                        }
+                        self.bookmarks.sort_by_key(|b| b.position);
+                        self.save();
+                        self.changed(&sender);
+                        self.render(&sender);
+                    }
+                    Err(e) => eprintln!("Error: Could not import {} ({e})", path.display()),
+                }
+            }
+            BookmarksMsg::Export => {
+                let Some(name) = self.path.as_ref().and_then(|p| p.file_stem()) else {
+                    return;
+                };
+                let dialog = rfd::AsyncFileDialog::new()
+                    .set_title("Export Bookmarks")
+                    .set_file_name(format!(
+                        "{}.{}",
+                        name.to_string_lossy(),
+                        BookmarkFormat::Json.extension()
+                    ))
+                    .add_filter("JSON", &[BookmarkFormat::Json.extension()])
+                    .add_filter("CSV", &[BookmarkFormat::Csv.extension()])
+                    .add_filter("FFmpeg Metadata", &[BookmarkFormat::FfMetadata.extension()])
+                    .save_file();
+                if let Some(file) = dialog.await {
+                    let path = file.path();
+                    let format = BookmarkFormat::from_path(path);
+                    let text = bookmarks::export(&self.bookmarks, format, self.duration);
+                    if let Err(e) = std::fs::write(path, text) {
+                        eprintln!("Error: Could not export {} ({e})", path.display());
+                    }
+                }
+            }
+            BookmarksMsg::Show => {
+                self.visible = true;
+            }
+            BookmarksMsg::Hide => {
+                self.visible = false;
+            }
+        }
+    }
+}
+
+impl BookmarksWindow {
+    fn save(&self) {
+        if let Some(path) = &self.path {
+            bookmarks::store(path, &self.bookmarks);
+        }
+    }
+
+    fn changed(&self, sender: &AsyncComponentSender<Self>) {
+        let marks = self
+            .bookmarks
+            .iter()
+            .map(|b| (b.position, b.name.clone()))
+            .collect();
+        let _ = sender.output(BookmarksOutput::Changed(marks));
+    }
+
+    /// Rebuilds a row for every bookmark, with its name and note editable in place.
+    fn render(&self, sender: &AsyncComponentSender<Self>) {
+        self.list.remove_all();
+        for (index, bookmark) in self.bookmarks.iter().enumerate() {
+            let row = adw::ExpanderRow::builder()
+                .title(timecode::format_precise(bookmark.position))
+                .subtitle(bookmark.name.as_str())
+                // Names are shown as typed rather than as markup
+                .use_markup(false)
+                .build();
+
+            let jump = gtk::Button::builder()
+                .icon_name("media-playback-start-symbolic")
+                .tooltip_text("Go to Bookmark")
+                .valign(gtk::Align::Center)
+                .css_classes(["flat"])
+                .build();
+            jump.connect_clicked(clone!(
+                #[strong]
+                sender,
+                move |_| sender.input(BookmarksMsg::Jump(index))
+            ));
+            row.add_prefix(&jump);
+
+            let remove = gtk::Button::builder()
+                .icon_name("user-trash-symbolic")
+                .tooltip_text("Remove Bookmark")
+                .valign(gtk::Align::Center)
+                .css_classes(["flat"])
+                .build();
+            remove.connect_clicked(clone!(
+                #[strong]
+                sender,
+                move |_| sender.input(BookmarksMsg::Remove(index))
+            ));
+            row.add_suffix(&remove);
+
+            let name = adw::EntryRow::builder()
+                .title("Name")
+                .text(bookmark.name.as_str())
+                .build();
+            name.connect_changed(clone!(
+                #[strong]
+                sender,
+                #[weak]
+                row,
+                move |entry| {
+                    row.set_subtitle(&entry.text());
+                    sender.input(BookmarksMsg::Rename(index, entry.text().into()));
+                }
+            ));
+            row.add_row(&name);
+
+            let note = adw::EntryRow::builder()
+                .title("Note")
+                .text(bookmark.note.as_str())
+                .build();
+            note.connect_changed(clone!(
+                #[strong]
+                sender,
+                move |entry| sender.input(BookmarksMsg::SetNote(index, entry.text().into()))
+            ));
+            row.add_row(&note);
+
+            self.list.append(&row);
+        }
+    }
+}
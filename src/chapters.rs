@@ -0,0 +1,217 @@
+use std::fmt::Write as _;
+use std::path::Path;
+
+use ffmpeg_next::{codec, encoder, format, media, rescale, Error, Rational, Rescale};
+
+use crate::ffmetadata::{Chapter, Metadata};
+use crate::timecode;
+
+/// Chapter file formats understood by muxers and editing tools.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum ChapterFormat {
+    /// ffmpeg's metadata format, for `-map_chapters`
+    #[default]
+    FfMetadata,
+    /// `CHAPTER01=` lines, as read by mkvmerge and most muxing GUIs
+    Ogm,
+    /// Matroska chapter XML, for mkvmerge and mkvpropedit
+    MatroskaXml,
+}
+
+impl ChapterFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::FfMetadata => "ffmeta",
+            Self::Ogm => "txt",
+            Self::MatroskaXml => "xml",
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("txt") => Self::Ogm,
+            Some("xml") => Self::MatroskaXml,
+            _ => Self::FfMetadata,
+        }
+    }
+}
+
+/// Returns the title of a chapter, or its number when it has none.
+pub fn title(chapter: &Chapter, index: usize) -> String {
+    chapter
+        .tag("title")
+        .filter(|title| !title.is_empty())
+        .map_or_else(|| format!("Chapter {}", index + 1), String::from)
+}
+
+/// Reads the chapters of `location`, with all their tags. This blocks, so it should be run off
+/// the main thread.
+pub fn read(location: &Path) -> Result<Vec<Chapter>, Error> {
+    ffmpeg_next::init()?;
+    let input = format::input(location)?;
+    let chapters = input
+        .chapters()
+        .map(|chapter| Chapter {
+            start: chapter
+                .start()
+                .rescale(chapter.time_base(), rescale::TIME_BASE),
+            end: chapter
+                .end()
+                .rescale(chapter.time_base(), rescale::TIME_BASE),
+            tags: chapter
+                .metadata()
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+        })
+        .collect();
+    Ok(chapters)
+}
+
+/// Sorts chapters and makes each one last until the next starts, and the last one until
+/// `duration` when it is known.
+pub fn with_ends(chapters: &[Chapter], duration: i64) -> Vec<Chapter> {
+    let mut chapters = chapters.to_vec();
+    chapters.sort_by_key(|c| c.start);
+    let starts = chapters.iter().skip(1).map(|c| c.start).collect::<Vec<_>>();
+    for (i, chapter) in chapters.iter_mut().enumerate() {
+        chapter.end = match starts.get(i) {
+            Some(next) => *next,
+            None if duration > chapter.start => duration,
+            None => chapter.end.max(chapter.start),
+        };
+    }
+    chapters
+}
+
+/// Formats a position as HH:MM:SS.nnnnnnnnn, as Matroska chapter files expect.
+fn format_nanos(position: i64) -> String {
+    let position = position.max(0);
+    let seconds = position / 1_000_000;
+    format!(
+        "{:02}:{:02}:{:02}.{:06}000",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60,
+        position % 1_000_000
+    )
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Writes chapters in `format`. Only titles are kept, except in ffmpeg metadata files.
+pub fn export(chapters: &[Chapter], format: ChapterFormat) -> String {
+    match format {
+        ChapterFormat::FfMetadata => Metadata {
+            tags: Vec::new(),
+            chapters: chapters.to_vec(),
+        }
+        .write(),
+        ChapterFormat::Ogm => {
+            let mut text = String::new();
+            for (i, chapter) in chapters.iter().enumerate() {
+                let number = i + 1;
+                let start = timecode::format_precise(chapter.start);
+                let _ = writeln!(text, "CHAPTER{number:02}={start}");
+                let _ = writeln!(text, "CHAPTER{number:02}NAME={}", title(chapter, i));
+            }
+            text
+        }
+        ChapterFormat::MatroskaXml => {
+            let mut text = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+            text += "<!DOCTYPE Chapters SYSTEM \"matroskachapters.dtd\">\n";
+            text += "<Chapters>\n  <EditionEntry>\n";
+            for (i, chapter) in chapters.iter().enumerate() {
+                text += "    <ChapterAtom>\n";
+                let _ = writeln!(
+                    text,
+                    "      <ChapterTimeStart>{}</ChapterTimeStart>",
+                    format_nanos(chapter.start)
+                );
+                let _ = writeln!(
+                    text,
+                    "      <ChapterTimeEnd>{}</ChapterTimeEnd>",
+                    format_nanos(chapter.end)
+                );
+                text += "      <ChapterDisplay>\n";
+                let _ = writeln!(
+                    text,
+                    "        <ChapterString>{}</ChapterString>",
+                    xml_escape(&title(chapter, i))
+                );
+                text += "        <ChapterLanguage>und</ChapterLanguage>\n";
+                text += "      </ChapterDisplay>\n    </ChapterAtom>\n";
+            }
+            text + "  </EditionEntry>\n</Chapters>\n"
+        }
+    }
+}
+
+/// Copies every audio, video, subtitle and attachment stream of `input` into `output` without
+/// re-encoding, replacing the chapters with `chapters`. The container is chosen from the
+/// output's extension. This blocks, so it should be run off the main thread.
+pub fn remux(input: &Path, output: &Path, chapters: &[Chapter]) -> Result<(), Error> {
+    ffmpeg_next::init()?;
+    let mut input = format::input(input)?;
+    let mut output = format::output(output)?;
+
+    // Output stream of each input stream, if it is copied
+    let mut mapping = vec![None; input.nb_streams() as usize];
+    let mut time_bases = vec![Rational(0, 1); input.nb_streams() as usize];
+    for stream in input.streams() {
+        let medium = stream.parameters().medium();
+        if !matches!(
+            medium,
+            media::Type::Audio
+                | media::Type::Video
+                | media::Type::Subtitle
+                | media::Type::Attachment
+        ) {
+            continue;
+        }
+        let mut copy = output.add_stream(encoder::find(codec::Id::None))?;
+        copy.set_parameters(stream.parameters());
+        copy.set_metadata(stream.metadata().to_owned());
+        // Tags of one container can be invalid in another, and ffmpeg-next has no setter for
+        // them or for the default and forced flags
+        unsafe {
+            (*copy.parameters().as_mut_ptr()).codec_tag = 0;
+            (*copy.as_mut_ptr()).disposition = stream.disposition().bits();
+        }
+        mapping[stream.index()] = Some(copy.index());
+        time_bases[stream.index()] = stream.time_base();
+    }
+
+    output.set_metadata(input.metadata().to_owned());
+    for (id, chapter) in chapters.iter().enumerate() {
+        let mut added = output.add_chapter(
+            id as i64 + 1,
+            rescale::TIME_BASE,
+            chapter.start,
+            chapter.end.max(chapter.start),
+            chapter.tag("title").unwrap_or_default(),
+        )?;
+        for (key, value) in &chapter.tags {
+            if !key.eq_ignore_ascii_case("title") {
+                added.set_metadata(key, value);
+            }
+        }
+    }
+    output.write_header()?;
+
+    for (stream, mut packet) in input.packets() {
+        let Some(index) = mapping[stream.index()] else {
+            continue;
+        };
+        let time_base = output.stream(index).unwrap().time_base();
+        packet.rescale_ts(time_bases[stream.index()], time_base);
+        packet.set_position(-1);
+        packet.set_stream(index);
+        packet.write_interleaved(&mut output)?;
+    }
+    output.write_trailer()
+}
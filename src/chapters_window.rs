@@ -0,0 +1,442 @@
+use std::path::PathBuf;
+
+use adw::prelude::*;
+use gtk::glib::clone;
+use relm4::{
+    actions::{RelmAction, RelmActionGroup},
+    prelude::*,
+};
+
+use crate::chapters::{self, ChapterFormat};
+use crate::ffmetadata::Chapter;
+use crate::{playlist, timecode};
+
+relm4::new_action_group!(ChaptersActionGroup, "chapters");
+relm4::new_stateless_action!(SaveChapters, ChaptersActionGroup, "save");
+relm4::new_stateless_action!(ExportChapters, ChaptersActionGroup, "export");
+
+/// Edit waiting for the player's position
+#[derive(Debug, Clone, Copy)]
+enum Pending {
+    Add,
+    Move(usize),
+}
+
+pub struct ChaptersWindow {
+    visible: bool,
+    /// File the chapters are read from
+    path: Option<PathBuf>,
+    duration: i64,
+    chapters: Vec<Chapter>,
+    /// Edited since the chapters were read
+    modified: bool,
+    saving: bool,
+    pending: Option<Pending>,
+    list: gtk::ListBox,
+}
+
+#[derive(Debug)]
+pub enum ChaptersMsg {
+    SetFile(PathBuf),
+    SetDuration(i64),
+    /// Adds a chapter at the playhead
+    Add,
+    /// Moves a chapter to the playhead
+    Move(usize),
+    /// Position of the playhead, reported by the player
+    Playhead(i64),
+    Rename(usize, String),
+    Remove(usize),
+    Jump(usize),
+    /// Asks where to write a copy of the file with the edited chapters
+    Save,
+    Export,
+    Show,
+    Hide,
+}
+
+#[derive(Debug)]
+pub enum ChaptersOutput {
+    /// Asks for the position of the playhead
+    Playhead,
+    SeekTo(i64),
+}
+
+#[derive(Debug)]
+pub enum ChaptersCommand {
+    Read(PathBuf, Result<Vec<Chapter>, ffmpeg_next::Error>),
+    Saved(PathBuf, Result<(), ffmpeg_next::Error>),
+}
+
+#[relm4::component(async, pub)]
+impl AsyncComponent for ChaptersWindow {
+    type Init = adw::Window;
+    type Input = ChaptersMsg;
+    type Output = ChaptersOutput;
+    type CommandOutput = ChaptersCommand;
+
+    menu! {
+        file_menu: {
+            section! {
+                "Save Copy with Chapters…" => SaveChapters,
+                "Export Chapters…" => ExportChapters,
+            }
+        }
+    }
+
+    view! {
+        #[root]
+        window = adw::Window {
+            set_title: Some("Chapters"),
+            set_default_size: (420, 480),
+            #[watch]
+            set_visible: model.visible,
+            gtk::Box {
+                set_orientation: gtk::Orientation::Vertical,
+                adw::HeaderBar {
+                    #[wrap(Some)]
+                    set_title_widget = &adw::WindowTitle {
+                        set_title: "Chapters",
+                        #[watch]
+                        set_subtitle: model.status(),
+                    },
+                    pack_start = &gtk::Button {
+                        set_icon_name: "list-add-symbolic",
+                        set_tooltip_text: Some("Add Chapter at Current Position"),
+                        #[watch]
+                        set_sensitive: model.path.is_some(),
+                        connect_clicked[sender] => move |_| {
+                            sender.input(ChaptersMsg::Add);
+                        },
+                    },
+                    pack_end = &gtk::MenuButton {
+                        set_icon_name: "document-save-symbolic",
+                        set_tooltip_text: Some("Save or Export Chapters"),
+                        set_menu_model: Some(&file_menu),
+                        #[watch]
+                        set_sensitive: model.path.is_some() && !model.saving,
+                    },
+                },
+                adw::StatusPage {
+                    set_icon_name: Some("view-list-symbolic"),
+                    set_title: "No Chapters",
+                    set_description: Some("Add chapters at the current position with +"),
+                    set_vexpand: true,
+                    #[watch]
+                    set_visible: model.chapters.is_empty(),
+                },
+                gtk::ScrolledWindow {
+                    set_hscrollbar_policy: gtk::PolicyType::Never,
+                    set_vexpand: true,
+                    #[watch]
+                    set_visible: !model.chapters.is_empty(),
+                    adw::Clamp {
+                        set_margin_all: 12,
+                        #[local_ref]
+                        list -> gtk::ListBox {
+                            set_selection_mode: gtk::SelectionMode::None,
+                            set_valign: gtk::Align::Start,
+                            add_css_class: "boxed-list",
+                        },
+                    },
+                },
+            },
+            connect_close_request[sender] => move |_| {
+                sender.input(ChaptersMsg::Hide);
+                gtk::glib::Propagation::Proceed
+            }
+        }
+    }
+
+    async fn init(
+        init: Self::Init,
+        root: Self::Root,
+        sender: AsyncComponentSender<Self>,
+    ) -> AsyncComponentParts<Self> {
+        let model = Self {
+            visible: false,
+            path: None,
+            duration: 0,
+            chapters: Vec::new(),
+            modified: false,
+            saving: false,
+            pending: None,
+            list: gtk::ListBox::new(),
+        };
+
+        let list = &model.list;
+        let widgets = view_output!();
+        widgets.window.set_transient_for(Some(&init));
+
+        let mut group = RelmActionGroup::<ChaptersActionGroup>::new();
+        group.add_action::<SaveChapters>(RelmAction::new_stateless(clone!(
+            #[strong]
+            sender,
+            move |_| {
+                sender.input(ChaptersMsg::Save);
+            }
+        )));
+        group.add_action::<ExportChapters>(RelmAction::new_stateless(clone!(
+            #[strong]
+            sender,
+            move |_| {
+                sender.input(ChaptersMsg::Export);
+            }
+        )));
+        group.register_for_widget(&widgets.window);
+
+        AsyncComponentParts { model, widgets }
+    }
+
+    async fn update(
+        &mut self,
+        msg: Self::Input,
+        sender: AsyncComponentSender<Self>,
+        _root: &Self::Root,
+    ) {
+        match msg {
+            ChaptersMsg::SetFile(path) => {
+                self.path = Some(path.clone());
+                self.duration = 0;
+                self.chapters.clear();
+                self.modified = false;
+                self.pending = None;
+                self.render(&sender);
+                sender.spawn_oneshot_command(move || {
+                    let chapters = chapters::read(&path);
+                    ChaptersCommand::Read(path, chapters)
+                });
+            }
+            ChaptersMsg::SetDuration(duration) => {
+                self.duration = duration;
+            }
+            ChaptersMsg::Add => {
+                self.pending = Some(Pending::Add);
+                let _ = sender.output(ChaptersOutput::Playhead);
+            }
+            ChaptersMsg::Move(index) => {
+                self.pending = Some(Pending::Move(index));
+                let _ = sender.output(ChaptersOutput::Playhead);
+            }
+            ChaptersMsg::Playhead(position) => {
+                let pending = self.pending.take();
+                if self.path.is_none() || self.chapters.iter().any(|c| c.start == position) {
+                    return;
+                }
+                match pending {
+                    Some(Pending::Add) => {
+                        let mut chapter = Chapter {
+                            start: position,
+                            end: position,
+                            tags: Vec::new(),
+                        };
+                        let title = format!("Chapter {}", self.chapters.len() + 1);
+                        chapter.set_tag("title", &title);
+                        self.chapters.push(chapter);
+                    }
+                    Some(Pending::Move(index)) => match self.chapters.get_mut(index) {
+                        Some(chapter) => chapter.start = position,
+                        None => return,
+                    },
+                    None => return,
+                }
+                self.chapters.sort_by_key(|c| c.start);
+                self.modified = true;
+                self.render(&sender);
+            }
+            // Renaming keeps the rows, so the entry being typed in keeps its focus
+            ChaptersMsg::Rename(index, title) => {
+                if let Some(chapter) = self.chapters.get_mut(index) {
+                    chapter.set_tag("title", &title);
+                    self.modified = true;
+                }
+            }
+            ChaptersMsg::Remove(index) => {
+                if index < self.chapters.len() {
+                    self.chapters.remove(index);
+                    self.modified = true;
+                    self.render(&sender);
+                }
+            }
+            ChaptersMsg::Jump(index) => {
+                if let Some(chapter) = self.chapters.get(index) {
+                    let _ = sender.output(ChaptersOutput::SeekTo(chapter.start));
+                }
+            }
+            ChaptersMsg::Save => {
+                // Network streams can be played but not copied
+                let Some(path) = self.path.clone().filter(|p| !playlist::is_url(p)) else {
+                    return;
+                };
+                let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+                let extension = path.extension().unwrap_or_default().to_string_lossy();
+                let dialog = rfd::AsyncFileDialog::new()
+                    .set_title("Save Copy with Chapters")
+                    .set_file_name(format!("{stem} (chapters).{extension}"))
+                    .save_file();
+                let Some(file) = dialog.await else {
+                    return;
+                };
+                let output = file.path().to_path_buf();
+                if output == path {
+                    eprintln!(
+                        "Error: Could not save {} (it is being read)",
+                        path.display()
+                    );
+                    return;
+                }
+                let chapters = chapters::with_ends(&self.chapters, self.duration);
+                self.saving = true;
+                sender.spawn_oneshot_command(move || {
+                    let saved = chapters::remux(&path, &output, &chapters);
+                    ChaptersCommand::Saved(output, saved)
+                });
+            }
+            ChaptersMsg::Export => {
+                let Some(name) = self.path.as_ref().and_then(|p| p.file_stem()) else {
+                    return;
+                };
+                let dialog = rfd::AsyncFileDialog::new()
+                    .set_title("Export Chapters")
+                    .set_file_name(format!(
+                        "{}.{}",
+                        name.to_string_lossy(),
+                        ChapterFormat::FfMetadata.extension()
+                    ))
+                    .add_filter("FFmpeg Metadata", &[ChapterFormat::FfMetadata.extension()])
+                    .add_filter("OGM Chapters", &[ChapterFormat::Ogm.extension()])
+                    .add_filter("Matroska XML", &[ChapterFormat::MatroskaXml.extension()])
+                    .save_file();
+                if let Some(file) = dialog.await {
+                    let path = file.path();
+                    let chapters = chapters::with_ends(&self.chapters, self.duration);
+                    let text = chapters::export(&chapters, ChapterFormat::from_path(path));
+                    if let Err(e) = std::fs::write(path, text) {
+                        eprintln!("Error: Could not export {} ({e})", path.display());
+                    }
+                }
+            }
+            ChaptersMsg::Show => {
+                self.visible = true;
+            }
+            ChaptersMsg::Hide => {
+                self.visible = false;
+            }
+        }
+    }
+
+    async fn update_cmd(
+        &mut self,
+        msg: Self::CommandOutput,
+        sender: AsyncComponentSender<Self>,
+        _root: &Self::Root,
+    ) {
+        match msg {
+            ChaptersCommand::Read(path, chapters) => {
+                // Chapters of a file that was replaced in the meantime, or edited already, are
+                // discarded
+                if self.path.as_ref() != Some(&path) || self.modified {
+                    return;
+                }
+                match chapters {
+                    Ok(chapters) => {
+                        self.chapters = chapters;
+                        self.chapters.sort_by_key(|c| c.start);
+                        self.render(&sender);
+                    }
+                    Err(e) => eprintln!("Error: Could not read chapters ({e})"),
+                }
+            }
+            ChaptersCommand::Saved(path, saved) => {
+                self.saving = false;
+                match saved {
+                    Ok(()) => self.modified = false,
+                    Err(e) => eprintln!("Error: Could not save {} ({e})", path.display()),
+                }
+            }
+        }
+    }
+}
+
+impl ChaptersWindow {
+    fn status(&self) -> &'static str {
+        if self.saving {
+            "Saving…"
+        } else if self.modified {
+            "Unsaved changes"
+        } else {
+            ""
+        }
+    }
+
+    /// Rebuilds a row for every chapter, with its title editable in place.
+    fn render(&self, sender: &AsyncComponentSender<Self>) {
+        self.list.remove_all();
+        for (index, chapter) in self.chapters.iter().enumerate() {
+            let title = chapters::title(chapter, index);
+            let row = adw::ExpanderRow::builder()
+                .title(timecode::format_precise(chapter.start))
+                .subtitle(title.as_str())
+                // Titles are shown as typed rather than as markup
+                .use_markup(false)
+                .build();
+
+            let jump = gtk::Button::builder()
+                .icon_name("media-playback-start-symbolic")
+                .tooltip_text("Go to Chapter")
+                .valign(gtk::Align::Center)
+                .css_classes(["flat"])
+                .build();
+            jump.connect_clicked(clone!(
+                #[strong]
+                sender,
+                move |_| sender.input(ChaptersMsg::Jump(index))
+            ));
+            row.add_prefix(&jump);
+
+            let move_here = gtk::Button::builder()
+                .icon_name("find-location-symbolic")
+                .tooltip_text("Move to Current Position")
+                .valign(gtk::Align::Center)
+                .css_classes(["flat"])
+                .build();
+            move_here.connect_clicked(clone!(
+                #[strong]
+                sender,
+                move |_| sender.input(ChaptersMsg::Move(index))
+            ));
+            row.add_suffix(&move_here);
+
+            let remove = gtk::Button::builder()
+                .icon_name("user-trash-symbolic")
+                .tooltip_text("Remove Chapter")
+                .valign(gtk::Align::Center)
+                .css_classes(["flat"])
+                .build();
+            remove.connect_clicked(clone!(
+                #[strong]
+                sender,
+                move |_| sender.input(ChaptersMsg::Remove(index))
+            ));
+            row.add_suffix(&remove);
+
+            let name = adw::EntryRow::builder()
+                .title("Title")
+                .text(title.as_str())
+                .build();
+            name.connect_changed(clone!(
+                #[strong]
+                sender,
+                #[weak]
+                row,
+                move |entry| {
+                    row.set_subtitle(&entry.text());
+                    sender.input(ChaptersMsg::Rename(index, entry.text().into()));
+                }
+            ));
+            row.add_row(&name);
+
+            self.list.append(&row);
+        }
+    }
+}
@@ -0,0 +1,1688 @@
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+use std::sync::mpsc::{self, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use ffmpeg_next::{
+    codec, decoder, error::EAGAIN, filter, format, frame, media, packet, rescale, threading,
+    ChannelLayout, Dictionary, Error, Packet, Rational, Rescale,
+};
+
+use crate::playlist;
+use crate::presentation::Orientation;
+use crate::timecode::{self, FrameRate};
+
+/// Decoded video frames kept ahead of the clock
+const VIDEO_QUEUE: usize = 8;
+/// Upper bound on queued video frames while waiting for audio in badly interleaved files
+const VIDEO_QUEUE_MAX: usize = 48;
+/// Decoded audio kept ahead of the clock, in microseconds
+const AUDIO_QUEUE: i64 = 300_000;
+const AUDIO_QUEUE_MAX: i64 = 2_000_000;
+/// Audio needed before playback resumes after running dry, in microseconds
+const AUDIO_PREROLL: i64 = 500_000;
+const RECONNECT_ATTEMPTS: u32 = 5;
+/// Highest sample value the limiter lets through
+const LIMITER_CEILING: f32 = 0.98;
+/// Fraction of the remaining gain reduction recovered per sample
+const LIMITER_RELEASE: f32 = 0.0001;
+/// Most recently played samples kept for the visualizer, mixed to mono
+const SCOPE_SIZE: usize = 2048;
+
+/// A decoded RGBA frame with its presentation time in microseconds.
+pub struct VideoFrame {
+    pub pts: i64,
+    pub width: u32,
+    pub height: u32,
+    pub stride: usize,
+    pub pixel_aspect: f64,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub enum Event {
+    Prepared {
+        has_audio: bool,
+        has_video: bool,
+        seekable: bool,
+        duration: i64,
+        /// Rotation and mirroring from the video stream's display matrix or `rotate` tag
+        orientation: Orientation,
+        /// Channels of the audio stream and of the output device
+        channels: Option<(u16, u16)>,
+        /// Average frame rate of the video stream
+        frame_rate: Option<FrameRate>,
+    },
+    Seeked,
+    Error(String),
+    /// Playback went on differently than requested, such as when a custom filter was dropped
+    Warning(String),
+}
+
+enum Command {
+    Play,
+    Pause,
+    Seek(i64, SeekMode),
+    SetVideoFilters(VideoFilters),
+    SetAudioFilters(AudioFilters),
+}
+
+/// How a seek finds the requested position.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum SeekMode {
+    /// Resume at the keyframe before the position, which is quick but can land seconds early
+    Fast,
+    /// Decode from that keyframe and discard everything before the position
+    #[default]
+    Exact,
+}
+
+impl SeekMode {
+    pub fn parse(id: &str) -> Self {
+        match id {
+            "fast" => Self::Fast,
+            _ => Self::Exact,
+        }
+    }
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            Self::Fast => "fast",
+            Self::Exact => "exact",
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Deinterlace {
+    Off,
+    /// Deinterlace frames flagged as interlaced
+    #[default]
+    Auto,
+    /// Deinterlace every frame, for files that are not flagged correctly
+    Forced,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Deinterlacer {
+    #[default]
+    Yadif,
+    Bwdif,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum FieldOrder {
+    /// Taken from the stream parameters, or from each frame if unknown
+    #[default]
+    Auto,
+    TopFirst,
+    BottomFirst,
+}
+
+/// Picture adjustments, using the ranges of ffmpeg's `eq` and `hue` filters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VideoAdjustments {
+    /// -1 to 1
+    pub brightness: f64,
+    /// 0 to 2
+    pub contrast: f64,
+    /// 0 to 3
+    pub saturation: f64,
+    /// 0.1 to 10
+    pub gamma: f64,
+    /// Hue rotation in degrees
+    pub hue: f64,
+}
+
+impl Default for VideoAdjustments {
+    fn default() -> Self {
+        Self {
+            brightness: 0.0,
+            contrast: 1.0,
+            saturation: 1.0,
+            gamma: 1.0,
+            hue: 0.0,
+        }
+    }
+}
+
+impl VideoAdjustments {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Filters applied to decoded video before it is converted to RGBA.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct VideoFilters {
+    pub deinterlace: Deinterlace,
+    pub deinterlacer: Deinterlacer,
+    pub field_order: FieldOrder,
+    pub adjustments: VideoAdjustments,
+    /// A user supplied filter chain in `-vf` syntax
+    pub custom: String,
+}
+
+impl VideoFilters {
+    /// Returns the filter graph description for a stream with the given field order.
+    fn spec(&self, stream_order: ffmpeg_next::FieldOrder) -> String {
+        let mut filters = Vec::new();
+
+        if self.deinterlace != Deinterlace::Off {
+            let parity = match (self.field_order, stream_order) {
+                (FieldOrder::TopFirst, _) => "tff",
+                (FieldOrder::BottomFirst, _) => "bff",
+                (_, ffmpeg_next::FieldOrder::TT | ffmpeg_next::FieldOrder::TB) => "tff",
+                (_, ffmpeg_next::FieldOrder::BB | ffmpeg_next::FieldOrder::BT) => "bff",
+                _ => "auto",
+            };
+            let deint = match self.deinterlace {
+                Deinterlace::Forced => "all",
+                _ => "interlaced",
+            };
+            let name = match self.deinterlacer {
+                Deinterlacer::Yadif => "yadif",
+                Deinterlacer::Bwdif => "bwdif",
+            };
+            filters.push(format!(
+                "{name}=mode=send_field:parity={parity}:deint={deint}"
+            ));
+        }
+
+        let adjustments = &self.adjustments;
+        if (
+            adjustments.brightness,
+            adjustments.contrast,
+            adjustments.saturation,
+            adjustments.gamma,
+        ) != (0.0, 1.0, 1.0, 1.0)
+        {
+            filters.push(format!(
+                "eq=brightness={}:contrast={}:saturation={}:gamma={}",
+                adjustments.brightness,
+                adjustments.contrast,
+                adjustments.saturation,
+                adjustments.gamma
+            ));
+        }
+        if adjustments.hue != 0.0 {
+            filters.push(format!("hue=h={}", adjustments.hue));
+        }
+
+        if !self.custom.trim().is_empty() {
+            filters.push(self.custom.clone());
+        }
+
+        filters.push(String::from("format=rgba"));
+        filters.join(",")
+    }
+}
+
+/// Centre frequencies of the equalizer bands, in Hz
+pub const EQUALIZER_BANDS: [u32; 10] = [31, 62, 125, 250, 500, 1000, 2000, 4000, 8000, 16000];
+
+/// Graphic equalizer and compressor for the audio output.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct AudioEqualizer {
+    pub enabled: bool,
+    /// Gain of each band in dB, from -12 to 12
+    pub gains: [f64; 10],
+    /// Compresses loud passages so dialog stays audible at low volume
+    pub night_mode: bool,
+}
+
+/// Channel layout audio is mixed to before it reaches the output device.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum OutputLayout {
+    /// Mix straight to the device's layout
+    #[default]
+    Auto,
+    Mono,
+    Stereo,
+    Surround51,
+}
+
+impl OutputLayout {
+    pub fn parse(id: &str) -> Self {
+        match id {
+            "mono" => Self::Mono,
+            "stereo" => Self::Stereo,
+            "5.1" => Self::Surround51,
+            _ => Self::Auto,
+        }
+    }
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            Self::Auto => "auto",
+            Self::Mono => "mono",
+            Self::Stereo => "stereo",
+            Self::Surround51 => "5.1",
+        }
+    }
+}
+
+/// How the left and right channels of a stereo mix are routed.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum ChannelMode {
+    #[default]
+    Normal,
+    /// Play the left channel on both sides
+    Left,
+    /// Play the right channel on both sides
+    Right,
+    Swap,
+}
+
+impl ChannelMode {
+    pub fn parse(id: &str) -> Self {
+        match id {
+            "left" => Self::Left,
+            "right" => Self::Right,
+            "swap" => Self::Swap,
+            _ => Self::Normal,
+        }
+    }
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            Self::Normal => "normal",
+            Self::Left => "left",
+            Self::Right => "right",
+            Self::Swap => "swap",
+        }
+    }
+}
+
+/// Downmixing and channel routing for the audio output.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ChannelRouting {
+    pub layout: OutputLayout,
+    /// Extra level of the centre channel when downmixing, in dB, to make dialog clearer
+    pub center_boost: f64,
+    pub mode: ChannelMode,
+}
+
+impl ChannelRouting {
+    /// Returns the layout mixed to, if it differs from the device's.
+    fn target(&self) -> Option<&'static str> {
+        match (self.layout, self.mode) {
+            (OutputLayout::Auto, ChannelMode::Normal) => None,
+            (OutputLayout::Auto | OutputLayout::Stereo, _) => Some("stereo"),
+            (OutputLayout::Mono, _) => Some("mono"),
+            (OutputLayout::Surround51, _) => Some("5.1"),
+        }
+    }
+
+    /// Centre mix level used by the resampler when downmixing, -3 dB by default.
+    fn center_level(&self) -> f64 {
+        std::f64::consts::FRAC_1_SQRT_2 * 10f64.powf(self.center_boost / 20.0)
+    }
+
+    fn filters(&self, filters: &mut Vec<String>) {
+        let Some(target) = self.target() else {
+            return;
+        };
+        filters.push(format!(
+            "aresample=clev={:.3},aformat=channel_layouts={target}",
+            self.center_level()
+        ));
+        if target == "stereo" {
+            match self.mode {
+                ChannelMode::Normal => {}
+                ChannelMode::Left => filters.push(String::from("pan=stereo|c0=c0|c1=c0")),
+                ChannelMode::Right => filters.push(String::from("pan=stereo|c0=c1|c1=c1")),
+                ChannelMode::Swap => filters.push(String::from("pan=stereo|c0=c1|c1=c0")),
+            }
+        }
+    }
+
+    /// Describes what is sent to a device with `device` channels.
+    pub fn describe(&self, device: u16) -> String {
+        let device = channel_layout_name(device);
+        let mode = match self.mode {
+            _ if self.target() != Some("stereo") => "",
+            ChannelMode::Normal => "",
+            ChannelMode::Left => ", left channel only",
+            ChannelMode::Right => ", right channel only",
+            ChannelMode::Swap => ", channels swapped",
+        };
+        match self.target() {
+            Some(target) if target != device => format!("{target} on a {device} device{mode}"),
+            _ => format!("{device}{mode}"),
+        }
+    }
+}
+
+/// Filters applied to decoded audio before it is resampled for the output device.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct AudioFilters {
+    /// Loudness normalization gain in dB
+    pub gain: f64,
+    pub equalizer: AudioEqualizer,
+    pub routing: ChannelRouting,
+    /// A user supplied filter chain in `-af` syntax
+    pub custom: String,
+}
+
+impl AudioFilters {
+    fn spec(&self, rate: u32, channels: u16) -> String {
+        let mut filters = Vec::new();
+
+        if self.gain != 0.0 {
+            filters.push(format!("volume={:.2}dB", self.gain));
+        }
+
+        self.routing.filters(&mut filters);
+
+        let equalizer = &self.equalizer;
+        if equalizer.enabled {
+            for (frequency, gain) in EQUALIZER_BANDS.iter().zip(equalizer.gains) {
+                if gain != 0.0 {
+                    filters.push(format!("equalizer=f={frequency}:t=o:w=1:g={gain}"));
+                }
+            }
+        }
+        if equalizer.night_mode {
+            filters.push(String::from(
+                "acompressor=threshold=-30dB:ratio=6:attack=10:release=250:makeup=8dB",
+            ));
+        }
+
+        if !self.custom.trim().is_empty() {
+            filters.push(self.custom.clone());
+        }
+
+        filters.push(format!(
+            "aresample={rate}:clev={:.3},aformat=sample_fmts=flt:channel_layouts={}",
+            self.routing.center_level(),
+            channel_layout_name(channels),
+        ));
+        filters.join(",")
+    }
+}
+
+/// Builds a graph from `spec` between the buffer source `filter` and `sink`.
+pub fn build_graph(
+    source: &str,
+    args: &str,
+    sink: &str,
+    spec: &str,
+) -> Result<filter::Graph, Error> {
+    let mut graph = filter::Graph::new();
+    graph.add(&filter::find(source).unwrap(), "in", args)?;
+    graph.add(&filter::find(sink).unwrap(), "out", "")?;
+    graph.output("in", 0)?.input("out", 0)?.parse(spec)?;
+    graph.validate()?;
+    Ok(graph)
+}
+
+/// Checks that a `-vf` style filter chain can be built, so mistakes are reported before
+/// playback uses it.
+pub fn validate_video_filter(spec: &str) -> Result<(), Error> {
+    if spec.trim().is_empty() {
+        return Ok(());
+    }
+    ffmpeg_next::init()?;
+    build_graph(
+        "buffer",
+        "video_size=64x64:pix_fmt=yuv420p:time_base=1/25:pixel_aspect=1/1",
+        "buffersink",
+        spec,
+    )
+    .map(|_| ())
+}
+
+/// Checks that an `-af` style filter chain can be built.
+pub fn validate_audio_filter(spec: &str) -> Result<(), Error> {
+    if spec.trim().is_empty() {
+        return Ok(());
+    }
+    ffmpeg_next::init()?;
+    build_graph(
+        "abuffer",
+        "time_base=1/48000:sample_rate=48000:sample_fmt=fltp:channel_layout=stereo",
+        "abuffersink",
+        spec,
+    )
+    .map(|_| ())
+}
+
+#[derive(Default)]
+struct Clock {
+    position: i64,
+    anchor: Option<Instant>,
+}
+
+impl Clock {
+    fn time(&self) -> i64 {
+        self.position + self.anchor.map_or(0, |a| a.elapsed().as_micros() as i64)
+    }
+
+    fn set(&mut self, position: i64) {
+        self.position = position;
+        if self.anchor.is_some() {
+            self.anchor = Some(Instant::now());
+        }
+    }
+
+    fn start(&mut self) {
+        if self.anchor.is_none() {
+            self.anchor = Some(Instant::now());
+        }
+    }
+
+    fn stop(&mut self) {
+        self.position = self.time();
+        self.anchor = None;
+    }
+}
+
+#[derive(Default)]
+struct AudioBuffer {
+    samples: VecDeque<f32>,
+    /// Presentation time of the first queued sample
+    pts: i64,
+    rate: u32,
+    channels: u16,
+    volume: f32,
+    /// Gain of the limiter that keeps boosted audio from clipping
+    limiter: f32,
+    /// Last played samples before the volume is applied, mixed to mono
+    scope: VecDeque<f32>,
+}
+
+impl AudioBuffer {
+    fn duration(&self) -> i64 {
+        if self.rate == 0 || self.channels == 0 {
+            return 0;
+        }
+        (self.samples.len() / self.channels as usize) as i64 * 1_000_000 / self.rate as i64
+    }
+
+    /// Drops up to `duration` microseconds from the front of the queue.
+    fn skip(&mut self, duration: i64) {
+        if self.rate == 0 || self.channels == 0 || duration <= 0 {
+            return;
+        }
+        let channels = self.channels as usize;
+        let frames = (duration * i64::from(self.rate) / 1_000_000) as usize;
+        let frames = frames.min(self.samples.len() / channels);
+        self.samples.drain(..frames * channels);
+        self.pts += frames as i64 * 1_000_000 / i64::from(self.rate);
+    }
+}
+
+#[derive(Default)]
+struct Shared {
+    clock: Mutex<Clock>,
+    audio: Mutex<AudioBuffer>,
+    video: Mutex<VecDeque<VideoFrame>>,
+    events: Mutex<Vec<Event>>,
+    playing: AtomicBool,
+    buffering: AtomicBool,
+    eof: AtomicBool,
+    has_audio: AtomicBool,
+    /// How much later audio is heard than the video it belongs to, in microseconds. Queued
+    /// audio is timed on the clock, which follows the video, so this is added to its timestamps.
+    audio_delay: AtomicI64,
+}
+
+impl Shared {
+    fn push_event(&self, event: Event) {
+        self.events.lock().unwrap().push(event);
+    }
+
+    /// Audio output callback, which also drives the clock while audio is playing.
+    fn fill(&self, data: &mut [f32]) {
+        let mut guard = self.audio.lock().unwrap();
+        let audio = &mut *guard;
+        let running =
+            self.playing.load(Ordering::Relaxed) && !self.buffering.load(Ordering::Relaxed);
+        let channels = audio.channels.max(1) as usize;
+        // Silence plays until the first queued sample is due, as it does while audio is delayed.
+        // The clock was last set to the end of the previous buffer, where this one starts.
+        let start = self.clock.lock().unwrap().position;
+        let gap = if running && !audio.samples.is_empty() && audio.rate > 0 {
+            let early = audio.pts - start;
+            let frames = (early.max(0) * i64::from(audio.rate) / 1_000_000) as usize;
+            (frames * channels).min(data.len() / channels * channels)
+        } else {
+            0
+        };
+        let n = if running {
+            (data.len() - gap).min(audio.samples.len())
+        } else {
+            0
+        };
+
+        let volume = audio.volume;
+        let mut gain = audio.limiter;
+        let mut mix = 0.0;
+        data[..gap].fill(0.0);
+        let played = data[gap..].iter_mut().zip(audio.samples.drain(..n));
+        for (i, (out, sample)) in played.enumerate() {
+            mix += sample;
+            if (i + 1) % channels == 0 {
+                audio.scope.push_back(mix / channels as f32);
+                mix = 0.0;
+            }
+            let value = sample * volume;
+            if volume > 1.0 {
+                // Duck instantly on peaks and recover slowly afterwards
+                if value.abs() * gain > LIMITER_CEILING {
+                    gain = LIMITER_CEILING / value.abs();
+                }
+                *out = value * gain;
+                gain += (1.0 - gain) * LIMITER_RELEASE;
+            } else {
+                *out = value;
+            }
+        }
+        audio.limiter = gain;
+        data[gap + n..].fill(0.0);
+        let excess = audio.scope.len().saturating_sub(SCOPE_SIZE);
+        audio.scope.drain(..excess);
+
+        if n > 0 {
+            let frames = (n / channels) as i64;
+            audio.pts += frames * 1_000_000 / audio.rate as i64;
+            self.clock.lock().unwrap().set(audio.pts);
+        } else if gap > 0 {
+            let end = start + (gap / channels) as i64 * 1_000_000 / audio.rate as i64;
+            self.clock.lock().unwrap().set(end);
+        }
+    }
+
+    fn set_audio_delay(&self, delay: i64) {
+        let mut audio = self.audio.lock().unwrap();
+        let change = delay - self.audio_delay.swap(delay, Ordering::Relaxed);
+        // Queued audio waits for longer, or skips ahead to play early
+        audio.pts += change;
+        audio.skip(-change);
+    }
+
+    fn clear(&self) {
+        self.video.lock().unwrap().clear();
+        let mut audio = self.audio.lock().unwrap();
+        audio.samples.clear();
+        audio.scope.clear();
+    }
+}
+
+/// Plays a file or network stream with ffmpeg on a background thread. Video frames are queued
+/// for the caller to present, audio is sent straight to the default output device.
+pub struct Decoder {
+    commands: mpsc::Sender<Command>,
+    shared: Arc<Shared>,
+}
+
+impl Decoder {
+    pub fn open(location: &Path) -> Self {
+        let (commands, receiver) = mpsc::channel();
+        let shared = Arc::new(Shared::default());
+        {
+            let mut audio = shared.audio.lock().unwrap();
+            audio.volume = 1.0;
+            audio.limiter = 1.0;
+        }
+
+        let location = location.to_path_buf();
+        let thread_shared = shared.clone();
+        thread::spawn(
+            move || match Worker::new(location, thread_shared.clone(), receiver) {
+                Ok(worker) => worker.run(),
+                Err(e) => thread_shared.push_event(Event::Error(e.to_string())),
+            },
+        );
+
+        Self { commands, shared }
+    }
+
+    pub fn play(&self) {
+        let _ = self.commands.send(Command::Play);
+    }
+
+    pub fn pause(&self) {
+        let _ = self.commands.send(Command::Pause);
+    }
+
+    pub fn seek(&self, timestamp: i64, mode: SeekMode) {
+        let _ = self.commands.send(Command::Seek(timestamp, mode));
+    }
+
+    pub fn set_video_filters(&self, filters: VideoFilters) {
+        let _ = self.commands.send(Command::SetVideoFilters(filters));
+    }
+
+    pub fn set_audio_filters(&self, filters: AudioFilters) {
+        let _ = self.commands.send(Command::SetAudioFilters(filters));
+    }
+
+    /// Sets the output gain, where values above 1 boost the audio through a limiter.
+    pub fn set_volume(&self, volume: f64) {
+        self.shared.audio.lock().unwrap().volume = volume as f32;
+    }
+
+    /// Delays audio relative to video, or plays it early for negative values. Positions stay
+    /// those of the video.
+    pub fn set_audio_delay(&self, delay: i64) {
+        self.shared.set_audio_delay(delay);
+    }
+
+    /// Current playback position in microseconds.
+    pub fn position(&self) -> i64 {
+        self.shared.clock.lock().unwrap().time()
+    }
+
+    /// Returns the most recently played audio, mixed to mono, with its sample rate.
+    pub fn scope(&self) -> (Vec<f32>, u32) {
+        let audio = self.shared.audio.lock().unwrap();
+        (audio.scope.iter().copied().collect(), audio.rate)
+    }
+
+    pub fn events(&self) -> Vec<Event> {
+        std::mem::take(&mut *self.shared.events.lock().unwrap())
+    }
+
+    /// True once the end of input was reached and everything queued has been presented.
+    pub fn is_finished(&self) -> bool {
+        self.shared.eof.load(Ordering::Relaxed)
+            && self.shared.video.lock().unwrap().is_empty()
+            && self.shared.audio.lock().unwrap().samples.is_empty()
+    }
+
+    /// Pauses the clock while the queues run dry and resumes it once enough has been decoded
+    /// again. Returns whether playback is currently buffering.
+    pub fn sync(&self) -> bool {
+        let shared = &self.shared;
+        let eof = shared.eof.load(Ordering::Relaxed);
+        let (queued, enough) = if shared.has_audio.load(Ordering::Relaxed) {
+            let audio = shared.audio.lock().unwrap().duration();
+            (audio > 0, audio >= AUDIO_PREROLL)
+        } else {
+            let video = shared.video.lock().unwrap().len();
+            (video > 0, video >= VIDEO_QUEUE / 2)
+        };
+
+        let buffering = shared.buffering.load(Ordering::Relaxed);
+        if !buffering && !eof && !queued && shared.playing.load(Ordering::Relaxed) {
+            shared.buffering.store(true, Ordering::Relaxed);
+            shared.clock.lock().unwrap().stop();
+        } else if buffering && (eof || enough) {
+            shared.buffering.store(false, Ordering::Relaxed);
+            if shared.playing.load(Ordering::Relaxed) {
+                shared.clock.lock().unwrap().start();
+            }
+        }
+
+        shared.buffering.load(Ordering::Relaxed)
+    }
+
+    /// Removes all frames that are due and returns the most recent one.
+    pub fn take_frame(&self) -> Option<VideoFrame> {
+        let position = self.position();
+        let mut video = self.shared.video.lock().unwrap();
+        let mut frame = None;
+        while video.front().is_some_and(|f| f.pts <= position) {
+            frame = video.pop_front();
+        }
+        frame
+    }
+}
+
+/// Returns the best video stream, leaving out cover art embedded in audio files.
+pub fn best_video(input: &format::context::Input) -> Option<ffmpeg_next::Stream<'_>> {
+    input
+        .streams()
+        .best(media::Type::Video)
+        .filter(|s| !is_cover_art(s))
+}
+
+/// True for attached pictures, such as the front cover of an MP3 or FLAC file.
+pub fn is_cover_art(stream: &ffmpeg_next::Stream) -> bool {
+    stream
+        .disposition()
+        .contains(format::stream::Disposition::ATTACHED_PIC)
+}
+
+fn open_input(location: &Path) -> Result<format::context::Input, Error> {
+    let mut options = Dictionary::new();
+    if playlist::is_url(location) {
+        options.set("reconnect", "1");
+        options.set("reconnect_streamed", "1");
+        options.set("reconnect_on_network_error", "1");
+        options.set("reconnect_delay_max", "10");
+        options.set("rw_timeout", "10000000");
+        options.set("rtsp_transport", "tcp");
+    }
+    format::input_with_dictionary(location, options)
+}
+
+/// Reads the orientation of a video stream from its display matrix, falling back to the
+/// `rotate` tag written by older muxers.
+pub fn stream_orientation(stream: &ffmpeg_next::Stream) -> Orientation {
+    let matrix = stream
+        .side_data()
+        .find(|d| d.kind() == packet::side_data::Type::DisplayMatrix)
+        .filter(|d| d.data().len() >= 36)
+        .map(|d| {
+            let mut matrix = [0.0; 9];
+            for (value, bytes) in matrix.iter_mut().zip(d.data().chunks_exact(4)) {
+                *value = i32::from_ne_bytes(bytes.try_into().unwrap()) as f64 / 65536.0;
+            }
+            matrix
+        });
+
+    match matrix {
+        Some(m) => {
+            // Same as av_display_rotation_get, which is counterclockwise
+            let scale_x = m[0].hypot(m[3]);
+            let scale_y = m[1].hypot(m[4]);
+            if scale_x == 0.0 || scale_y == 0.0 {
+                return Orientation::default();
+            }
+            let counterclockwise = -(m[1] / scale_y).atan2(m[0] / scale_x).to_degrees();
+            let orientation = Orientation::rotated(-counterclockwise.round() as i64);
+            // A negative determinant means the picture is mirrored
+            if m[0] * m[4] - m[1] * m[3] < 0.0 {
+                Orientation::FLIP_HORIZONTAL.then(orientation)
+            } else {
+                orientation
+            }
+        }
+        None => stream
+            .metadata()
+            .get("rotate")
+            .and_then(|r| r.parse::<i64>().ok())
+            .map_or_else(Orientation::default, Orientation::rotated),
+    }
+}
+
+pub fn channel_layout_name(channels: u16) -> String {
+    match channels {
+        1 => String::from("mono"),
+        2 => String::from("stereo"),
+        6 => String::from("5.1"),
+        8 => String::from("7.1"),
+        n => format!("{n}c"),
+    }
+}
+
+fn to_micros(pts: Option<i64>, time_base: Rational) -> i64 {
+    pts.unwrap_or(0).rescale(time_base, rescale::TIME_BASE)
+}
+
+struct VideoTrack {
+    index: usize,
+    time_base: Rational,
+    field_order: ffmpeg_next::FieldOrder,
+    decoder: decoder::Video,
+    filters: VideoFilters,
+    graph: Option<(filter::Graph, (u32, u32, format::Pixel))>,
+    landing: Landing,
+}
+
+impl VideoTrack {
+    fn new(stream: &ffmpeg_next::Stream, filters: VideoFilters) -> Result<Self, Error> {
+        let parameters = stream.parameters();
+        // ffmpeg-next has no accessor for the field order of codec parameters
+        let field_order = unsafe { (*parameters.as_ptr()).field_order }.into();
+        let mut context = codec::Context::from_parameters(parameters)?;
+        context.set_threading(threading::Config::kind(threading::Type::Frame));
+        let mut decoder = context.decoder().video()?;
+        decoder.set_packet_time_base(stream.time_base());
+
+        Ok(Self {
+            index: stream.index(),
+            time_base: stream.time_base(),
+            field_order,
+            decoder,
+            filters,
+            graph: None,
+            landing: Landing::default(),
+        })
+    }
+
+    fn graph(&self, frame: &frame::Video) -> Result<filter::Graph, Error> {
+        let aspect = frame.aspect_ratio();
+        let args = format!(
+            "video_size={}x{}:pix_fmt={}:time_base={}:pixel_aspect={}",
+            frame.width(),
+            frame.height(),
+            frame.format().descriptor().map_or("yuv420p", |d| d.name()),
+            self.time_base,
+            if aspect.numerator() > 0 {
+                aspect
+            } else {
+                Rational::new(1, 1)
+            },
+        );
+
+        build_graph(
+            "buffer",
+            &args,
+            "buffersink",
+            &self.filters.spec(self.field_order),
+        )
+    }
+
+    fn decode(&mut self, packet: Option<&Packet>, start: i64, shared: &Shared) {
+        let result = match packet {
+            Some(packet) => self.decoder.send_packet(packet),
+            None => self.decoder.send_eof(),
+        };
+        if let Err(e) = result {
+            eprintln!("Error: Could not decode video ({e})");
+            return;
+        }
+
+        let mut decoded = frame::Video::empty();
+        while self.decoder.receive_frame(&mut decoded).is_ok() {
+            decoded.set_pts(decoded.timestamp());
+
+            let key = (decoded.width(), decoded.height(), decoded.format());
+            if self.graph.as_ref().map(|(_, k)| *k) != Some(key) {
+                let mut graph = self.graph(&decoded);
+                if let Err(e) = &graph {
+                    if !self.filters.custom.is_empty() {
+                        shared.push_event(Event::Warning(format!(
+                            "Video filter \"{}\" failed ({e})",
+                            self.filters.custom
+                        )));
+                        self.filters.custom.clear();
+                        graph = self.graph(&decoded);
+                    }
+                }
+                match graph {
+                    Ok(graph) => self.graph = Some((graph, key)),
+                    Err(e) => {
+                        eprintln!("Error: Could not create video filter ({e})");
+                        return;
+                    }
+                }
+            }
+            let (graph, _) = self.graph.as_mut().unwrap();
+
+            if graph.get("in").unwrap().source().add(&decoded).is_err() {
+                continue;
+            }
+
+            let mut sink = graph.get("out").unwrap();
+            let mut sink = sink.sink();
+            let time_base = sink.time_base();
+            let mut filtered = frame::Video::empty();
+            while sink.frame(&mut filtered).is_ok() {
+                let aspect = filtered.aspect_ratio();
+                let frame = VideoFrame {
+                    pts: to_micros(filtered.pts(), time_base) - start,
+                    width: filtered.width(),
+                    height: filtered.height(),
+                    stride: filtered.stride(0),
+                    pixel_aspect: if aspect.numerator() > 0 {
+                        f64::from(aspect)
+                    } else {
+                        1.0
+                    },
+                    data: filtered.data(0).to_vec(),
+                };
+                self.landing.push(frame, shared);
+            }
+        }
+
+        // The seek position is past the last frame, so show that one
+        if packet.is_none() {
+            self.landing.skip_until = None;
+            if let Some(held) = self.landing.held.take() {
+                shared.video.lock().unwrap().push_back(held);
+            }
+        }
+    }
+}
+
+/// Where video resumes after a seek.
+#[derive(Default)]
+struct Landing {
+    /// Position of an exact seek, frames before it are dropped
+    skip_until: Option<i64>,
+    /// Latest frame dropped so far, which is the one showing at the seek position
+    held: Option<VideoFrame>,
+    /// Moves the clock to the next frame, so a fast seek reports the keyframe it landed on
+    snap: bool,
+}
+
+impl Landing {
+    fn push(&mut self, frame: VideoFrame, shared: &Shared) {
+        if let Some(target) = self.skip_until {
+            if frame.pts < target {
+                self.held = Some(frame);
+                return;
+            }
+            self.skip_until = None;
+            if let Some(mut held) = self.held.take() {
+                // The seek position falls between two frames, so the earlier one shows first
+                if frame.pts > target {
+                    held.pts = target;
+                    shared.video.lock().unwrap().push_back(held);
+                }
+            }
+        }
+        if self.snap {
+            self.snap = false;
+            shared.clock.lock().unwrap().set(frame.pts);
+        }
+        shared.video.lock().unwrap().push_back(frame);
+    }
+}
+
+struct AudioTrack {
+    index: usize,
+    time_base: Rational,
+    decoder: decoder::Audio,
+    filters: AudioFilters,
+    graph: Option<(filter::Graph, (u32, u64, format::Sample))>,
+    rate: u32,
+    channels: u16,
+    /// Position of an exact seek, samples before it are dropped
+    skip_until: Option<i64>,
+}
+
+impl AudioTrack {
+    fn new(
+        stream: &ffmpeg_next::Stream,
+        rate: u32,
+        channels: u16,
+        filters: AudioFilters,
+    ) -> Result<Self, Error> {
+        let context = codec::Context::from_parameters(stream.parameters())?;
+        let mut decoder = context.decoder().audio()?;
+        decoder.set_packet_time_base(stream.time_base());
+
+        Ok(Self {
+            index: stream.index(),
+            time_base: stream.time_base(),
+            decoder,
+            filters,
+            graph: None,
+            rate,
+            channels,
+            skip_until: None,
+        })
+    }
+
+    fn graph(&self, frame: &frame::Audio, layout: u64) -> Result<filter::Graph, Error> {
+        let args = format!(
+            "time_base={}:sample_rate={}:sample_fmt={}:channel_layout=0x{:x}",
+            self.time_base,
+            frame.rate(),
+            frame.format().name(),
+            layout,
+        );
+        build_graph(
+            "abuffer",
+            &args,
+            "abuffersink",
+            &self.filters.spec(self.rate, self.channels),
+        )
+    }
+
+    fn decode(&mut self, packet: Option<&Packet>, start: i64, shared: &Shared) {
+        let result = match packet {
+            Some(packet) => self.decoder.send_packet(packet),
+            None => self.decoder.send_eof(),
+        };
+        if let Err(e) = result {
+            eprintln!("Error: Could not decode audio ({e})");
+            return;
+        }
+
+        let mut decoded = frame::Audio::empty();
+        while self.decoder.receive_frame(&mut decoded).is_ok() {
+            decoded.set_pts(decoded.timestamp());
+
+            let layout = match decoded.channel_layout().bits() {
+                0 => ChannelLayout::default(decoded.channels() as i32).bits(),
+                bits => bits,
+            };
+            let key = (decoded.rate(), layout, decoded.format());
+            if self.graph.as_ref().map(|(_, k)| *k) != Some(key) {
+                let mut graph = self.graph(&decoded, layout);
+                if let Err(e) = &graph {
+                    if !self.filters.custom.is_empty() {
+                        shared.push_event(Event::Warning(format!(
+                            "Audio filter \"{}\" failed ({e})",
+                            self.filters.custom
+                        )));
+                        self.filters.custom.clear();
+                        graph = self.graph(&decoded, layout);
+                    }
+                }
+                match graph {
+                    Ok(graph) => self.graph = Some((graph, key)),
+                    Err(e) => {
+                        eprintln!("Error: Could not create audio filter ({e})");
+                        return;
+                    }
+                }
+            }
+            let (graph, _) = self.graph.as_mut().unwrap();
+
+            if graph.get("in").unwrap().source().add(&decoded).is_err() {
+                continue;
+            }
+
+            let mut sink = graph.get("out").unwrap();
+            let mut sink = sink.sink();
+            let time_base = sink.time_base();
+            let mut filtered = frame::Audio::empty();
+            while sink.frame(&mut filtered).is_ok() {
+                let mut pts = to_micros(filtered.pts(), time_base) - start;
+                let mut skipped = 0;
+                if let Some(target) = self.skip_until {
+                    let count = filtered.samples() as i64;
+                    skipped = ((target - pts) * i64::from(self.rate) / 1_000_000).clamp(0, count);
+                    if skipped == count {
+                        continue;
+                    }
+                    pts += skipped * 1_000_000 / i64::from(self.rate);
+                    self.skip_until = None;
+                }
+
+                let len = filtered.samples() * self.channels as usize * 4;
+                let samples = filtered.data(0)[skipped as usize * self.channels as usize * 4..len]
+                    .chunks_exact(4)
+                    .map(|b| f32::from_ne_bytes([b[0], b[1], b[2], b[3]]));
+
+                let delay = shared.audio_delay.load(Ordering::Relaxed);
+                let mut audio = shared.audio.lock().unwrap();
+                let restart = audio.samples.is_empty();
+                if restart {
+                    audio.pts = pts + delay;
+                }
+                audio.samples.extend(samples);
+                if restart && delay < 0 {
+                    // Audio played early starts at the clock rather than holding it back
+                    let behind = shared.clock.lock().unwrap().time() - audio.pts;
+                    audio.skip(behind.min(-delay));
+                }
+            }
+        }
+    }
+}
+
+struct Worker {
+    location: PathBuf,
+    shared: Arc<Shared>,
+    commands: mpsc::Receiver<Command>,
+    input: format::context::Input,
+    video: Option<VideoTrack>,
+    audio: Option<AudioTrack>,
+    /// Kept alive for as long as audio should play
+    output: Option<(cpal::Stream, u32, u16)>,
+    /// Start time of the input in microseconds, subtracted from all timestamps
+    start: i64,
+    live: bool,
+    /// Earliest and latest positions demuxed from a live stream, which bound its seeks since
+    /// the demuxers do not tell how much of the stream is still available
+    window: (i64, i64),
+    video_filters: VideoFilters,
+    audio_filters: AudioFilters,
+}
+
+impl Worker {
+    fn new(
+        location: PathBuf,
+        shared: Arc<Shared>,
+        commands: mpsc::Receiver<Command>,
+    ) -> Result<Self, Error> {
+        ffmpeg_next::init()?;
+        let input = open_input(&location)?;
+
+        let output = audio_output(shared.clone());
+        let mut worker = Self {
+            location,
+            shared,
+            commands,
+            input,
+            video: None,
+            audio: None,
+            output,
+            start: 0,
+            live: false,
+            window: (i64::MAX, i64::MIN),
+            video_filters: VideoFilters::default(),
+            audio_filters: AudioFilters::default(),
+        };
+        worker.open_tracks();
+
+        // Keep the offset across reconnects so live positions stay continuous
+        let streams = worker.input.streams();
+        worker.start = best_video(&worker.input)
+            .or_else(|| streams.best(media::Type::Audio))
+            .filter(|s| s.start_time() != i64::MIN)
+            .map_or(0, |s| {
+                s.start_time().rescale(s.time_base(), rescale::TIME_BASE)
+            });
+
+        let duration = worker.input.duration();
+        worker.live = duration <= 0;
+        let seekable = !worker.live || ["hls", "dash"].contains(&worker.input.format().name());
+
+        let orientation =
+            best_video(&worker.input).map_or_else(Orientation::default, |s| stream_orientation(&s));
+        let frame_rate = best_video(&worker.input).and_then(|s| {
+            let rate = s.avg_frame_rate();
+            FrameRate::new(rate.numerator().into(), rate.denominator().into())
+        });
+
+        worker.shared.push_event(Event::Prepared {
+            has_audio: worker.audio.is_some(),
+            has_video: worker.video.is_some(),
+            seekable,
+            duration: duration.max(0),
+            orientation,
+            channels: worker
+                .audio
+                .as_ref()
+                .map(|audio| (audio.decoder.channels(), audio.channels)),
+            frame_rate,
+        });
+
+        Ok(worker)
+    }
+
+    fn open_tracks(&mut self) {
+        let video = best_video(&self.input);
+        let audio = self.input.streams().best(media::Type::Audio);
+
+        self.video = video.and_then(|s| {
+            VideoTrack::new(&s, self.video_filters.clone())
+                .inspect_err(|e| eprintln!("Error: Could not open video decoder ({e})"))
+                .ok()
+        });
+
+        self.audio = match (audio, &self.output) {
+            (Some(s), Some((_, rate, channels))) => {
+                AudioTrack::new(&s, *rate, *channels, self.audio_filters.clone())
+                    .inspect_err(|e| eprintln!("Error: Could not open audio decoder ({e})"))
+                    .ok()
+            }
+            _ => None,
+        };
+
+        self.shared
+            .has_audio
+            .store(self.audio.is_some(), Ordering::Relaxed);
+    }
+
+    fn run(mut self) {
+        loop {
+            loop {
+                match self.commands.try_recv() {
+                    Ok(command) => self.handle(command),
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => return,
+                }
+            }
+
+            if self.shared.eof.load(Ordering::Relaxed) || self.is_full() {
+                thread::sleep(Duration::from_millis(5));
+                continue;
+            }
+
+            let mut packet = Packet::empty();
+            match packet.read(&mut self.input) {
+                Ok(()) => self.decode(&packet),
+                Err(Error::Eof) => self.finish(),
+                Err(Error::Other { errno: EAGAIN }) => thread::sleep(Duration::from_millis(5)),
+                Err(e) if playlist::is_url(&self.location) => {
+                    if !self.reconnect(e) {
+                        return;
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Error: Could not read packet ({e})");
+                    self.finish();
+                }
+            }
+        }
+    }
+
+    fn handle(&mut self, command: Command) {
+        let shared = &self.shared;
+        match command {
+            Command::Play => {
+                shared.playing.store(true, Ordering::Relaxed);
+                if !shared.buffering.load(Ordering::Relaxed) {
+                    shared.clock.lock().unwrap().start();
+                }
+            }
+            Command::Pause => {
+                shared.playing.store(false, Ordering::Relaxed);
+                shared.clock.lock().unwrap().stop();
+            }
+            Command::Seek(timestamp, mode) => {
+                self.seek(timestamp, mode);
+                self.shared.push_event(Event::Seeked);
+            }
+            Command::SetVideoFilters(filters) => {
+                if let Some(video) = &mut self.video {
+                    video.filters = filters.clone();
+                    video.graph = None;
+                }
+                self.video_filters = filters;
+            }
+            Command::SetAudioFilters(filters) => {
+                if let Some(audio) = &mut self.audio {
+                    audio.filters = filters.clone();
+                    audio.graph = None;
+                }
+                self.audio_filters = filters;
+            }
+        }
+    }
+
+    fn seek(&mut self, timestamp: i64, mode: SeekMode) {
+        let timestamp = if self.live && self.window.0 <= self.window.1 {
+            let clamped = timestamp.clamp(self.window.0, self.window.1);
+            if clamped != timestamp {
+                self.shared.push_event(Event::Warning(format!(
+                    "Only {} to {} of this live stream can be played",
+                    timecode::format_clock(self.window.0),
+                    timecode::format_clock(self.window.1)
+                )));
+            }
+            clamped
+        } else {
+            timestamp.max(0)
+        };
+
+        let target = timestamp + self.start;
+        if let Err(e) = self.input.seek(target, ..target) {
+            eprintln!("Error: Could not seek ({e})");
+            return;
+        }
+
+        let skip_until = (mode == SeekMode::Exact).then_some(timestamp);
+        if let Some(video) = &mut self.video {
+            video.decoder.flush();
+            video.graph = None;
+            video.landing = Landing {
+                skip_until,
+                held: None,
+                snap: mode == SeekMode::Fast,
+            };
+        }
+        if let Some(audio) = &mut self.audio {
+            audio.decoder.flush();
+            audio.graph = None;
+            audio.skip_until = skip_until;
+        }
+
+        self.shared.clear();
+        self.shared.clock.lock().unwrap().set(timestamp);
+        self.shared.eof.store(false, Ordering::Relaxed);
+    }
+
+    fn is_full(&self) -> bool {
+        let video = self.shared.video.lock().unwrap().len();
+        if self.audio.is_none() {
+            return video >= VIDEO_QUEUE;
+        }
+
+        // Delayed audio waits in the queue on top of what is decoded ahead
+        let delay = self.shared.audio_delay.load(Ordering::Relaxed).max(0);
+        let audio = self.shared.audio.lock().unwrap().duration() - delay;
+        (video >= VIDEO_QUEUE && audio >= AUDIO_QUEUE)
+            || video >= VIDEO_QUEUE_MAX
+            || audio >= AUDIO_QUEUE_MAX
+    }
+
+    fn decode(&mut self, packet: &Packet) {
+        let index = packet.stream();
+
+        if self.live {
+            if let Some(stream) = self.input.stream(index) {
+                let position = to_micros(packet.pts(), stream.time_base()) - self.start;
+                self.window = (self.window.0.min(position), self.window.1.max(position));
+            }
+        }
+
+        if let Some(video) = self.video.as_mut().filter(|v| v.index == index) {
+            video.decode(Some(packet), self.start, &self.shared);
+        } else if let Some(audio) = self.audio.as_mut().filter(|a| a.index == index) {
+            audio.decode(Some(packet), self.start, &self.shared);
+        }
+    }
+
+    fn finish(&mut self) {
+        if let Some(video) = &mut self.video {
+            video.decode(None, self.start, &self.shared);
+        }
+        if let Some(audio) = &mut self.audio {
+            audio.decode(None, self.start, &self.shared);
+        }
+        self.shared.eof.store(true, Ordering::Relaxed);
+    }
+
+    /// Reopens a dropped network stream, resuming at the current position unless it is live.
+    fn reconnect(&mut self, error: Error) -> bool {
+        eprintln!("Error: Stream interrupted ({error}), reconnecting");
+        self.shared.buffering.store(true, Ordering::Relaxed);
+        self.shared.clock.lock().unwrap().stop();
+        let position = self.shared.clock.lock().unwrap().time();
+
+        // Commands sent meanwhile are handled once the stream is back
+        let mut queued = Vec::new();
+        for attempt in 0..RECONNECT_ATTEMPTS {
+            thread::sleep(Duration::from_secs(1 << attempt));
+            loop {
+                match self.commands.try_recv() {
+                    Ok(command) => queued.push(command),
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => return false,
+                }
+            }
+
+            match open_input(&self.location) {
+                Ok(input) => {
+                    self.input = input;
+                    self.open_tracks();
+                    self.shared.clear();
+                    if !self.live {
+                        self.seek(position, SeekMode::Exact);
+                    }
+                    for command in queued {
+                        self.handle(command);
+                    }
+                    return true;
+                }
+                Err(e) => eprintln!("Error: Reconnect attempt {} failed ({e})", attempt + 1),
+            }
+        }
+
+        self.shared
+            .push_event(Event::Error(format!("Lost connection ({error})")));
+        false
+    }
+}
+
+fn audio_output(shared: Arc<Shared>) -> Option<(cpal::Stream, u32, u16)> {
+    let device = cpal::default_host().default_output_device()?;
+    let config = device
+        .default_output_config()
+        .inspect_err(|e| eprintln!("Error: No audio output configuration ({e})"))
+        .ok()?
+        .config();
+
+    {
+        let mut audio = shared.audio.lock().unwrap();
+        audio.rate = config.sample_rate.0;
+        audio.channels = config.channels;
+    }
+
+    let stream = device
+        .build_output_stream(
+            &config,
+            move |data: &mut [f32], _| shared.fill(data),
+            |e| eprintln!("Error: Audio output failed ({e})"),
+            None,
+        )
+        .inspect_err(|e| eprintln!("Error: Could not open audio output ({e})"))
+        .ok()?;
+    stream.play().ok()?;
+
+    Some((stream, config.sample_rate.0, config.channels))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    use super::*;
+
+    const RATE: i32 = 25;
+
+    /// Encodes frames `frames` of a 64x48 clip at 25 fps with a keyframe every second, so
+    /// frame `n` starts at `n * 40ms`. The container is chosen from the extension of `path`.
+    fn encode(path: &Path, frames: std::ops::Range<i64>) {
+        ffmpeg_next::init().unwrap();
+        let codec = ffmpeg_next::encoder::find(codec::Id::MPEG4).unwrap();
+        let mut output = format::output(&path).unwrap();
+
+        let mut encoder = codec::Context::new_with_codec(codec)
+            .encoder()
+            .video()
+            .unwrap();
+        encoder.set_width(64);
+        encoder.set_height(48);
+        encoder.set_format(format::Pixel::YUV420P);
+        encoder.set_time_base(Rational(1, RATE));
+        encoder.set_frame_rate(Some(Rational(RATE, 1)));
+        encoder.set_gop(RATE as u32);
+        encoder.set_max_b_frames(0);
+        if output
+            .format()
+            .flags()
+            .contains(format::Flags::GLOBAL_HEADER)
+        {
+            encoder.set_flags(codec::Flags::GLOBAL_HEADER);
+        }
+        let mut encoder = encoder.open_as(codec).unwrap();
+        output.add_stream(codec).unwrap().set_parameters(&encoder);
+        output.write_header().unwrap();
+        let time_base = output.stream(0).unwrap().time_base();
+
+        let mut write = |encoder: &mut ffmpeg_next::encoder::Video| {
+            let mut packet = Packet::empty();
+            while encoder.receive_packet(&mut packet).is_ok() {
+                packet.set_stream(0);
+                packet.rescale_ts(Rational(1, RATE), time_base);
+                packet.write_interleaved(&mut output).unwrap();
+            }
+        };
+        for n in frames {
+            let mut frame = frame::Video::new(format::Pixel::YUV420P, 64, 48);
+            // A different shade for every frame, so none is coded as skipped
+            frame.data_mut(0).fill((n * 2 % 220 + 16) as u8);
+            frame.data_mut(1).fill(128);
+            frame.data_mut(2).fill(128);
+            frame.set_pts(Some(n));
+            encoder.send_frame(&frame).unwrap();
+            write(&mut encoder);
+        }
+        encoder.send_eof().unwrap();
+        write(&mut encoder);
+        output.write_trailer().unwrap();
+    }
+
+    fn fixture(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("simple_video_player-{}-{name}", std::process::id()))
+    }
+
+    /// Waits until `decoder` has handled a seek, then returns the times of the first two frames
+    /// it queued.
+    fn frames_after_seek(decoder: &Decoder) -> (i64, i64) {
+        let mut seeked = false;
+        for _ in 0..500 {
+            seeked |= decoder.events().iter().any(|e| matches!(e, Event::Seeked));
+            let video = decoder.shared.video.lock().unwrap();
+            if seeked && video.len() >= 2 {
+                return (video[0].pts, video[1].pts);
+            }
+            drop(video);
+            thread::sleep(Duration::from_millis(10));
+        }
+        panic!("No frames decoded after seeking");
+    }
+
+    #[test]
+    fn seeks_exactly_or_to_keyframes() {
+        let path = fixture("seek.mkv");
+        encode(&path, 0..4 * i64::from(RATE));
+        let rate = FrameRate::new(RATE.into(), 1).unwrap();
+
+        // Frame 63 starts at 2.52s, after the keyframe at 2s
+        let decoder = Decoder::open(&path);
+        decoder.seek(rate.frame_start(63), SeekMode::Exact);
+        assert_eq!(frames_after_seek(&decoder), (2_520_000, 2_560_000));
+        assert_eq!(decoder.position(), 2_520_000);
+
+        // Between two frames, the earlier one shows from the requested position
+        decoder.seek(2_530_000, SeekMode::Exact);
+        assert_eq!(frames_after_seek(&decoder), (2_530_000, 2_560_000));
+
+        decoder.seek(2_530_000, SeekMode::Fast);
+        assert_eq!(frames_after_seek(&decoder), (2_000_000, 2_040_000));
+        assert_eq!(decoder.position(), 2_000_000);
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    /// Serves `files` over HTTP on a free local port, dropping every connection while `down`
+    /// is set. Returns the address to request them from.
+    fn serve(files: Vec<(String, Vec<u8>)>, down: Arc<AtomicBool>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else {
+                    continue;
+                };
+                if down.load(Ordering::Relaxed) {
+                    continue;
+                }
+
+                let mut request = Vec::new();
+                let mut buffer = [0; 1024];
+                while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                    match stream.read(&mut buffer) {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => request.extend_from_slice(&buffer[..n]),
+                    }
+                }
+                let request = String::from_utf8_lossy(&request);
+                let path = request.split_whitespace().nth(1).unwrap_or_default();
+                let file = files.iter().find(|(name, _)| path == format!("/{name}"));
+                let (status, body) = match file {
+                    Some((_, body)) => ("200 OK", &body[..]),
+                    None => ("404 Not Found", &[][..]),
+                };
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    body.len()
+                );
+                let _ = stream.write_all(body);
+            }
+        });
+        format!("http://{address}")
+    }
+
+    #[test]
+    fn plays_and_reconnects_to_live_hls() {
+        // A live playlist has no end tag, so its duration is unknown
+        let mut playlist = String::from(
+            "#EXTM3U\n#EXT-X-VERSION:3\n#EXT-X-TARGETDURATION:1\n#EXT-X-MEDIA-SEQUENCE:0\n",
+        );
+        let mut files = Vec::new();
+        for segment in 0..3 {
+            let name = format!("segment{segment}.ts");
+            let path = fixture(&name);
+            let first = segment * i64::from(RATE);
+            encode(&path, first..first + i64::from(RATE));
+            playlist += &format!("#EXTINF:1.0,\n{name}\n");
+            files.push((name, std::fs::read(&path).unwrap()));
+            let _ = std::fs::remove_file(path);
+        }
+        files.push((String::from("live.m3u8"), playlist.into_bytes()));
+        let down = Arc::new(AtomicBool::new(false));
+        let url = format!("{}/live.m3u8", serve(files, down.clone()));
+
+        let (commands, receiver) = mpsc::channel();
+        let shared = Arc::new(Shared::default());
+        let mut worker = Worker::new(PathBuf::from(url), shared.clone(), receiver).unwrap();
+        assert!(worker.live);
+        assert!(worker.video.is_some());
+        let events = std::mem::take(&mut *shared.events.lock().unwrap());
+        assert!(matches!(
+            events[..],
+            [Event::Prepared {
+                has_video: true,
+                seekable: true,
+                duration: 0,
+                ..
+            }]
+        ));
+
+        for _ in 0..30 {
+            let mut packet = Packet::empty();
+            packet.read(&mut worker.input).unwrap();
+            worker.decode(&packet);
+        }
+        let (earliest, latest) = worker.window;
+        assert!(earliest <= latest);
+
+        // Seeks past what was demuxed are clamped and reported
+        worker.seek(latest + 60_000_000, SeekMode::Fast);
+        let events = std::mem::take(&mut *shared.events.lock().unwrap());
+        assert!(matches!(events[..], [Event::Warning(_)]));
+
+        // The server is back before the second attempt, and the command sent while it was
+        // down is handled then rather than dropped
+        down.store(true, Ordering::Relaxed);
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(1500));
+            down.store(false, Ordering::Relaxed);
+        });
+        commands.send(Command::Play).unwrap();
+        assert!(worker.reconnect(Error::Eof));
+        assert!(shared.playing.load(Ordering::Relaxed));
+        let mut packet = Packet::empty();
+        assert!(packet.read(&mut worker.input).is_ok());
+    }
+
+    /// Plays audio at 1000 samples a second, one channel, queued from `start`.
+    fn playing_audio(start: i64, samples: usize) -> Shared {
+        let shared = Shared::default();
+        {
+            let mut audio = shared.audio.lock().unwrap();
+            audio.rate = 1000;
+            audio.channels = 1;
+            audio.volume = 1.0;
+            audio.limiter = 1.0;
+            audio.pts = start;
+            audio.samples.extend(std::iter::repeat(1.0).take(samples));
+        }
+        shared.clock.lock().unwrap().set(start);
+        shared.playing.store(true, Ordering::Relaxed);
+        shared
+    }
+
+    #[test]
+    fn delays_audio_without_moving_the_clock() {
+        let shared = playing_audio(0, 500);
+        shared.set_audio_delay(100_000);
+        let mut data = [0.5; 60];
+        shared.fill(&mut data);
+        assert!(data.iter().all(|s| *s == 0.0));
+        assert_eq!(shared.clock.lock().unwrap().position, 60_000);
+        shared.fill(&mut data);
+        assert!(data[..40].iter().all(|s| *s == 0.0));
+        assert!(data[40..].iter().all(|s| *s == 1.0));
+        assert_eq!(shared.clock.lock().unwrap().position, 120_000);
+
+        // Playing early skips what is due before the clock
+        let shared = playing_audio(0, 500);
+        shared.set_audio_delay(-100_000);
+        assert_eq!(shared.audio.lock().unwrap().samples.len(), 400);
+        shared.fill(&mut data);
+        assert!(data.iter().all(|s| *s == 1.0));
+        assert_eq!(shared.clock.lock().unwrap().position, 60_000);
+    }
+}
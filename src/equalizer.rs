@@ -0,0 +1,239 @@
+use adw::prelude::*;
+use gtk::glib;
+use relm4::prelude::*;
+
+use crate::decoder::{AudioEqualizer, EQUALIZER_BANDS};
+
+/// Largest boost or cut of a band, in dB
+const MAX_GAIN: f64 = 12.0;
+
+const PRESETS: [(&str, [f64; 10]); 8] = [
+    ("Flat", [0.0; 10]),
+    (
+        "Bass Boost",
+        [6.0, 5.0, 4.0, 2.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+    ),
+    (
+        "Treble Boost",
+        [0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 2.0, 4.0, 5.0, 6.0],
+    ),
+    (
+        "Vocal",
+        [-2.0, -2.0, -1.0, 1.0, 3.0, 4.0, 3.0, 1.0, 0.0, -1.0],
+    ),
+    ("Rock", [4.0, 3.0, 2.0, 0.0, -1.0, -1.0, 1.0, 2.0, 3.0, 4.0]),
+    (
+        "Pop",
+        [-1.0, 1.0, 3.0, 4.0, 3.0, 0.0, -1.0, -1.0, -1.0, -1.0],
+    ),
+    (
+        "Classical",
+        [4.0, 3.0, 2.0, 1.0, 0.0, 0.0, 0.0, 1.0, 2.0, 3.0],
+    ),
+    (
+        "Loudness",
+        [5.0, 4.0, 1.0, 0.0, -1.0, 0.0, 0.0, 1.0, 3.0, 4.0],
+    ),
+];
+
+fn band_label(frequency: u32) -> String {
+    if frequency >= 1000 {
+        format!("{}k", frequency / 1000)
+    } else {
+        frequency.to_string()
+    }
+}
+
+#[derive(Debug)]
+pub struct EqualizerPopover {
+    equalizer: AudioEqualizer,
+}
+
+#[derive(Debug)]
+pub enum EqualizerMsg {
+    Enable(bool),
+    Gain(usize, f64),
+    Preset(u32),
+    NightMode(bool),
+    Reset,
+}
+
+pub struct EqualizerWidgets {
+    enabled: (gtk::Switch, glib::SignalHandlerId),
+    presets: (gtk::DropDown, glib::SignalHandlerId),
+    bands: Vec<(gtk::Scale, glib::SignalHandlerId)>,
+    night_mode: (gtk::Switch, glib::SignalHandlerId),
+    reset: gtk::Button,
+}
+
+impl SimpleComponent for EqualizerPopover {
+    type Init = AudioEqualizer;
+    type Input = EqualizerMsg;
+    type Output = AudioEqualizer;
+    type Root = gtk::Popover;
+    type Widgets = EqualizerWidgets;
+
+    fn init(
+        init: Self::Init,
+        root: Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let model = Self { equalizer: init };
+
+        let content = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .spacing(6)
+            .margin_top(6)
+            .margin_bottom(6)
+            .margin_start(6)
+            .margin_end(6)
+            .build();
+        root.set_child(Some(&content));
+
+        let header = gtk::Box::builder().spacing(6).build();
+        header.append(
+            &gtk::Label::builder()
+                .label("Equalizer")
+                .halign(gtk::Align::Start)
+                .hexpand(true)
+                .build(),
+        );
+        let presets = gtk::DropDown::from_strings(&PRESETS.map(|(name, _)| name));
+        let presets_handler = presets.connect_selected_notify(clone_sender(&sender, |d| {
+            EqualizerMsg::Preset(d.selected())
+        }));
+        header.append(&presets);
+        let enabled = gtk::Switch::builder().valign(gtk::Align::Center).build();
+        let enabled_handler = enabled.connect_active_notify(clone_sender(&sender, |s| {
+            EqualizerMsg::Enable(s.is_active())
+        }));
+        header.append(&enabled);
+        content.append(&header);
+
+        let band_box = gtk::Box::builder().spacing(2).homogeneous(true).build();
+        let bands = EQUALIZER_BANDS
+            .iter()
+            .enumerate()
+            .map(|(index, &frequency)| {
+                let column = gtk::Box::builder()
+                    .orientation(gtk::Orientation::Vertical)
+                    .spacing(2)
+                    .build();
+                let scale =
+                    gtk::Scale::with_range(gtk::Orientation::Vertical, -MAX_GAIN, MAX_GAIN, 0.5);
+                scale.set_inverted(true);
+                scale.set_height_request(160);
+                scale.add_mark(0.0, gtk::PositionType::Left, None);
+                let handler = scale.connect_value_changed(clone_sender(&sender, move |s| {
+                    EqualizerMsg::Gain(index, s.value())
+                }));
+                column.append(&scale);
+                column.append(
+                    &gtk::Label::builder()
+                        .label(band_label(frequency))
+                        .css_classes(["caption"])
+                        .build(),
+                );
+                band_box.append(&column);
+                (scale, handler)
+            })
+            .collect();
+        content.append(&band_box);
+
+        let footer = gtk::Box::builder().spacing(6).build();
+        footer.append(
+            &gtk::Label::builder()
+                .label("Night Mode")
+                .tooltip_text("Compress loud passages so dialog stays audible")
+                .halign(gtk::Align::Start)
+                .hexpand(true)
+                .build(),
+        );
+        let night_mode = gtk::Switch::builder().valign(gtk::Align::Center).build();
+        let night_mode_handler = night_mode.connect_active_notify(clone_sender(&sender, |s| {
+            EqualizerMsg::NightMode(s.is_active())
+        }));
+        footer.append(&night_mode);
+        content.append(&footer);
+
+        let reset = gtk::Button::with_label("Reset");
+        reset.connect_clicked(clone_sender(&sender, |_| EqualizerMsg::Reset));
+        content.append(&reset);
+
+        let mut widgets = EqualizerWidgets {
+            enabled: (enabled, enabled_handler),
+            presets: (presets, presets_handler),
+            bands,
+            night_mode: (night_mode, night_mode_handler),
+            reset,
+        };
+        model.update_view(&mut widgets, sender);
+
+        ComponentParts { model, widgets }
+    }
+
+    fn init_root() -> Self::Root {
+        gtk::Popover::new()
+    }
+
+    fn update(&mut self, msg: Self::Input, sender: ComponentSender<Self>) {
+        let equalizer = &mut self.equalizer;
+        match msg {
+            EqualizerMsg::Enable(enabled) => equalizer.enabled = enabled,
+            EqualizerMsg::Gain(index, gain) => equalizer.gains[index] = gain,
+            EqualizerMsg::Preset(index) => match PRESETS.get(index as usize) {
+                Some((_, gains)) => {
+                    equalizer.gains = *gains;
+                    equalizer.enabled = true;
+                }
+                None => return,
+            },
+            EqualizerMsg::NightMode(night_mode) => equalizer.night_mode = night_mode,
+            EqualizerMsg::Reset => *equalizer = AudioEqualizer::default(),
+        }
+        let _ = sender.output(self.equalizer);
+    }
+
+    fn update_view(&self, widgets: &mut Self::Widgets, _sender: ComponentSender<Self>) {
+        let equalizer = &self.equalizer;
+
+        let (switch, handler) = &widgets.enabled;
+        switch.block_signal(handler);
+        switch.set_active(equalizer.enabled);
+        switch.unblock_signal(handler);
+
+        let (dropdown, handler) = &widgets.presets;
+        let preset = PRESETS
+            .iter()
+            .position(|(_, gains)| *gains == equalizer.gains)
+            .map_or(gtk::INVALID_LIST_POSITION, |i| i as u32);
+        dropdown.block_signal(handler);
+        dropdown.set_selected(preset);
+        dropdown.unblock_signal(handler);
+
+        for ((scale, handler), gain) in widgets.bands.iter().zip(equalizer.gains) {
+            scale.block_signal(handler);
+            scale.set_value(gain);
+            scale.set_sensitive(equalizer.enabled);
+            scale.unblock_signal(handler);
+        }
+
+        let (switch, handler) = &widgets.night_mode;
+        switch.block_signal(handler);
+        switch.set_active(equalizer.night_mode);
+        switch.unblock_signal(handler);
+
+        widgets
+            .reset
+            .set_sensitive(*equalizer != AudioEqualizer::default());
+    }
+}
+
+/// Returns a signal handler that sends the message built from the emitting widget.
+fn clone_sender<W>(
+    sender: &ComponentSender<EqualizerPopover>,
+    message: impl Fn(&W) -> EqualizerMsg + 'static,
+) -> impl Fn(&W) + 'static {
+    let sender = sender.clone();
+    move |widget| sender.input(message(widget))
+}
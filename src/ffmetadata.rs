@@ -0,0 +1,204 @@
+use std::fmt::Write as _;
+
+/// First line of every file, which ffmpeg checks before reading the rest
+const HEADER: &str = ";FFMETADATA1";
+/// Time base ffmpeg assumes for chapters that do not state one
+const DEFAULT_TIME_BASE: (i64, i64) = (1, 1_000_000_000);
+
+/// A chapter with its start and end in microseconds and its tags, such as `title`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Chapter {
+    pub start: i64,
+    pub end: i64,
+    pub tags: Vec<(String, String)>,
+}
+
+impl Chapter {
+    /// Returns the value of the first tag called `name`, ignoring case.
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Replaces the value of the first tag called `name`, or adds the tag.
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self
+            .tags
+            .iter_mut()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        {
+            Some((_, old)) => *old = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+}
+
+/// The contents of an ffmpeg metadata file, as read and written by `-f ffmetadata`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Metadata {
+    /// Container tags
+    pub tags: Vec<(String, String)>,
+    pub chapters: Vec<Chapter>,
+}
+
+/// Escapes the characters that are special in keys and values.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '=' | ';' | '#' | '\\' | '\n') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Splits text into lines, keeping escaped line breaks and dropping the escapes. Returns each
+/// line with a flag telling whether its first character was escaped, so escaped `;`, `#` and
+/// `[` do not start comments or sections, and the position of the first unescaped `=`.
+fn lines(text: &str) -> Vec<(String, bool, Option<usize>)> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    let mut escaped_start = false;
+    let mut separator = None;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                if let Some(next) = chars.next() {
+                    escaped_start |= line.is_empty();
+                    line.push(next);
+                }
+            }
+            '=' if separator.is_none() => {
+                separator = Some(line.len());
+                line.push(c);
+            }
+            '\n' => {
+                let line = std::mem::take(&mut line);
+                lines.push((line, escaped_start, separator.take()));
+                escaped_start = false;
+            }
+            '\r' => {}
+            c => line.push(c),
+        }
+    }
+    if !line.is_empty() {
+        lines.push((line, escaped_start, separator));
+    }
+    lines
+}
+
+/// Converts a timestamp in `time_base` to microseconds, saturating on overflow.
+fn to_micros(value: i64, (numerator, denominator): (i64, i64)) -> i64 {
+    let micros = i128::from(value) * i128::from(numerator) * 1_000_000 / i128::from(denominator);
+    micros.clamp(i128::from(i64::MIN), i128::from(i64::MAX)) as i64
+}
+
+fn parse_time_base(text: &str) -> Option<(i64, i64)> {
+    let (numerator, denominator) = text.trim().split_once('/')?;
+    let numerator = numerator.trim().parse().ok()?;
+    let denominator = denominator.trim().parse().ok()?;
+    (numerator > 0 && denominator > 0).then_some((numerator, denominator))
+}
+
+impl Metadata {
+    /// Writes the tags and chapters, with chapter times in milliseconds.
+    pub fn write(&self) -> String {
+        let mut text = format!("{HEADER}\n");
+        for (key, value) in &self.tags {
+            let _ = writeln!(text, "{}={}", escape(key), escape(value));
+        }
+        for chapter in &self.chapters {
+            let _ = writeln!(text, "\n[CHAPTER]\nTIMEBASE=1/1000");
+            let _ = writeln!(text, "START={}", chapter.start / 1000);
+            let _ = writeln!(text, "END={}", chapter.end.max(chapter.start) / 1000);
+            for (key, value) in &chapter.tags {
+                let _ = writeln!(text, "{}={}", escape(key), escape(value));
+            }
+        }
+        text
+    }
+
+    /// Reads tags and chapters, skipping stream sections. Chapters are returned in file order.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+        if !text.starts_with(HEADER) {
+            return Err(String::from("Not an ffmpeg metadata file"));
+        }
+
+        #[derive(PartialEq)]
+        enum Section {
+            Global,
+            Chapter,
+            Other,
+        }
+        let mut metadata = Self::default();
+        let mut section = Section::Global;
+        // Raw start, end and time base of the chapter being read
+        let mut times = (None, None, DEFAULT_TIME_BASE);
+        let finish = |metadata: &mut Self, times: (Option<i64>, Option<i64>, (i64, i64))| {
+            if let Some(chapter) = metadata.chapters.last_mut() {
+                let (start, end, time_base) = times;
+                chapter.start = to_micros(start.unwrap_or_default(), time_base);
+                chapter.end = to_micros(end.unwrap_or_default(), time_base).max(chapter.start);
+            }
+        };
+
+        for (number, (line, escaped_start, separator)) in lines(text).into_iter().enumerate() {
+            if line.trim().is_empty() || (!escaped_start && line.starts_with([';', '#'])) {
+                continue;
+            }
+            if !escaped_start && line.starts_with('[') {
+                if section == Section::Chapter {
+                    finish(&mut metadata, times);
+                }
+                section = match line.trim() {
+                    "[CHAPTER]" => {
+                        metadata.chapters.push(Chapter::default());
+                        times = (None, None, DEFAULT_TIME_BASE);
+                        Section::Chapter
+                    }
+                    _ => Section::Other,
+                };
+                continue;
+            }
+            let Some(separator) = separator else {
+                return Err(format!("Line {} has no value", number + 1));
+            };
+            let (key, value) = (&line[..separator], &line[separator + 1..]);
+
+            match section {
+                Section::Global => metadata.tags.push((key.into(), value.into())),
+                Section::Chapter => {
+                    let time = |value: &str| {
+                        value
+                            .trim()
+                            .parse::<i64>()
+                            .map_err(|_| format!("Line {} has an invalid time", number + 1))
+                    };
+                    match key {
+                        "TIMEBASE" => {
+                            times.2 = parse_time_base(value).ok_or_else(|| {
+                                format!("Line {} has an invalid time base", number + 1)
+                            })?;
+                        }
+                        "START" => times.0 = Some(time(value)?),
+                        "END" => times.1 = Some(time(value)?),
+                        _ => {
+                            let chapter = metadata.chapters.last_mut().unwrap();
+                            chapter.tags.push((key.into(), value.into()));
+                        }
+                    }
+                }
+                Section::Other => {}
+            }
+        }
+        if section == Section::Chapter {
+            finish(&mut metadata, times);
+        }
+        Ok(metadata)
+    }
+}
@@ -0,0 +1,75 @@
+use std::path::Path;
+
+use crate::decoder::VideoAdjustments;
+use crate::playlist;
+use crate::storage;
+
+const FILE: &str = "file-settings.ini";
+
+/// Picture adjustments and delays of one file. They are kept apart from the history, so they
+/// are not lost once the file drops out of it.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct FileSettings {
+    pub adjustments: VideoAdjustments,
+    /// Audio and subtitle offsets in microseconds
+    pub audio_delay: i64,
+    pub subtitle_delay: i64,
+}
+
+impl FileSettings {
+    /// Returns the settings saved for `location`, or the defaults.
+    pub fn load(location: &Path) -> Self {
+        let file = storage::load(&storage::data_file(FILE));
+        let group = storage::key_for(location);
+        let default = VideoAdjustments::default();
+        let double = |key, default| file.double(&group, key).unwrap_or(default);
+        Self {
+            adjustments: VideoAdjustments {
+                brightness: double("brightness", default.brightness),
+                contrast: double("contrast", default.contrast),
+                saturation: double("saturation", default.saturation),
+                gamma: double("gamma", default.gamma),
+                hue: double("hue", default.hue),
+            },
+            audio_delay: file.int64(&group, "audio-delay").unwrap_or_default(),
+            subtitle_delay: file.int64(&group, "subtitle-delay").unwrap_or_default(),
+        }
+    }
+
+    /// Saves the settings of `location`, forgetting the file when they are all defaults, and
+    /// forgets local files that no longer exist.
+    pub fn store(&self, location: &Path) {
+        let path = storage::data_file(FILE);
+        let file = storage::load(&path);
+        for group in file.groups().iter() {
+            let group = group.as_str();
+            let present = file.string(group, "location").is_ok_and(|saved| {
+                let saved = Path::new(saved.as_str());
+                playlist::is_url(saved) || saved.exists()
+            });
+            if !present {
+                let _ = file.remove_group(group);
+            }
+        }
+        let group = storage::key_for(location);
+        let _ = file.remove_group(&group);
+        if *self != Self::default() {
+            file.set_string(&group, "location", &location.to_string_lossy());
+            let adjustments = &self.adjustments;
+            if !adjustments.is_default() {
+                file.set_double(&group, "brightness", adjustments.brightness);
+                file.set_double(&group, "contrast", adjustments.contrast);
+                file.set_double(&group, "saturation", adjustments.saturation);
+                file.set_double(&group, "gamma", adjustments.gamma);
+                file.set_double(&group, "hue", adjustments.hue);
+            }
+            if self.audio_delay != 0 {
+                file.set_int64(&group, "audio-delay", self.audio_delay);
+            }
+            if self.subtitle_delay != 0 {
+                file.set_int64(&group, "subtitle-delay", self.subtitle_delay);
+            }
+        }
+        storage::save(&file, &path);
+    }
+}
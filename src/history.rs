@@ -0,0 +1,181 @@
+use std::path::{Path, PathBuf};
+
+use gtk::{gio, prelude::*};
+
+use crate::{playlist, storage, timecode};
+
+const FILE: &str = "history.ini";
+/// Unpinned entries kept in the history
+const MAX_RECENT: usize = 20;
+
+#[derive(Debug, Clone)]
+pub struct RecentFile {
+    pub location: PathBuf,
+    /// Position playback resumes from, in microseconds
+    pub position: i64,
+    pub pinned: bool,
+    pub thumbnail: Option<PathBuf>,
+}
+
+impl RecentFile {
+    fn new(location: &Path) -> Self {
+        Self {
+            location: location.to_path_buf(),
+            position: 0,
+            pinned: false,
+            thumbnail: None,
+        }
+    }
+
+    /// Returns the file name, or the whole address for network streams.
+    pub fn name(&self) -> String {
+        match self.location.file_name() {
+            Some(name) if !playlist::is_url(&self.location) => name.to_string_lossy().into(),
+            _ => self.location.display().to_string(),
+        }
+    }
+
+    pub fn label(&self) -> String {
+        if self.position > 0 {
+            format!(
+                "{} ({})",
+                self.name(),
+                timecode::format_clock(self.position)
+            )
+        } else {
+            self.name()
+        }
+    }
+
+    pub fn is_missing(&self) -> bool {
+        !playlist::is_url(&self.location) && !self.location.exists()
+    }
+
+    /// Returns the URI registered with the desktop's recent files.
+    pub fn uri(&self) -> String {
+        if playlist::is_url(&self.location) {
+            self.location.display().to_string()
+        } else {
+            gio::File::for_path(&self.location).uri().into()
+        }
+    }
+
+    fn forget(&self) {
+        if let Some(thumbnail) = &self.thumbnail {
+            let _ = std::fs::remove_file(thumbnail);
+        }
+    }
+}
+
+/// Recently opened files, most recent first, with pinned files exempt from trimming and clearing.
+#[derive(Debug, Default)]
+pub struct History {
+    files: Vec<RecentFile>,
+}
+
+impl History {
+    pub fn load() -> Self {
+        let file = storage::load(&storage::data_file(FILE));
+        let files = file
+            .groups()
+            .iter()
+            .filter_map(|group| {
+                let group = group.as_str();
+                let location = file.string(group, "location").ok()?;
+                Some(RecentFile {
+                    location: PathBuf::from(location.as_str()),
+                    position: file.int64(group, "position").unwrap_or_default(),
+                    pinned: file.boolean(group, "pinned").unwrap_or_default(),
+                    thumbnail: file
+                        .string(group, "thumbnail")
+                        .ok()
+                        .map(|t| PathBuf::from(t.as_str()))
+                        .filter(|t| t.exists()),
+                })
+            })
+            .collect();
+        Self { files }
+    }
+
+    pub fn save(&self) {
+        let file = gtk::glib::KeyFile::new();
+        for (i, recent) in self.files.iter().enumerate() {
+            let group = format!("Recent {i}");
+            file.set_string(&group, "location", &recent.location.to_string_lossy());
+            file.set_int64(&group, "position", recent.position);
+            file.set_boolean(&group, "pinned", recent.pinned);
+            if let Some(thumbnail) = &recent.thumbnail {
+                file.set_string(&group, "thumbnail", &thumbnail.to_string_lossy());
+            }
+        }
+        storage::save(&file, &storage::data_file(FILE));
+    }
+
+    pub fn files(&self) -> &[RecentFile] {
+        &self.files
+    }
+
+    pub fn get(&self, location: &Path) -> Option<&RecentFile> {
+        self.files.iter().find(|f| f.location == location)
+    }
+
+    fn get_mut(&mut self, location: &Path) -> Option<&mut RecentFile> {
+        self.files.iter_mut().find(|f| f.location == location)
+    }
+
+    /// Moves `location` to the top, dropping the oldest unpinned entries beyond the limit.
+    pub fn add(&mut self, location: &Path) {
+        let recent = match self.files.iter().position(|f| f.location == location) {
+            Some(index) => self.files.remove(index),
+            None => RecentFile::new(location),
+        };
+        self.files.insert(0, recent);
+
+        let mut unpinned = 0;
+        self.files.retain(|f| {
+            if f.pinned {
+                return true;
+            }
+            unpinned += 1;
+            if unpinned > MAX_RECENT {
+                f.forget();
+            }
+            unpinned <= MAX_RECENT
+        });
+    }
+
+    pub fn set_position(&mut self, location: &Path, position: i64) {
+        if let Some(recent) = self.get_mut(location) {
+            recent.position = position;
+        }
+    }
+
+    pub fn set_thumbnail(&mut self, location: &Path, thumbnail: PathBuf) {
+        if let Some(recent) = self.get_mut(location) {
+            recent.thumbnail = Some(thumbnail);
+        }
+    }
+
+    pub fn toggle_pinned(&mut self, location: &Path) {
+        if let Some(recent) = self.get_mut(location) {
+            recent.pinned = !recent.pinned;
+        }
+    }
+
+    /// Removes entries whose files no longer exist and returns them.
+    pub fn remove_missing(&mut self) -> Vec<RecentFile> {
+        self.remove_where(RecentFile::is_missing)
+    }
+
+    /// Removes all unpinned entries and returns them.
+    pub fn clear(&mut self) -> Vec<RecentFile> {
+        self.remove_where(|f| !f.pinned)
+    }
+
+    fn remove_where(&mut self, predicate: impl Fn(&RecentFile) -> bool) -> Vec<RecentFile> {
+        let (removed, kept): (Vec<_>, Vec<_>) = self.files.drain(..).partition(|f| predicate(f));
+        self.files = kept;
+        removed.iter().for_each(RecentFile::forget);
+        removed
+    }
+}
@@ -0,0 +1,255 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::playlist;
+use crate::probe::{self, Probe};
+use crate::storage;
+
+const FILE: &str = "library.db";
+/// How long a connection waits for another one's write to finish
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+/// Most entries shown at once, so a broad search stays responsive
+const MAX_RESULTS: usize = 500;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS media (
+        id INTEGER PRIMARY KEY,
+        path TEXT NOT NULL UNIQUE,
+        title TEXT NOT NULL,
+        modified INTEGER NOT NULL,
+        size INTEGER NOT NULL,
+        duration INTEGER NOT NULL,
+        width INTEGER NOT NULL,
+        height INTEGER NOT NULL,
+        format TEXT NOT NULL
+    );
+    CREATE VIRTUAL TABLE IF NOT EXISTS search USING fts5(title, tags);
+";
+
+/// A media file found in one of the library folders.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub path: PathBuf,
+    pub title: String,
+    /// Modification time in seconds since the epoch
+    pub modified: i64,
+    /// Duration in microseconds
+    pub duration: i64,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Sort {
+    /// Newest first
+    #[default]
+    Date,
+    /// Longest first
+    Duration,
+    /// Largest first
+    Resolution,
+    Title,
+}
+
+impl Sort {
+    pub const ALL: [Sort; 4] = [Sort::Date, Sort::Duration, Sort::Resolution, Sort::Title];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Date => "Date",
+            Self::Duration => "Duration",
+            Self::Resolution => "Resolution",
+            Self::Title => "Title",
+        }
+    }
+
+    fn order_by(&self) -> &'static str {
+        match self {
+            Self::Date => "media.modified DESC",
+            Self::Duration => "media.duration DESC",
+            Self::Resolution => "media.width * media.height DESC",
+            Self::Title => "media.title COLLATE NOCASE",
+        }
+    }
+}
+
+/// Turns what the user typed into an FTS5 query matching every word as a prefix.
+fn search_query(text: &str) -> String {
+    text.split_whitespace()
+        .map(|word| format!("\"{}\"*", word.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Probed media files in the library folders, kept in an SQLite database with a full-text
+/// index over titles and tags.
+pub struct Library {
+    connection: Connection,
+}
+
+impl Library {
+    pub fn open() -> rusqlite::Result<Self> {
+        let connection = Connection::open(storage::data_file(FILE))?;
+        connection.busy_timeout(BUSY_TIMEOUT)?;
+        connection.execute_batch(SCHEMA)?;
+        Ok(Self { connection })
+    }
+
+    /// Probes new and changed media files below `folder` and drops the ones that are gone.
+    /// This blocks while probing, so it should be run off the main thread. Returns whether
+    /// anything changed.
+    pub fn scan(&mut self, folder: &Path) -> rusqlite::Result<bool> {
+        let files = playlist::collect_media(folder);
+        let mut changed = false;
+
+        for path in &files {
+            let Some((modified, size)) = probe::file_stamp(path) else {
+                continue;
+            };
+            let known = self
+                .connection
+                .query_row(
+                    "SELECT modified, size FROM media WHERE path = ?1",
+                    [path.to_string_lossy()],
+                    |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)),
+                )
+                .optional()?;
+            if known == Some((modified, size)) {
+                continue;
+            }
+            match probe::probe(path) {
+                Ok(probe) => {
+                    self.insert(path, modified, size, &probe)?;
+                    changed = true;
+                }
+                Err(e) => eprintln!("Error: Could not probe {} ({e})", path.display()),
+            }
+        }
+
+        let prefix = format!("{}/", folder.to_string_lossy().trim_end_matches('/'));
+        let stored = self.paths_below(&prefix)?;
+        for path in stored.iter().filter(|p| !files.contains(p)) {
+            self.remove(path)?;
+            changed = true;
+        }
+        Ok(changed)
+    }
+
+    fn insert(
+        &mut self,
+        path: &Path,
+        modified: i64,
+        size: i64,
+        probe: &Probe,
+    ) -> rusqlite::Result<()> {
+        let title = probe.tag("title").map_or_else(
+            || {
+                path.file_stem()
+                    .map_or_else(String::new, |s| s.to_string_lossy().to_string())
+            },
+            String::from,
+        );
+        let tags = probe
+            .tags
+            .iter()
+            .map(|(_, value)| value.as_str())
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let transaction = self.connection.transaction()?;
+        let id: i64 = transaction.query_row(
+            "INSERT INTO media (path, title, modified, size, duration, width, height, format)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+             ON CONFLICT (path) DO UPDATE SET
+                title = excluded.title, modified = excluded.modified, size = excluded.size,
+                duration = excluded.duration, width = excluded.width, height = excluded.height,
+                format = excluded.format
+             RETURNING id",
+            params![
+                path.to_string_lossy(),
+                title,
+                modified,
+                size,
+                probe.duration,
+                probe.width,
+                probe.height,
+                probe.format,
+            ],
+            |row| row.get(0),
+        )?;
+        transaction.execute("DELETE FROM search WHERE rowid = ?1", [id])?;
+        transaction.execute(
+            "INSERT INTO search (rowid, title, tags) VALUES (?1, ?2, ?3)",
+            params![id, title, tags],
+        )?;
+        transaction.commit()
+    }
+
+    fn remove(&mut self, path: &Path) -> rusqlite::Result<()> {
+        let transaction = self.connection.transaction()?;
+        transaction.execute(
+            "DELETE FROM search WHERE rowid IN (SELECT id FROM media WHERE path = ?1)",
+            [path.to_string_lossy()],
+        )?;
+        transaction.execute(
+            "DELETE FROM media WHERE path = ?1",
+            [path.to_string_lossy()],
+        )?;
+        transaction.commit()
+    }
+
+    fn paths_below(&self, prefix: &str) -> rusqlite::Result<Vec<PathBuf>> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT path FROM media WHERE substr(path, 1, length(?1)) = ?1")?;
+        let paths = statement
+            .query_map([prefix], |row| row.get::<_, String>(0))?
+            .map(|path| path.map(PathBuf::from))
+            .collect();
+        paths
+    }
+
+    /// Forgets all files below a folder that was removed from the library.
+    pub fn remove_folder(&mut self, folder: &Path) -> rusqlite::Result<()> {
+        let prefix = format!("{}/", folder.to_string_lossy().trim_end_matches('/'));
+        for path in self.paths_below(&prefix)? {
+            self.remove(&path)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the entries whose title or tags contain every word of `text` as a prefix, or
+    /// all entries if it is empty.
+    pub fn search(&self, text: &str, sort: Sort) -> rusqlite::Result<Vec<Entry>> {
+        let query = search_query(text);
+        let filter = if query.is_empty() {
+            ""
+        } else {
+            "WHERE media.id IN (SELECT rowid FROM search WHERE search MATCH ?1)"
+        };
+        let sql = format!(
+            "SELECT path, title, modified, duration, width, height FROM media {filter}
+             ORDER BY {} LIMIT {MAX_RESULTS}",
+            sort.order_by()
+        );
+        let mut statement = self.connection.prepare(&sql)?;
+        let row = |row: &rusqlite::Row| {
+            Ok(Entry {
+                path: PathBuf::from(row.get::<_, String>(0)?),
+                title: row.get(1)?,
+                modified: row.get(2)?,
+                duration: row.get(3)?,
+                width: row.get(4)?,
+                height: row.get(5)?,
+            })
+        };
+        let entries = if query.is_empty() {
+            statement.query_map([], row)?.collect()
+        } else {
+            statement.query_map([query], row)?.collect()
+        };
+        entries
+    }
+}
@@ -0,0 +1,552 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use adw::prelude::*;
+use gtk::{gio, glib};
+use relm4::prelude::*;
+
+use crate::library::{Entry, Library, Sort};
+use crate::thumbnail::{self, Thumbnail};
+use crate::timecode;
+
+/// Changes are collected for a moment before rescanning, as copies arrive in many events
+const RESCAN_DELAY: Duration = Duration::from_secs(2);
+const GRID_WIDTH: i32 = 160;
+const LIST_WIDTH: i32 = 96;
+
+pub struct LibraryWindow {
+    visible: bool,
+    library: Option<Library>,
+    folders: Vec<PathBuf>,
+    query: String,
+    sort: Sort,
+    grid: bool,
+    entries: Vec<Entry>,
+    /// Incremented whenever `entries` or the layout changes, so the view is rebuilt only then
+    revision: u32,
+    /// Folders currently being scanned
+    scanning: HashSet<PathBuf>,
+    /// Folders with a rescan scheduled after a change
+    scheduled: HashSet<PathBuf>,
+    /// Folders that changed while being scanned, scanned again once that scan ends
+    pending: HashSet<PathBuf>,
+    /// Thumbnails captured or being captured this session
+    requested: HashSet<PathBuf>,
+    /// Thumbnail saved during the last update
+    thumbnail: Option<PathBuf>,
+    monitors: Vec<gio::FileMonitor>,
+}
+
+#[derive(Debug)]
+pub enum LibraryMsg {
+    Show,
+    Hide,
+    AddFolder,
+    SetFolder(PathBuf),
+    RemoveFolder(PathBuf),
+    Search(String),
+    Sort(u32),
+    SetGrid(bool),
+    /// Something changed below a library folder
+    Changed(PathBuf),
+    Scan(PathBuf),
+    Activate(usize),
+}
+
+#[derive(Debug)]
+pub enum LibraryOutput {
+    Open(PathBuf),
+    /// The library folders changed and should be saved
+    Folders(Vec<PathBuf>),
+}
+
+#[derive(Debug)]
+pub enum LibraryCommand {
+    Scanned(PathBuf, bool),
+    Thumbnail(PathBuf, Option<Thumbnail>),
+}
+
+pub struct LibraryWidgets {
+    window: adw::Window,
+    stack: gtk::Stack,
+    content: gtk::FlowBox,
+    folder_list: gtk::ListBox,
+    spinner: gtk::Spinner,
+    pictures: HashMap<PathBuf, gtk::Picture>,
+    revision: u32,
+    folders: Vec<PathBuf>,
+}
+
+impl Component for LibraryWindow {
+    type Init = Vec<PathBuf>;
+    type Input = LibraryMsg;
+    type Output = LibraryOutput;
+    type CommandOutput = LibraryCommand;
+    type Root = adw::Window;
+    type Widgets = LibraryWidgets;
+
+    fn init(
+        init: Self::Init,
+        root: Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let library = Library::open()
+            .inspect_err(|e| eprintln!("Error: Could not open the library ({e})"))
+            .ok();
+        let mut model = Self {
+            visible: false,
+            library,
+            folders: init,
+            query: String::new(),
+            sort: Sort::default(),
+            grid: true,
+            entries: Vec::new(),
+            revision: 0,
+            scanning: HashSet::new(),
+            scheduled: HashSet::new(),
+            pending: HashSet::new(),
+            requested: HashSet::new(),
+            thumbnail: None,
+            monitors: Vec::new(),
+        };
+
+        let header = adw::HeaderBar::new();
+
+        let search = gtk::SearchEntry::builder()
+            .placeholder_text("Search titles and tags")
+            .build();
+        search.connect_search_changed(clone_sender(&sender, |s: &gtk::SearchEntry| {
+            LibraryMsg::Search(s.text().to_string())
+        }));
+        header.set_title_widget(Some(&search));
+
+        let add = gtk::Button::builder()
+            .icon_name("folder-new-symbolic")
+            .tooltip_text("Add Folder")
+            .build();
+        add.connect_clicked(clone_sender(&sender, |_| LibraryMsg::AddFolder));
+        header.pack_start(&add);
+
+        let folder_list = gtk::ListBox::builder()
+            .selection_mode(gtk::SelectionMode::None)
+            .css_classes(["boxed-list"])
+            .build();
+        let folders = gtk::MenuButton::builder()
+            .icon_name("folder-symbolic")
+            .tooltip_text("Library Folders")
+            .popover(&gtk::Popover::builder().child(&folder_list).build())
+            .build();
+        header.pack_start(&folders);
+
+        let spinner = gtk::Spinner::builder()
+            .tooltip_text("Scanning")
+            .visible(false)
+            .build();
+        header.pack_start(&spinner);
+
+        let grid = gtk::ToggleButton::builder()
+            .icon_name("view-grid-symbolic")
+            .tooltip_text("Show as Grid")
+            .active(model.grid)
+            .build();
+        grid.connect_toggled(clone_sender(&sender, |b: &gtk::ToggleButton| {
+            LibraryMsg::SetGrid(b.is_active())
+        }));
+        header.pack_end(&grid);
+
+        let sort = gtk::DropDown::from_strings(&Sort::ALL.map(|s| s.label()));
+        sort.set_tooltip_text(Some("Sort By"));
+        sort.connect_selected_notify(clone_sender(&sender, |d: &gtk::DropDown| {
+            LibraryMsg::Sort(d.selected())
+        }));
+        header.pack_end(&sort);
+
+        let content = gtk::FlowBox::builder()
+            .valign(gtk::Align::Start)
+            .selection_mode(gtk::SelectionMode::None)
+            .homogeneous(true)
+            .row_spacing(12)
+            .column_spacing(12)
+            .margin_top(12)
+            .margin_bottom(12)
+            .margin_start(12)
+            .margin_end(12)
+            .build();
+        let activate = sender.clone();
+        content.connect_child_activated(move |_, child| {
+            activate.input(LibraryMsg::Activate(child.index().max(0) as usize));
+        });
+
+        let empty = adw::StatusPage::builder()
+            .icon_name("folder-videos-symbolic")
+            .title("No Media")
+            .description("Add a folder to browse its videos and audio files")
+            .build();
+
+        let stack = gtk::Stack::new();
+        stack.add_named(
+            &gtk::ScrolledWindow::builder()
+                .hscrollbar_policy(gtk::PolicyType::Never)
+                .vexpand(true)
+                .child(&content)
+                .build(),
+            Some("content"),
+        );
+        stack.add_named(&empty, Some("empty"));
+
+        let toolbar = adw::ToolbarView::new();
+        toolbar.add_top_bar(&header);
+        toolbar.set_content(Some(&stack));
+        root.set_content(Some(&toolbar));
+
+        let close = sender.clone();
+        root.connect_close_request(move |_| {
+            close.input(LibraryMsg::Hide);
+            glib::Propagation::Stop
+        });
+
+        model.watch(&sender);
+        for folder in model.folders.clone() {
+            model.scan(folder, &sender);
+        }
+        model.refresh(&sender);
+
+        let mut widgets = LibraryWidgets {
+            window: root,
+            stack,
+            content,
+            folder_list,
+            spinner,
+            pictures: HashMap::new(),
+            revision: u32::MAX,
+            folders: Vec::new(),
+        };
+        model.update_view(&mut widgets, sender);
+
+        ComponentParts { model, widgets }
+    }
+
+    fn init_root() -> Self::Root {
+        adw::Window::builder()
+            .title("Library")
+            .default_width(900)
+            .default_height(600)
+            .build()
+    }
+
+    fn update(&mut self, msg: Self::Input, sender: ComponentSender<Self>, _root: &Self::Root) {
+        self.thumbnail = None;
+        match msg {
+            LibraryMsg::Show => {
+                self.visible = true;
+            }
+            LibraryMsg::Hide => {
+                self.visible = false;
+            }
+            LibraryMsg::AddFolder => {
+                let dialog = rfd::AsyncFileDialog::new()
+                    .set_title("Add a Folder to the Library")
+                    .pick_folder();
+                let sender = sender.clone();
+                relm4::spawn_local(async move {
+                    if let Some(folder) = dialog.await {
+                        sender.input(LibraryMsg::SetFolder(folder.path().to_path_buf()));
+                    }
+                });
+            }
+            LibraryMsg::SetFolder(folder) => {
+                if self.folders.contains(&folder) {
+                    return;
+                }
+                self.folders.push(folder.clone());
+                let _ = sender.output(LibraryOutput::Folders(self.folders.clone()));
+                self.watch(&sender);
+                self.scan(folder, &sender);
+            }
+            LibraryMsg::RemoveFolder(folder) => {
+                self.folders.retain(|f| *f != folder);
+                let _ = sender.output(LibraryOutput::Folders(self.folders.clone()));
+                if let Some(library) = self.library.as_mut() {
+                    if let Err(e) = library.remove_folder(&folder) {
+                        eprintln!("Error: Could not remove {} ({e})", folder.display());
+                    }
+                }
+                self.watch(&sender);
+                self.refresh(&sender);
+            }
+            LibraryMsg::Search(query) => {
+                self.query = query;
+                self.refresh(&sender);
+            }
+            LibraryMsg::Sort(index) => {
+                self.sort = Sort::ALL.get(index as usize).copied().unwrap_or_default();
+                self.refresh(&sender);
+            }
+            LibraryMsg::SetGrid(grid) => {
+                self.grid = grid;
+                self.revision = self.revision.wrapping_add(1);
+            }
+            LibraryMsg::Changed(folder) => {
+                if self.scheduled.insert(folder.clone()) {
+                    let sender = sender.clone();
+                    glib::timeout_add_local_once(RESCAN_DELAY, move || {
+                        sender.input(LibraryMsg::Scan(folder));
+                    });
+                }
+            }
+            LibraryMsg::Scan(folder) => {
+                self.scheduled.remove(&folder);
+                if self.folders.contains(&folder) {
+                    self.watch(&sender);
+                    self.scan(folder, &sender);
+                }
+            }
+            LibraryMsg::Activate(index) => {
+                if let Some(entry) = self.entries.get(index) {
+                    let _ = sender.output(LibraryOutput::Open(entry.path.clone()));
+                }
+            }
+        }
+    }
+
+    fn update_cmd(
+        &mut self,
+        message: Self::CommandOutput,
+        sender: ComponentSender<Self>,
+        _root: &Self::Root,
+    ) {
+        self.thumbnail = None;
+        match message {
+            LibraryCommand::Scanned(folder, changed) => {
+                self.scanning.remove(&folder);
+                if changed {
+                    self.refresh(&sender);
+                }
+                if self.pending.remove(&folder) && self.folders.contains(&folder) {
+                    self.scan(folder, &sender);
+                }
+            }
+            LibraryCommand::Thumbnail(location, Some(thumbnail)) => {
+                if thumbnail.save(&thumbnail::path_for(&location)) {
+                    self.thumbnail = Some(location);
+                }
+            }
+            LibraryCommand::Thumbnail(_, None) => {}
+        }
+    }
+
+    fn update_view(&self, widgets: &mut Self::Widgets, sender: ComponentSender<Self>) {
+        widgets.window.set_visible(self.visible);
+        widgets.spinner.set_visible(!self.scanning.is_empty());
+        widgets.spinner.set_spinning(!self.scanning.is_empty());
+
+        if widgets.folders != self.folders {
+            widgets.folders = self.folders.clone();
+            widgets.folder_list.remove_all();
+            for folder in &self.folders {
+                let row = adw::ActionRow::builder()
+                    .title(folder.display().to_string())
+                    .build();
+                let remove = gtk::Button::builder()
+                    .icon_name("list-remove-symbolic")
+                    .tooltip_text("Remove from Library")
+                    .valign(gtk::Align::Center)
+                    .css_classes(["flat"])
+                    .build();
+                let folder = folder.clone();
+                remove.connect_clicked(clone_sender(&sender, move |_| {
+                    LibraryMsg::RemoveFolder(folder.clone())
+                }));
+                row.add_suffix(&remove);
+                widgets.folder_list.append(&row);
+            }
+            widgets.folder_list.set_visible(!self.folders.is_empty());
+        }
+
+        if widgets.revision != self.revision {
+            widgets.revision = self.revision;
+            widgets.content.remove_all();
+            widgets.pictures.clear();
+            widgets
+                .content
+                .set_max_children_per_line(if self.grid { 32 } else { 1 });
+            for entry in &self.entries {
+                let (card, picture) = card(entry, self.grid);
+                widgets.content.append(&card);
+                widgets.pictures.insert(entry.path.clone(), picture);
+            }
+            widgets
+                .stack
+                .set_visible_child_name(if self.entries.is_empty() {
+                    "empty"
+                } else {
+                    "content"
+                });
+        }
+
+        if let Some(location) = &self.thumbnail {
+            if let Some(picture) = widgets.pictures.get(location) {
+                picture.set_filename(Some(thumbnail::path_for(location)));
+            }
+        }
+    }
+}
+
+impl LibraryWindow {
+    /// Runs the search again and captures thumbnails that are still missing.
+    fn refresh(&mut self, sender: &ComponentSender<Self>) {
+        let Some(library) = self.library.as_ref() else {
+            return;
+        };
+        self.entries = library
+            .search(&self.query, self.sort)
+            .inspect_err(|e| eprintln!("Error: Could not search the library ({e})"))
+            .unwrap_or_default();
+        self.revision = self.revision.wrapping_add(1);
+
+        let missing = self
+            .entries
+            .iter()
+            .map(|e| e.path.clone())
+            .filter(|p| !thumbnail::path_for(p).exists() && self.requested.insert(p.clone()))
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            sender.spawn_command(move |out| {
+                for location in missing {
+                    let thumbnail = thumbnail::capture(&location);
+                    if out
+                        .send(LibraryCommand::Thumbnail(location, thumbnail))
+                        .is_err()
+                    {
+                        break;
+                    }
+                }
+            });
+        }
+    }
+
+    /// Probes `folder` in the background with a connection of its own, or once more after the
+    /// scan already running, which may have missed the latest changes.
+    fn scan(&mut self, folder: PathBuf, sender: &ComponentSender<Self>) {
+        if !self.scanning.insert(folder.clone()) {
+            self.pending.insert(folder);
+            return;
+        }
+        sender.spawn_oneshot_command(move || {
+            let changed = Library::open()
+                .and_then(|mut library| library.scan(&folder))
+                .inspect_err(|e| eprintln!("Error: Could not scan {} ({e})", folder.display()))
+                .unwrap_or(false);
+            LibraryCommand::Scanned(folder, changed)
+        });
+    }
+
+    /// Monitors every library folder and its subfolders for added, removed and moved files.
+    fn watch(&mut self, sender: &ComponentSender<Self>) {
+        self.monitors.clear();
+        for folder in &self.folders {
+            for dir in directories(folder) {
+                let monitor = match gio::File::for_path(&dir)
+                    .monitor_directory(gio::FileMonitorFlags::WATCH_MOVES, gio::Cancellable::NONE)
+                {
+                    Ok(m) => m,
+                    Err(e) => {
+                        eprintln!("Error: Could not watch {} ({e})", dir.display());
+                        continue;
+                    }
+                };
+                let sender = sender.clone();
+                let folder = folder.clone();
+                monitor.connect_changed(move |_, _, _, event| {
+                    if matches!(
+                        event,
+                        gio::FileMonitorEvent::ChangesDoneHint
+                            | gio::FileMonitorEvent::Deleted
+                            | gio::FileMonitorEvent::MovedIn
+                            | gio::FileMonitorEvent::MovedOut
+                            | gio::FileMonitorEvent::Renamed
+                    ) {
+                        sender.input(LibraryMsg::Changed(folder.clone()));
+                    }
+                });
+                self.monitors.push(monitor);
+            }
+        }
+    }
+}
+
+/// Returns `root` and all directories below it.
+fn directories(root: &Path) -> Vec<PathBuf> {
+    let mut dirs = vec![root.to_path_buf()];
+    let mut index = 0;
+    while let Some(dir) = dirs.get(index).cloned() {
+        if let Ok(entries) = std::fs::read_dir(&dir) {
+            dirs.extend(entries.flatten().map(|e| e.path()).filter(|p| p.is_dir()));
+        }
+        index += 1;
+    }
+    dirs
+}
+
+/// Builds the grid or list item for an entry, returning the picture showing its thumbnail.
+fn card(entry: &Entry, grid: bool) -> (gtk::Box, gtk::Picture) {
+    let width = if grid { GRID_WIDTH } else { LIST_WIDTH };
+    let picture = gtk::Picture::builder()
+        .content_fit(gtk::ContentFit::Cover)
+        .width_request(width)
+        .height_request(width * 9 / 16)
+        .css_classes(["card"])
+        .build();
+    let thumbnail = thumbnail::path_for(&entry.path);
+    if thumbnail.exists() {
+        picture.set_filename(Some(&thumbnail));
+    }
+
+    let mut details = vec![timecode::format_clock(entry.duration)];
+    if entry.width > 0 && entry.height > 0 {
+        details.push(format!("{}×{}", entry.width, entry.height));
+    }
+
+    let labels = gtk::Box::builder()
+        .orientation(gtk::Orientation::Vertical)
+        .valign(gtk::Align::Center)
+        .build();
+    labels.append(
+        &gtk::Label::builder()
+            .label(&entry.title)
+            .tooltip_text(entry.path.display().to_string())
+            .ellipsize(gtk::pango::EllipsizeMode::End)
+            .max_width_chars(if grid { 18 } else { 60 })
+            .xalign(0.0)
+            .build(),
+    );
+    labels.append(
+        &gtk::Label::builder()
+            .label(details.join(" · "))
+            .css_classes(["caption", "dim-label"])
+            .xalign(0.0)
+            .build(),
+    );
+
+    let card = gtk::Box::builder()
+        .orientation(if grid {
+            gtk::Orientation::Vertical
+        } else {
+            gtk::Orientation::Horizontal
+        })
+        .spacing(6)
+        .build();
+    card.append(&picture);
+    card.append(&labels);
+    (card, picture)
+}
+
+/// Returns a signal handler that sends the message built from the emitting widget.
+fn clone_sender<W>(
+    sender: &ComponentSender<LibraryWindow>,
+    message: impl Fn(&W) -> LibraryMsg + 'static,
+) -> impl Fn(&W) + 'static {
+    let sender = sender.clone();
+    move |widget| sender.input(message(widget))
+}
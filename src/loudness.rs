@@ -0,0 +1,241 @@
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::UNIX_EPOCH;
+
+use ffmpeg_next::{codec, format, frame, media, ChannelLayout, Error};
+
+use crate::decoder::build_graph;
+use crate::storage;
+
+const FILE: &str = "loudness.ini";
+/// Reference level of ReplayGain 2.0, in LUFS
+const REPLAYGAIN_REFERENCE: f64 = -18.0;
+/// Highest true peak normalization may raise audio to, in dBTP
+const PEAK_CEILING: f64 = -1.0;
+/// Largest gain applied either way, so near silent files are not amplified into noise
+const MAX_GAIN: f64 = 20.0;
+
+/// Loudness of a file's audio as measured by ffmpeg's `ebur128` filter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Loudness {
+    /// Integrated loudness in LUFS
+    pub integrated: f64,
+    /// True peak in dBTP
+    pub true_peak: f64,
+    /// Loudness range in LU
+    pub range: f64,
+    /// Track gain from ReplayGain tags, in dB
+    pub replaygain: Option<f64>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Normalization {
+    #[default]
+    Off,
+    /// ReplayGain track gain, taken from tags or measured against -18 LUFS
+    TrackGain,
+    /// Brings the integrated loudness to a target in LUFS
+    Target(f64),
+}
+
+impl Normalization {
+    /// Parses the identifiers used by the menu and settings: `off`, `track` or a target in LUFS.
+    pub fn parse(id: &str) -> Self {
+        match id {
+            "track" => Self::TrackGain,
+            target => target.parse().map_or(Self::Off, Self::Target),
+        }
+    }
+
+    pub fn id(&self) -> String {
+        match self {
+            Self::Off => String::from("off"),
+            Self::TrackGain => String::from("track"),
+            Self::Target(target) => format!("{target}"),
+        }
+    }
+
+    /// Returns the gain in dB that normalizes audio with the given loudness.
+    pub fn gain(&self, loudness: &Loudness) -> f64 {
+        let gain = match self {
+            Self::Off => return 0.0,
+            Self::TrackGain => loudness
+                .replaygain
+                .unwrap_or(REPLAYGAIN_REFERENCE - loudness.integrated),
+            Self::Target(target) => target - loudness.integrated,
+        };
+        if !gain.is_finite() {
+            return 0.0;
+        }
+        gain.min(PEAK_CEILING - loudness.true_peak)
+            .clamp(-MAX_GAIN, MAX_GAIN)
+    }
+}
+
+fn modified(location: &Path) -> i64 {
+    std::fs::metadata(location)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_secs() as i64)
+}
+
+/// Returns the measurement cached for `location`, unless the file changed since.
+pub fn cached(location: &Path) -> Option<Loudness> {
+    let file = storage::load(&storage::cache_dir("loudness").join(FILE));
+    let group = storage::key_for(location);
+    if file.int64(&group, "modified").ok()? != modified(location) {
+        return None;
+    }
+    Some(Loudness {
+        integrated: file.double(&group, "integrated").ok()?,
+        true_peak: file.double(&group, "true-peak").ok()?,
+        range: file.double(&group, "range").ok()?,
+        replaygain: file.double(&group, "replaygain").ok(),
+    })
+}
+
+pub fn store(location: &Path, loudness: &Loudness) {
+    let path = storage::cache_dir("loudness").join(FILE);
+    let file = storage::load(&path);
+    let group = storage::key_for(location);
+    file.set_int64(&group, "modified", modified(location));
+    file.set_double(&group, "integrated", loudness.integrated);
+    file.set_double(&group, "true-peak", loudness.true_peak);
+    file.set_double(&group, "range", loudness.range);
+    match loudness.replaygain {
+        Some(gain) => file.set_double(&group, "replaygain", gain),
+        None => {
+            let _ = file.remove_key(&group, "replaygain");
+        }
+    }
+    storage::save(&file, &path);
+}
+
+/// Reads a ReplayGain track gain such as `-6.50 dB` from the container or stream tags.
+fn replaygain(input: &format::context::Input, index: usize) -> Option<f64> {
+    let stream = input.stream(index)?;
+    [input.metadata(), stream.metadata()]
+        .iter()
+        .flat_map(|metadata| metadata.iter())
+        .find(|(key, _)| key.eq_ignore_ascii_case("replaygain_track_gain"))
+        .and_then(|(_, value)| value.trim().trim_end_matches("dB").trim().parse().ok())
+}
+
+#[derive(Default)]
+struct Measurement {
+    integrated: Option<f64>,
+    range: Option<f64>,
+    /// Highest linear sample peak over all channels
+    peak: f64,
+}
+
+impl Measurement {
+    /// Reads the running values `ebur128` attaches to each frame.
+    fn update(&mut self, frame: &frame::Audio) {
+        let metadata = frame.metadata();
+        let value = |key: &str| metadata.get(key).and_then(|v| v.parse::<f64>().ok());
+        if let Some(integrated) = value("lavfi.r128.I") {
+            self.integrated = Some(integrated);
+        }
+        if let Some(range) = value("lavfi.r128.LRA") {
+            self.range = Some(range);
+        }
+        for channel in 0..frame.channels() {
+            if let Some(peak) = value(&format!("lavfi.r128.true_peaks_ch{channel}")) {
+                self.peak = self.peak.max(peak);
+            }
+        }
+    }
+}
+
+/// Decodes all audio of `location` through `ebur128` to measure its loudness. This takes a
+/// while for long files, so it should be run off the main thread. Fails with `Error::Exit`
+/// once `cancel` is set.
+pub fn analyze(location: &Path, cancel: &AtomicBool) -> Result<Loudness, Error> {
+    ffmpeg_next::init()?;
+    let mut input = format::input(location)?;
+    let (index, time_base, parameters) = {
+        let stream = input
+            .streams()
+            .best(media::Type::Audio)
+            .ok_or(Error::StreamNotFound)?;
+        (stream.index(), stream.time_base(), stream.parameters())
+    };
+    let replaygain = replaygain(&input, index);
+    let mut decoder = codec::Context::from_parameters(parameters)?
+        .decoder()
+        .audio()?;
+
+    let mut graph = None;
+    let mut measurement = Measurement::default();
+    let mut decoded = frame::Audio::empty();
+    let mut filtered = frame::Audio::empty();
+
+    let mut process = |decoder: &mut codec::decoder::Audio, eof: bool| -> Result<(), Error> {
+        while decoder.receive_frame(&mut decoded).is_ok() {
+            if graph.is_none() {
+                let layout = match decoded.channel_layout().bits() {
+                    0 => ChannelLayout::default(decoded.channels() as i32).bits(),
+                    bits => bits,
+                };
+                let args = format!(
+                    "time_base={time_base}:sample_rate={}:sample_fmt={}:channel_layout=0x{layout:x}",
+                    decoded.rate(),
+                    decoded.format().name(),
+                );
+                graph = Some(build_graph(
+                    "abuffer",
+                    &args,
+                    "abuffersink",
+                    "ebur128=peak=true:metadata=1",
+                )?);
+            }
+            let graph = graph.as_mut().unwrap();
+            decoded.set_pts(decoded.timestamp());
+            graph.get("in").unwrap().source().add(&decoded)?;
+            while graph
+                .get("out")
+                .unwrap()
+                .sink()
+                .frame(&mut filtered)
+                .is_ok()
+            {
+                measurement.update(&filtered);
+            }
+        }
+        if eof {
+            if let Some(graph) = graph.as_mut() {
+                graph.get("in").unwrap().source().flush()?;
+                while graph
+                    .get("out")
+                    .unwrap()
+                    .sink()
+                    .frame(&mut filtered)
+                    .is_ok()
+                {
+                    measurement.update(&filtered);
+                }
+            }
+        }
+        Ok(())
+    };
+
+    for (stream, packet) in input.packets() {
+        if cancel.load(Ordering::Relaxed) {
+            return Err(Error::Exit);
+        }
+        if stream.index() == index && decoder.send_packet(&packet).is_ok() {
+            process(&mut decoder, false)?;
+        }
+    }
+    decoder.send_eof()?;
+    process(&mut decoder, true)?;
+
+    Ok(Loudness {
+        integrated: measurement.integrated.ok_or(Error::InvalidData)?,
+        true_peak: 20.0 * measurement.peak.log10(),
+        range: measurement.range.unwrap_or_default(),
+        replaygain,
+    })
+}
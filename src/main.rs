@@ -1,26 +1,155 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
 use adw::prelude::*;
-use gtk::glib::clone;
+use gtk::{gdk, gio, glib, glib::clone};
 use relm4::{
     actions::{AccelsPlus, RelmAction, RelmActionGroup},
     prelude::*,
 };
 
+pub mod decoder;
+use decoder::{
+    AudioEqualizer, ChannelMode, Deinterlace, Deinterlacer, FieldOrder, OutputLayout, SeekMode,
+    VideoAdjustments,
+};
+
+pub mod playback;
+
 pub mod player;
-use player::{Player, PlayerMsg};
+use player::{Player, PlayerMsg, PlayerOutput, Volume};
+
+pub mod presentation;
+use presentation::{Crop, Fit, Orientation};
+
+pub mod playlist;
+use playlist::Playlist;
+
+pub mod history;
+use history::History;
+
+pub mod file_settings;
+use file_settings::FileSettings;
+
+pub mod settings;
+use settings::Settings;
+
+pub mod storage;
+
+pub mod thumbnail;
+use thumbnail::Thumbnail;
+
+pub mod loudness;
+use loudness::{Loudness, Normalization};
+
+pub mod subtitles;
+
+pub mod artwork;
+
+pub mod visualizer;
+use visualizer::Visualization;
+
+pub mod adjustments;
+use adjustments::{AdjustmentsMsg, AdjustmentsPopover};
+
+pub mod equalizer;
+use equalizer::EqualizerPopover;
+
+pub mod probe;
+
+pub mod report;
+
+pub mod timecode;
+use report::BatchFormat;
+use timecode::FrameRate;
+
+pub mod library;
+
+pub mod library_window;
+use library_window::{LibraryMsg, LibraryOutput, LibraryWindow};
 
 pub mod media_info;
 use media_info::{MediaInfoMsg, MediaInfoWindow};
 
+pub mod csv;
+
+pub mod ffmetadata;
+
+pub mod bookmarks;
+
+pub mod bookmarks_window;
+use bookmarks_window::{BookmarksMsg, BookmarksOutput, BookmarksWindow};
+
+pub mod review;
+use review::ReviewPoint;
+
+pub mod review_window;
+use review_window::{ReviewMsg, ReviewOutput, ReviewWindow};
+
+pub mod chapters;
+
+pub mod chapters_window;
+use chapters_window::{ChaptersMsg, ChaptersOutput, ChaptersWindow};
+
 pub mod about;
 use about::{AboutDialog, AboutDialogMsg};
 
 pub mod shortcuts;
 use shortcuts::{Shortcuts, ShortcutsMsg};
 
+/// Command line options.
+#[derive(Debug, Default)]
+struct Options {
+    locations: Vec<PathBuf>,
+    /// Custom filters for this session only, overriding the saved ones
+    video_filter: Option<String>,
+    audio_filter: Option<String>,
+    /// Writes a report of the locations in this format instead of opening a window
+    report: Option<String>,
+    output: Option<PathBuf>,
+}
+
 struct App {
     file: Option<String>,
+    playlist: Playlist,
+    history: History,
+    settings: Settings,
+    /// Settings as last written, and whether a write is scheduled
+    saved_settings: Settings,
+    saving_settings: bool,
+    /// Picture adjustments and delays of the current file, as set and as last written, and
+    /// where they are written
+    file_settings: FileSettings,
+    saved_file_settings: FileSettings,
+    file_settings_location: Option<PathBuf>,
+    /// Measured loudness of the current file
+    loudness: Option<Loudness>,
+    /// Cancels the loudness measurement running for the current file
+    measuring: Option<Arc<AtomicBool>>,
+    /// Channels of the current file's audio and of the output device
+    channels: Option<(u16, u16)>,
+    /// Duration and video frame rate of the current file, once prepared
+    duration: i64,
+    frame_rate: Option<FrameRate>,
+    /// Aspect ratio menu choice currently applied
+    aspect: String,
+    /// Custom filter chains currently applied
+    video_filter: String,
+    audio_filter: String,
+    toasts: adw::ToastOverlay,
+    recent_menu: gio::Menu,
+    /// Popover of the main menu, which draws the thumbnails of recent files
+    main_popover: gtk::PopoverMenu,
     player: Controller<Player>,
+    adjustments: Controller<AdjustmentsPopover>,
+    equalizer: Controller<EqualizerPopover>,
     media_info_window: AsyncController<MediaInfoWindow>,
+    bookmarks_window: AsyncController<BookmarksWindow>,
+    review_window: AsyncController<ReviewWindow>,
+    chapters_window: AsyncController<ChaptersWindow>,
+    library_window: Controller<LibraryWindow>,
     about_dialog: Controller<AboutDialog>,
     shortcuts_window: Controller<Shortcuts>,
 }
@@ -28,39 +157,384 @@ struct App {
 #[derive(Debug)]
 pub enum AppMsg {
     SelectFile,
+    OpenUrl,
+    Open(Vec<PathBuf>),
+    Paste,
+    Next,
+    Position(i64),
+    VideoSize(i32, i32),
+    SetAutoResize(bool),
+    Aspect(String),
+    Crop(String),
+    Fit(String),
+    ZoomIn,
+    ZoomOut,
+    ZoomReset,
+    Pan(f64, f64),
+    Orient(Orientation),
+    ResetOrientation,
+    Orientation(Orientation),
+    Deinterlace(String),
+    Deinterlacer(String),
+    FieldOrder(String),
+    Adjust(VideoAdjustments),
+    Equalize(AudioEqualizer),
+    EditFilters,
+    SetVideoFilter(String),
+    SetAudioFilter(String),
+    Warning(String),
+    ShiftAudioDelay(i64),
+    ShiftSubtitleDelay(i64),
+    EditDelays,
+    TogglePinned,
+    RemoveMissing,
+    ClearHistory,
     OpenMediaInfo,
+    OpenLibrary,
+    LibraryFolders(Vec<PathBuf>),
     PlayPause,
     Fullscreen,
     SeekForwards,
     SeekBackwards,
+    SeekShortForwards,
+    SeekShortBackwards,
+    SeekLongForwards,
+    SeekLongBackwards,
+    SetShortSeek(String),
+    SetLongSeek(String),
+    SetSeekMode(String),
+    SeekPercent(String),
+    GoTo,
+    Timing(i64, Option<FrameRate>),
+    SeekTo(i64),
+    AddBookmark,
+    /// Bookmarks a position of the current file
+    Bookmark(i64),
+    OpenBookmarks,
+    NextBookmark,
+    PreviousBookmark,
+    /// Bookmarks of the current file changed
+    Marks(Vec<(i64, String)>),
+    /// Asks the player for the current frame, for a review comment
+    Review(ReviewPoint),
+    /// Frame taken by the player for a review comment
+    ReviewAt(ReviewPoint, i64),
+    OpenReview,
+    SetReviewAuthor(String),
+    OpenChapters,
+    /// Asks the player for its position, for editing chapters
+    RequestPlayhead,
+    Playhead(i64),
     VolumeUp,
     VolumeDown,
+    ToggleMute,
+    SetVolumeStep(String),
+    SetBoost(bool),
+    Volume(Volume),
+    Normalization(String),
+    OutputLayout(String),
+    CenterBoost(String),
+    ChannelMode(String),
+    Channels(u16, u16),
+    Visualization(String),
+    /// Writes the settings and those of the current file once they have stopped changing for
+    /// a moment
+    SaveSettings,
     Quit,
 }
 
+#[derive(Debug)]
+pub enum AppCommand {
+    Thumbnail(PathBuf, Option<Thumbnail>),
+    /// Measurement of a file, with the flag that cancels it
+    Loudness(PathBuf, Arc<AtomicBool>, Option<Loudness>),
+}
+
+/// How long settings must stay unchanged before they are written
+const SAVE_DELAY: Duration = Duration::from_secs(1);
+/// Pan distance per key press, as a fraction of the video's overflow
+const PAN_STEP: f64 = 0.1;
+/// Audio and subtitle delay change per key press, in microseconds
+const DELAY_STEP: i64 = 50_000;
+
 relm4::new_action_group!(WindowActionGroup, "win");
+relm4::new_action_group!(AppActionGroup, "app");
+
+relm4::new_stateful_action!(
+    SetVideoFilter,
+    AppActionGroup,
+    "set-video-filter",
+    String,
+    ()
+);
+relm4::new_stateful_action!(
+    SetAudioFilter,
+    AppActionGroup,
+    "set-audio-filter",
+    String,
+    ()
+);
 
 relm4::new_stateless_action!(Open, WindowActionGroup, "open");
+relm4::new_stateless_action!(OpenUrl, WindowActionGroup, "openurl");
+relm4::new_stateless_action!(Paste, WindowActionGroup, "paste");
+relm4::new_stateful_action!(Recent, WindowActionGroup, "recent", String, ());
+relm4::new_stateless_action!(Pin, WindowActionGroup, "pin");
+relm4::new_stateless_action!(RemoveMissing, WindowActionGroup, "removemissing");
+relm4::new_stateless_action!(ClearHistory, WindowActionGroup, "clearhistory");
+relm4::new_stateful_action!(AutoResize, WindowActionGroup, "autoresize", (), bool);
+relm4::new_stateful_action!(Aspect, WindowActionGroup, "aspect", String, String);
+relm4::new_stateful_action!(CropMode, WindowActionGroup, "crop", String, String);
+relm4::new_stateful_action!(FitMode, WindowActionGroup, "fit", String, String);
+relm4::new_stateless_action!(ZoomIn, WindowActionGroup, "zoomin");
+relm4::new_stateless_action!(ZoomOut, WindowActionGroup, "zoomout");
+relm4::new_stateless_action!(ZoomReset, WindowActionGroup, "zoomreset");
+relm4::new_stateless_action!(PanLeft, WindowActionGroup, "panleft");
+relm4::new_stateless_action!(PanRight, WindowActionGroup, "panright");
+relm4::new_stateless_action!(PanUp, WindowActionGroup, "panup");
+relm4::new_stateless_action!(PanDown, WindowActionGroup, "pandown");
+relm4::new_stateless_action!(Rotate90, WindowActionGroup, "rotate90");
+relm4::new_stateless_action!(Rotate180, WindowActionGroup, "rotate180");
+relm4::new_stateless_action!(Rotate270, WindowActionGroup, "rotate270");
+relm4::new_stateless_action!(FlipHorizontal, WindowActionGroup, "fliphorizontal");
+relm4::new_stateless_action!(FlipVertical, WindowActionGroup, "flipvertical");
+relm4::new_stateless_action!(ResetOrientation, WindowActionGroup, "resetorientation");
+relm4::new_stateful_action!(
+    DeinterlaceMode,
+    WindowActionGroup,
+    "deinterlace",
+    String,
+    String
+);
+relm4::new_stateful_action!(
+    DeinterlaceFilter,
+    WindowActionGroup,
+    "deinterlacer",
+    String,
+    String
+);
+relm4::new_stateful_action!(
+    FieldOrderMode,
+    WindowActionGroup,
+    "fieldorder",
+    String,
+    String
+);
+relm4::new_stateless_action!(Filters, WindowActionGroup, "filters");
+relm4::new_stateless_action!(Delays, WindowActionGroup, "delays");
+relm4::new_stateless_action!(AudioDelayUp, WindowActionGroup, "audiodelayup");
+relm4::new_stateless_action!(AudioDelayDown, WindowActionGroup, "audiodelaydown");
+relm4::new_stateless_action!(SubtitleDelayUp, WindowActionGroup, "subtitledelayup");
+relm4::new_stateless_action!(SubtitleDelayDown, WindowActionGroup, "subtitledelaydown");
 relm4::new_stateless_action!(About, WindowActionGroup, "about");
 relm4::new_stateless_action!(Info, WindowActionGroup, "mediainfo");
+relm4::new_stateless_action!(Library, WindowActionGroup, "library");
 relm4::new_stateless_action!(Shortcut, WindowActionGroup, "shortcuts");
 relm4::new_stateless_action!(Quit, WindowActionGroup, "quit");
 relm4::new_stateless_action!(PlayPause, WindowActionGroup, "playpause");
 relm4::new_stateless_action!(Fullscreen, WindowActionGroup, "fullscreen");
 relm4::new_stateless_action!(SeekForwards, WindowActionGroup, "seekforwards");
 relm4::new_stateless_action!(SeekBackwards, WindowActionGroup, "seekbackwards");
+relm4::new_stateless_action!(SeekShortForwards, WindowActionGroup, "seekshortforwards");
+relm4::new_stateless_action!(SeekShortBackwards, WindowActionGroup, "seekshortbackwards");
+relm4::new_stateless_action!(SeekLongForwards, WindowActionGroup, "seeklongforwards");
+relm4::new_stateless_action!(SeekLongBackwards, WindowActionGroup, "seeklongbackwards");
+relm4::new_stateful_action!(SeekPercent, WindowActionGroup, "seekpercent", String, ());
+relm4::new_stateless_action!(GoTo, WindowActionGroup, "goto");
+relm4::new_stateless_action!(AddBookmark, WindowActionGroup, "addbookmark");
+relm4::new_stateless_action!(Bookmarks, WindowActionGroup, "bookmarks");
+relm4::new_stateless_action!(NextBookmark, WindowActionGroup, "nextbookmark");
+relm4::new_stateless_action!(PreviousBookmark, WindowActionGroup, "previousbookmark");
+relm4::new_stateless_action!(Review, WindowActionGroup, "review");
+relm4::new_stateless_action!(MarkIn, WindowActionGroup, "markin");
+relm4::new_stateless_action!(MarkOut, WindowActionGroup, "markout");
+relm4::new_stateless_action!(AddComment, WindowActionGroup, "addcomment");
+relm4::new_stateless_action!(Chapters, WindowActionGroup, "chapters");
+relm4::new_stateful_action!(ShortSeek, WindowActionGroup, "shortseek", String, String);
+relm4::new_stateful_action!(LongSeek, WindowActionGroup, "longseek", String, String);
+relm4::new_stateful_action!(SeekAccuracy, WindowActionGroup, "seekmode", String, String);
 relm4::new_stateless_action!(VolumeUp, WindowActionGroup, "volumeup");
 relm4::new_stateless_action!(VolumeDown, WindowActionGroup, "volumedown");
+relm4::new_stateless_action!(Mute, WindowActionGroup, "mute");
+relm4::new_stateful_action!(VolumeStep, WindowActionGroup, "volumestep", String, String);
+relm4::new_stateful_action!(VolumeBoost, WindowActionGroup, "volumeboost", (), bool);
+relm4::new_stateful_action!(
+    OutputChannels,
+    WindowActionGroup,
+    "outputchannels",
+    String,
+    String
+);
+relm4::new_stateful_action!(
+    CenterBoost,
+    WindowActionGroup,
+    "centerboost",
+    String,
+    String
+);
+relm4::new_stateful_action!(
+    ChannelRoute,
+    WindowActionGroup,
+    "channelmode",
+    String,
+    String
+);
+relm4::new_stateful_action!(
+    VisualizerMode,
+    WindowActionGroup,
+    "visualizer",
+    String,
+    String
+);
+relm4::new_stateful_action!(
+    NormalizationMode,
+    WindowActionGroup,
+    "normalization",
+    String,
+    String
+);
 
 #[relm4::component(async)]
 impl AsyncComponent for App {
     type Input = AppMsg;
     type Output = ();
-    type Init = u8;
-    type CommandOutput = ();
+    type Init = Options;
+    type CommandOutput = AppCommand;
 
     menu! {
         main_menu: {
+            section! {
+                "Open URL…" => OpenUrl,
+                "Library" => Library,
+                "Resize Window to Video" => AutoResize,
+            },
+            section! {
+                "Playback" {
+                    "Go to…" => GoTo,
+                    section! {
+                        "Add Bookmark" => AddBookmark,
+                        "Next Bookmark" => NextBookmark,
+                        "Previous Bookmark" => PreviousBookmark,
+                        "Bookmarks…" => Bookmarks,
+                    },
+                    section! {
+                        "Mark In" => MarkIn,
+                        "Mark Out" => MarkOut,
+                        "Add Comment" => AddComment,
+                        "Review…" => Review,
+                    },
+                    section! {
+                        "Chapters…" => Chapters,
+                    },
+                    "Short Seek Step" {
+                        "1 Second" => ShortSeek(String::from("1")),
+                        "5 Seconds" => ShortSeek(String::from("5")),
+                        "10 Seconds" => ShortSeek(String::from("10")),
+                    },
+                    "Long Seek Step" {
+                        "30 Seconds" => LongSeek(String::from("30")),
+                        "1 Minute" => LongSeek(String::from("60")),
+                        "5 Minutes" => LongSeek(String::from("300")),
+                    },
+                    "Seek Mode" {
+                        "Exact" => SeekAccuracy(String::from("exact")),
+                        "Fast (Keyframes)" => SeekAccuracy(String::from("fast")),
+                    },
+                },
+                "Video" {
+                    "Aspect Ratio" {
+                        "Auto" => Aspect(String::from("auto")),
+                        "4:3" => Aspect(String::from("4:3")),
+                        "16:9" => Aspect(String::from("16:9")),
+                        "2.35:1" => Aspect(String::from("2.35:1")),
+                        "Custom…" => Aspect(String::from("custom")),
+                    },
+                    "Crop" {
+                        "None" => CropMode(String::from("none")),
+                        "Remove Black Bars" => CropMode(String::from("auto")),
+                        "4:3" => CropMode(String::from("4:3")),
+                        "16:9" => CropMode(String::from("16:9")),
+                        "2.35:1" => CropMode(String::from("2.35:1")),
+                    },
+                    "Scaling" {
+                        "Fit" => FitMode(String::from("fit")),
+                        "Fill" => FitMode(String::from("fill")),
+                        "Stretch" => FitMode(String::from("stretch")),
+                        "Original Size" => FitMode(String::from("original")),
+                    },
+                    "Rotation" {
+                        "Rotate 90°" => Rotate90,
+                        "Rotate 180°" => Rotate180,
+                        "Rotate 270°" => Rotate270,
+                        "Flip Horizontally" => FlipHorizontal,
+                        "Flip Vertically" => FlipVertical,
+                        "Reset" => ResetOrientation,
+                    },
+                    "Deinterlace" {
+                        section! {
+                            "Off" => DeinterlaceMode(String::from("off")),
+                            "Interlaced Frames" => DeinterlaceMode(String::from("auto")),
+                            "All Frames" => DeinterlaceMode(String::from("forced")),
+                        },
+                        section! {
+                            "Yadif" => DeinterlaceFilter(String::from("yadif")),
+                            "Bwdif" => DeinterlaceFilter(String::from("bwdif")),
+                        },
+                        section! {
+                            "Automatic Field Order" => FieldOrderMode(String::from("auto")),
+                            "Top Field First" => FieldOrderMode(String::from("tff")),
+                            "Bottom Field First" => FieldOrderMode(String::from("bff")),
+                        },
+                    },
+                    section! {
+                        "Zoom In" => ZoomIn,
+                        "Zoom Out" => ZoomOut,
+                        "Reset Zoom" => ZoomReset,
+                    },
+                },
+                "Audio" {
+                    "Mute" => Mute,
+                    "Allow Volume Boost" => VolumeBoost,
+                    "Volume Step" {
+                        "1%" => VolumeStep(String::from("1")),
+                        "5%" => VolumeStep(String::from("5")),
+                        "10%" => VolumeStep(String::from("10")),
+                    },
+                    "Loudness Normalization" {
+                        "Off" => NormalizationMode(String::from("off")),
+                        "ReplayGain Track Gain" => NormalizationMode(String::from("track")),
+                        "Target -14 LUFS" => NormalizationMode(String::from("-14")),
+                        "Target -16 LUFS" => NormalizationMode(String::from("-16")),
+                        "Target -23 LUFS" => NormalizationMode(String::from("-23")),
+                    },
+                    "Output Channels" {
+                        section! {
+                            "Automatic" => OutputChannels(String::from("auto")),
+                            "Stereo" => OutputChannels(String::from("stereo")),
+                            "Mono" => OutputChannels(String::from("mono")),
+                            "5.1 Surround" => OutputChannels(String::from("5.1")),
+                        },
+                        section! {
+                            "Normal" => ChannelRoute(String::from("normal")),
+                            "Left Channel Only" => ChannelRoute(String::from("left")),
+                            "Right Channel Only" => ChannelRoute(String::from("right")),
+                            "Swap Left and Right" => ChannelRoute(String::from("swap")),
+                        },
+                    },
+                    "Visualizer" {
+                        "Off" => VisualizerMode(String::from("off")),
+                        "Spectrum" => VisualizerMode(String::from("spectrum")),
+                        "Waveform" => VisualizerMode(String::from("waveform")),
+                    },
+                    "Center Boost" {
+                        "Off" => CenterBoost(String::from("0")),
+                        "+3 dB" => CenterBoost(String::from("3")),
+                        "+6 dB" => CenterBoost(String::from("6")),
+                    },
+                },
+                "Custom Filters…" => Filters,
+                "Synchronization…" => Delays,
+            },
             section! {
                 "About" => About,
                 "Keyboard Shortcuts" => Shortcut
@@ -71,8 +545,14 @@ impl AsyncComponent for App {
     view! {
         window = adw::Window {
             set_title: Some("Simple Video Player"),
-            set_default_width: 800,
-            set_default_height: 450,
+            set_default_width: model.settings.width,
+            set_default_height: model.settings.height,
+            set_maximized: model.settings.maximized,
+            set_fullscreened: model.settings.fullscreen,
+            connect_close_request[sender] => move |_| {
+                sender.input(AppMsg::Quit);
+                glib::Propagation::Stop
+            },
             gtk::Box {
                 set_orientation: gtk::Orientation::Vertical,
                 adw::HeaderBar {
@@ -82,7 +562,17 @@ impl AsyncComponent for App {
                     },
                     pack_end = &gtk::MenuButton {
                         set_icon_name: "open-menu-symbolic",
-                        set_menu_model: Some(&main_menu),
+                        set_popover: Some(&model.main_popover),
+                    },
+                    pack_end = &gtk::MenuButton {
+                        set_icon_name: "audio-speakers-symbolic",
+                        set_tooltip_text: Some("Equalizer"),
+                        set_popover: Some(model.equalizer.widget()),
+                    },
+                    pack_end = &gtk::MenuButton {
+                        set_icon_name: "display-brightness-symbolic",
+                        set_tooltip_text: Some("Video Adjustments"),
+                        set_popover: Some(model.adjustments.widget()),
                     },
                     pack_end = &gtk::Button {
                         set_icon_name: "documentinfo-symbolic",
@@ -92,28 +582,106 @@ impl AsyncComponent for App {
                         connect_clicked => AppMsg::OpenMediaInfo,
                     },
                 },
-                #[local]
-                player_box -> gtk::Box {},
+                #[local_ref]
+                toasts -> adw::ToastOverlay {},
             }
         }
     }
 
     async fn init(
-        _init: Self::Init,
+        init: Self::Init,
         root: Self::Root,
         sender: AsyncComponentSender<Self>,
     ) -> AsyncComponentParts<Self> {
-        let media_info_broker: relm4::MessageBroker<MediaInfoMsg> = relm4::MessageBroker::new();
         let about_dialog_broker: relm4::MessageBroker<AboutDialogMsg> = relm4::MessageBroker::new();
         let shortcuts_broker: relm4::MessageBroker<ShortcutsMsg> = relm4::MessageBroker::new();
 
-        let model = App {
+        let settings = Settings::load();
+        let volume = settings.volume;
+        let equalizer = settings.equalizer;
+        let routing = settings.routing;
+        let visualization = settings.visualization;
+        let library_folders = settings.library_folders.clone();
+        let review_author = settings.review_author.clone();
+        let mut model = App {
             file: None,
-            player: Player::builder().launch(()).detach(),
+            playlist: Playlist::default(),
+            history: History::load(),
+            saved_settings: settings.clone(),
+            saving_settings: false,
+            settings,
+            file_settings: FileSettings::default(),
+            saved_file_settings: FileSettings::default(),
+            file_settings_location: None,
+            loudness: None,
+            measuring: None,
+            channels: None,
+            duration: 0,
+            frame_rate: None,
+            aspect: String::from("auto"),
+            video_filter: String::new(),
+            audio_filter: String::new(),
+            toasts: adw::ToastOverlay::new(),
+            recent_menu: gio::Menu::new(),
+            main_popover: gtk::PopoverMenu::from_model(None::<&gio::MenuModel>),
+            player: Player::builder().launch(volume).forward(
+                sender.input_sender(),
+                |msg| match msg {
+                    PlayerOutput::Ended => AppMsg::Next,
+                    PlayerOutput::Position(position) => AppMsg::Position(position),
+                    PlayerOutput::VideoSize(width, height) => AppMsg::VideoSize(width, height),
+                    PlayerOutput::Orientation(orientation) => AppMsg::Orientation(orientation),
+                    PlayerOutput::Warning(message) => AppMsg::Warning(message),
+                    PlayerOutput::Volume(volume) => AppMsg::Volume(volume),
+                    PlayerOutput::Channels(source, output) => AppMsg::Channels(source, output),
+                    PlayerOutput::Timing(duration, frame_rate) => {
+                        AppMsg::Timing(duration, frame_rate)
+                    }
+                    PlayerOutput::Bookmark(position) => AppMsg::Bookmark(position),
+                    PlayerOutput::Review(point, position) => AppMsg::ReviewAt(point, position),
+                    PlayerOutput::Playhead(position) => AppMsg::Playhead(position),
+                },
+            ),
+            adjustments: AdjustmentsPopover::builder()
+                .launch(())
+                .forward(sender.input_sender(), AppMsg::Adjust),
+            equalizer: EqualizerPopover::builder()
+                .launch(equalizer)
+                .forward(sender.input_sender(), AppMsg::Equalize),
             media_info_window: MediaInfoWindow::builder()
                 .transient_for(root.clone())
-                .launch_with_broker(root.clone(), &media_info_broker)
+                .launch(root.clone())
                 .detach(),
+            bookmarks_window: BookmarksWindow::builder()
+                .transient_for(root.clone())
+                .launch(root.clone())
+                .forward(sender.input_sender(), |msg| match msg {
+                    BookmarksOutput::AddCurrent => AppMsg::AddBookmark,
+                    BookmarksOutput::SeekTo(position) => AppMsg::SeekTo(position),
+                    BookmarksOutput::Changed(marks) => AppMsg::Marks(marks),
+                }),
+            review_window: ReviewWindow::builder()
+                .transient_for(root.clone())
+                .launch((root.clone(), review_author))
+                .forward(sender.input_sender(), |msg| match msg {
+                    ReviewOutput::AddCurrent => AppMsg::Review(ReviewPoint::Comment),
+                    ReviewOutput::SeekTo(position) => AppMsg::SeekTo(position),
+                    ReviewOutput::Author(author) => AppMsg::SetReviewAuthor(author),
+                }),
+            chapters_window: ChaptersWindow::builder()
+                .transient_for(root.clone())
+                .launch(root.clone())
+                .forward(sender.input_sender(), |msg| match msg {
+                    ChaptersOutput::Playhead => AppMsg::RequestPlayhead,
+                    ChaptersOutput::SeekTo(position) => AppMsg::SeekTo(position),
+                }),
+            library_window: LibraryWindow::builder()
+                .transient_for(root.clone())
+                .launch(library_folders)
+                .forward(sender.input_sender(), |msg| match msg {
+                    LibraryOutput::Open(path) => AppMsg::Open(vec![path]),
+                    LibraryOutput::Folders(folders) => AppMsg::LibraryFolders(folders),
+                }),
             about_dialog: AboutDialog::builder()
                 .transient_for(root.clone())
                 .launch_with_broker((), &about_dialog_broker)
@@ -124,23 +692,127 @@ impl AsyncComponent for App {
                 .detach(),
         };
 
-        let player_box = model.player.widget().to_owned();
+        model.media_info_window.widget().set_default_size(
+            model.settings.media_info_width,
+            model.settings.media_info_height,
+        );
+
+        model
+            .player
+            .sender()
+            .send(PlayerMsg::SetEqualizer(equalizer))
+            .unwrap();
+        model
+            .player
+            .sender()
+            .send(PlayerMsg::SetRouting(routing))
+            .unwrap();
+        model
+            .player
+            .sender()
+            .send(PlayerMsg::SetVisualization(visualization))
+            .unwrap();
+        model
+            .player
+            .sender()
+            .send(PlayerMsg::SetSeekMode(model.settings.seek_mode))
+            .unwrap();
+
+        model.toasts.set_child(Some(model.player.widget()));
+        let toasts = &model.toasts;
         let widgets = view_output!();
 
+        let video_filter = init
+            .video_filter
+            .unwrap_or_else(|| model.settings.video_filter.clone());
+        model.apply_video_filter(video_filter);
+        let audio_filter = init
+            .audio_filter
+            .unwrap_or_else(|| model.settings.audio_filter.clone());
+        model.apply_audio_filter(audio_filter);
+
+        let recent_section = gio::Menu::new();
+        recent_section.append_submenu(Some("Recent"), &model.recent_menu);
+        main_menu.prepend_section(None, &recent_section);
+        model.main_popover.set_menu_model(Some(&main_menu));
+        model.update_recent_menu();
+
+        let drop_target = gtk::DropTarget::new(glib::Type::INVALID, gdk::DragAction::COPY);
+        drop_target.set_types(&[gdk::FileList::static_type(), glib::Type::STRING]);
+        drop_target.connect_drop(clone!(
+            #[strong]
+            sender,
+            move |_, value, _, _| {
+                let paths = if let Ok(files) = value.get::<gdk::FileList>() {
+                    files.files().iter().map(file_location).collect()
+                } else if let Ok(text) = value.get::<String>() {
+                    text_locations(&text)
+                } else {
+                    return false;
+                };
+                sender.input(AppMsg::Open(paths));
+                true
+            }
+        ));
+        model.player.widget().add_controller(drop_target);
+
+        if !init.locations.is_empty() {
+            sender.input(AppMsg::Open(init.locations));
+        }
+
         let app = relm4::main_application();
         let mut group = RelmActionGroup::<WindowActionGroup>::new();
 
+        // Application actions are exported over D-Bus, so running instances can be controlled
+        // with `gapplication action`
+        let mut app_group = RelmActionGroup::<AppActionGroup>::new();
+        app_group.add_action::<SetVideoFilter>(RelmAction::new_with_target_value(clone!(
+            #[strong]
+            sender,
+            move |_, filter: String| {
+                sender.input(AppMsg::SetVideoFilter(filter));
+            }
+        )));
+        app_group.add_action::<SetAudioFilter>(RelmAction::new_with_target_value(clone!(
+            #[strong]
+            sender,
+            move |_, filter: String| {
+                sender.input(AppMsg::SetAudioFilter(filter));
+            }
+        )));
+        app_group.register_for_main_application();
+
         app.set_accelerators_for_action::<Open>(&["<Ctrl>O"]);
-        app.set_accelerators_for_action::<About>(&["<Ctrl>A"]);
+        app.set_accelerators_for_action::<OpenUrl>(&["<Ctrl>L"]);
+        app.set_accelerators_for_action::<ZoomIn>(&["<Ctrl>plus", "<Ctrl>equal"]);
+        app.set_accelerators_for_action::<ZoomOut>(&["<Ctrl>minus"]);
+        app.set_accelerators_for_action::<ZoomReset>(&["<Ctrl>0"]);
+        app.set_accelerators_for_action::<PanLeft>(&["<Alt>Left"]);
+        app.set_accelerators_for_action::<PanRight>(&["<Alt>Right"]);
+        app.set_accelerators_for_action::<PanUp>(&["<Alt>Up"]);
+        app.set_accelerators_for_action::<PanDown>(&["<Alt>Down"]);
+        app.set_accelerators_for_action::<Rotate90>(&["<Ctrl>R"]);
+        app.set_accelerators_for_action::<Rotate270>(&["<Ctrl><Shift>R"]);
         app.set_accelerators_for_action::<Shortcut>(&["<Ctrl>question"]);
         app.set_accelerators_for_action::<Info>(&["<Ctrl>I"]);
+        app.set_accelerators_for_action::<Library>(&["<Ctrl><Shift>L"]);
         app.set_accelerators_for_action::<Quit>(&["<Ctrl>Q"]);
-        app.set_accelerators_for_action::<PlayPause>(&["space"]);
-        app.set_accelerators_for_action::<Fullscreen>(&["F"]);
-        app.set_accelerators_for_action::<SeekForwards>(&["Right"]);
-        app.set_accelerators_for_action::<SeekBackwards>(&["Left"]);
-        app.set_accelerators_for_action::<VolumeUp>(&["Up"]);
-        app.set_accelerators_for_action::<VolumeDown>(&["Down"]);
+        app.set_accelerators_for_action::<GoTo>(&["<Ctrl>G"]);
+        app.set_accelerators_for_action::<AddBookmark>(&["<Ctrl>B"]);
+        app.set_accelerators_for_action::<Bookmarks>(&["<Ctrl><Shift>B"]);
+        app.set_accelerators_for_action::<Review>(&["<Ctrl>E"]);
+        app.set_accelerators_for_action::<AddComment>(&["<Ctrl>M"]);
+        app.set_accelerators_for_action::<Chapters>(&["<Ctrl><Shift>C"]);
+        set_editing_accels(true);
+        // Single keys, paste and the other editing keys would otherwise be taken from text
+        // fields, such as those of the Go to and Open URL dialogs
+        root.connect_focus_widget_notify(|window| {
+            set_editing_accels(
+                !window
+                    .focus_widget()
+                    .is_some_and(|w| w.is::<gtk::Editable>()),
+            );
+        });
 
         group.add_action::<Open>(RelmAction::new_stateless(clone!(
             #[strong]
@@ -150,139 +822,1824 @@ impl AsyncComponent for App {
             }
         )));
 
-        group.add_action::<About>(RelmAction::new_stateless(move |_| {
-            about_dialog_broker.send(AboutDialogMsg::Show);
-        }));
+        group.add_action::<OpenUrl>(RelmAction::new_stateless(clone!(
+            #[strong]
+            sender,
+            move |_| {
+                sender.input(AppMsg::OpenUrl);
+            }
+        )));
 
-        group.add_action::<Info>(RelmAction::new_stateless(move |_| {
-            media_info_broker.send(MediaInfoMsg::Show);
-        }));
+        group.add_action::<Paste>(RelmAction::new_stateless(clone!(
+            #[strong]
+            sender,
+            move |_| {
+                sender.input(AppMsg::Paste);
+            }
+        )));
 
-        group.add_action::<Shortcut>(RelmAction::new_stateless(move |_| {
-            shortcuts_broker.send(ShortcutsMsg::Show);
-        }));
+        group.add_action::<Recent>(RelmAction::new_with_target_value(clone!(
+            #[strong]
+            sender,
+            move |_, location: String| {
+                sender.input(AppMsg::Open(vec![PathBuf::from(location)]));
+            }
+        )));
 
-        group.add_action::<Quit>(RelmAction::new_stateless(clone!(
+        group.add_action::<Pin>(RelmAction::new_stateless(clone!(
             #[strong]
             sender,
             move |_| {
-                sender.input(AppMsg::Quit);
+                sender.input(AppMsg::TogglePinned);
             }
         )));
 
-        group.add_action::<PlayPause>(RelmAction::new_stateless(clone!(
+        group.add_action::<RemoveMissing>(RelmAction::new_stateless(clone!(
             #[strong]
             sender,
             move |_| {
-                sender.input(AppMsg::PlayPause);
+                sender.input(AppMsg::RemoveMissing);
             }
         )));
 
-        group.add_action::<Fullscreen>(RelmAction::new_stateless(clone!(
+        group.add_action::<ClearHistory>(RelmAction::new_stateless(clone!(
             #[strong]
             sender,
             move |_| {
-                sender.input(AppMsg::Fullscreen);
+                sender.input(AppMsg::ClearHistory);
             }
         )));
 
-        group.add_action::<SeekForwards>(RelmAction::new_stateless(clone!(
+        group.add_action::<AutoResize>(RelmAction::new_stateful(
+            &model.settings.auto_resize,
+            clone!(
+                #[strong]
+                sender,
+                move |_, state: &mut bool| {
+                    *state = !*state;
+                    sender.input(AppMsg::SetAutoResize(*state));
+                }
+            ),
+        ));
+
+        group.add_action::<Aspect>(RelmAction::new_stateful_with_target_value(
+            &String::from("auto"),
+            clone!(
+                #[strong]
+                sender,
+                move |_, state: &mut String, value: String| {
+                    *state = value.clone();
+                    sender.input(AppMsg::Aspect(value));
+                }
+            ),
+        ));
+
+        group.add_action::<CropMode>(RelmAction::new_stateful_with_target_value(
+            &String::from("none"),
+            clone!(
+                #[strong]
+                sender,
+                move |_, state: &mut String, value: String| {
+                    *state = value.clone();
+                    sender.input(AppMsg::Crop(value));
+                }
+            ),
+        ));
+
+        group.add_action::<FitMode>(RelmAction::new_stateful_with_target_value(
+            &String::from("fit"),
+            clone!(
+                #[strong]
+                sender,
+                move |_, state: &mut String, value: String| {
+                    *state = value.clone();
+                    sender.input(AppMsg::Fit(value));
+                }
+            ),
+        ));
+
+        group.add_action::<ZoomIn>(RelmAction::new_stateless(clone!(
             #[strong]
             sender,
             move |_| {
-                sender.input(AppMsg::SeekForwards);
+                sender.input(AppMsg::ZoomIn);
             }
         )));
 
-        group.add_action::<SeekBackwards>(RelmAction::new_stateless(clone!(
+        group.add_action::<ZoomOut>(RelmAction::new_stateless(clone!(
             #[strong]
             sender,
             move |_| {
-                sender.input(AppMsg::SeekBackwards);
+                sender.input(AppMsg::ZoomOut);
             }
         )));
 
-        group.add_action::<VolumeUp>(RelmAction::new_stateless(clone!(
+        group.add_action::<ZoomReset>(RelmAction::new_stateless(clone!(
             #[strong]
             sender,
             move |_| {
-                sender.input(AppMsg::VolumeUp);
+                sender.input(AppMsg::ZoomReset);
             }
         )));
 
-        group.add_action::<VolumeDown>(RelmAction::new_stateless(move |_| {
-            sender.input(AppMsg::VolumeDown);
-        }));
+        group.add_action::<PanLeft>(RelmAction::new_stateless(clone!(
+            #[strong]
+            sender,
+            move |_| {
+                sender.input(AppMsg::Pan(PAN_STEP, 0.0));
+            }
+        )));
 
-        widgets
-            .window
-            .insert_action_group("win", Some(&group.into_action_group()));
+        group.add_action::<PanRight>(RelmAction::new_stateless(clone!(
+            #[strong]
+            sender,
+            move |_| {
+                sender.input(AppMsg::Pan(-PAN_STEP, 0.0));
+            }
+        )));
 
-        AsyncComponentParts { model, widgets }
-    }
+        group.add_action::<PanUp>(RelmAction::new_stateless(clone!(
+            #[strong]
+            sender,
+            move |_| {
+                sender.input(AppMsg::Pan(0.0, PAN_STEP));
+            }
+        )));
 
-    async fn update(
-        &mut self,
-        msg: AppMsg,
-        _sender: AsyncComponentSender<Self>,
-        root: &Self::Root,
-    ) {
-        match msg {
-            AppMsg::SelectFile => {
-                let dialog = rfd::AsyncFileDialog::new()
-                    .set_title("Select a Video")
-                    .add_filter(
-                        "Video",
-                        &[
-                            "mp4", "mkv", "mka", "mk3d", "mks", "mov", "avi", "wmv", "flv", "f4v",
-                            "webm", "ogv",
-                        ],
-                    )
-                    .pick_file();
-                if let Some(path) = dialog.await {
-                    self.file = Some(path.path().display().to_string());
-                    self.player
-                        .sender()
-                        .send(PlayerMsg::SetVideo(path.path().to_path_buf()))
-                        .unwrap();
-                    self.media_info_window
-                        .sender()
-                        .send(MediaInfoMsg::GetInfo(path.path().to_path_buf()))
-                        .unwrap();
-                }
+        group.add_action::<PanDown>(RelmAction::new_stateless(clone!(
+            #[strong]
+            sender,
+            move |_| {
+                sender.input(AppMsg::Pan(0.0, -PAN_STEP));
             }
-            AppMsg::OpenMediaInfo => {
-                self.media_info_window
-                    .sender()
-                    .send(MediaInfoMsg::Show)
-                    .unwrap();
+        )));
+
+        group.add_action::<Rotate90>(RelmAction::new_stateless(clone!(
+            #[strong]
+            sender,
+            move |_| {
+                sender.input(AppMsg::Orient(Orientation::rotated(90)));
             }
-            AppMsg::PlayPause => {
-                self.player.sender().send(PlayerMsg::PlayPause).unwrap();
+        )));
+
+        group.add_action::<Rotate180>(RelmAction::new_stateless(clone!(
+            #[strong]
+            sender,
+            move |_| {
+                sender.input(AppMsg::Orient(Orientation::rotated(180)));
             }
-            AppMsg::Fullscreen => {
-                root.set_fullscreened(!root.is_fullscreen());
+        )));
+
+        group.add_action::<Rotate270>(RelmAction::new_stateless(clone!(
+            #[strong]
+            sender,
+            move |_| {
+                sender.input(AppMsg::Orient(Orientation::rotated(270)));
             }
-            AppMsg::SeekForwards => {
-                self.player.sender().send(PlayerMsg::SeekForwards).unwrap();
+        )));
+
+        group.add_action::<FlipHorizontal>(RelmAction::new_stateless(clone!(
+            #[strong]
+            sender,
+            move |_| {
+                sender.input(AppMsg::Orient(Orientation::FLIP_HORIZONTAL));
             }
-            AppMsg::SeekBackwards => {
-                self.player.sender().send(PlayerMsg::SeekBackwards).unwrap();
+        )));
+
+        group.add_action::<FlipVertical>(RelmAction::new_stateless(clone!(
+            #[strong]
+            sender,
+            move |_| {
+                sender.input(AppMsg::Orient(Orientation::FLIP_VERTICAL));
             }
-            AppMsg::VolumeUp => {
-                self.player.sender().send(PlayerMsg::VolumeUp).unwrap();
+        )));
+
+        group.add_action::<ResetOrientation>(RelmAction::new_stateless(clone!(
+            #[strong]
+            sender,
+            move |_| {
+                sender.input(AppMsg::ResetOrientation);
             }
-            AppMsg::VolumeDown => {
-                self.player.sender().send(PlayerMsg::VolumeDown).unwrap();
+        )));
+
+        group.add_action::<DeinterlaceMode>(RelmAction::new_stateful_with_target_value(
+            &String::from("auto"),
+            clone!(
+                #[strong]
+                sender,
+                move |_, state: &mut String, value: String| {
+                    *state = value.clone();
+                    sender.input(AppMsg::Deinterlace(value));
+                }
+            ),
+        ));
+
+        group.add_action::<DeinterlaceFilter>(RelmAction::new_stateful_with_target_value(
+            &String::from("yadif"),
+            clone!(
+                #[strong]
+                sender,
+                move |_, state: &mut String, value: String| {
+                    *state = value.clone();
+                    sender.input(AppMsg::Deinterlacer(value));
+                }
+            ),
+        ));
+
+        group.add_action::<FieldOrderMode>(RelmAction::new_stateful_with_target_value(
+            &String::from("auto"),
+            clone!(
+                #[strong]
+                sender,
+                move |_, state: &mut String, value: String| {
+                    *state = value.clone();
+                    sender.input(AppMsg::FieldOrder(value));
+                }
+            ),
+        ));
+
+        group.add_action::<Filters>(RelmAction::new_stateless(clone!(
+            #[strong]
+            sender,
+            move |_| {
+                sender.input(AppMsg::EditFilters);
             }
-            AppMsg::Quit => {
-                relm4::main_application().quit();
+        )));
+
+        group.add_action::<Delays>(RelmAction::new_stateless(clone!(
+            #[strong]
+            sender,
+            move |_| {
+                sender.input(AppMsg::EditDelays);
             }
-        }
-    }
-}
+        )));
 
-fn main() {
-    let app = RelmApp::new("dy-tea.simplevideo.player");
-    app.run_async::<App>(0);
+        group.add_action::<AudioDelayUp>(RelmAction::new_stateless(clone!(
+            #[strong]
+            sender,
+            move |_| {
+                sender.input(AppMsg::ShiftAudioDelay(DELAY_STEP));
+            }
+        )));
+
+        group.add_action::<AudioDelayDown>(RelmAction::new_stateless(clone!(
+            #[strong]
+            sender,
+            move |_| {
+                sender.input(AppMsg::ShiftAudioDelay(-DELAY_STEP));
+            }
+        )));
+
+        group.add_action::<SubtitleDelayUp>(RelmAction::new_stateless(clone!(
+            #[strong]
+            sender,
+            move |_| {
+                sender.input(AppMsg::ShiftSubtitleDelay(DELAY_STEP));
+            }
+        )));
+
+        group.add_action::<SubtitleDelayDown>(RelmAction::new_stateless(clone!(
+            #[strong]
+            sender,
+            move |_| {
+                sender.input(AppMsg::ShiftSubtitleDelay(-DELAY_STEP));
+            }
+        )));
+
+        group.add_action::<About>(RelmAction::new_stateless(move |_| {
+            about_dialog_broker.send(AboutDialogMsg::Show);
+        }));
+
+        group.add_action::<Info>(RelmAction::new_stateless(clone!(
+            #[strong]
+            sender,
+            move |_| {
+                sender.input(AppMsg::OpenMediaInfo);
+            }
+        )));
+
+        group.add_action::<Shortcut>(RelmAction::new_stateless(move |_| {
+            shortcuts_broker.send(ShortcutsMsg::Show);
+        }));
+
+        group.add_action::<Quit>(RelmAction::new_stateless(clone!(
+            #[strong]
+            sender,
+            move |_| {
+                sender.input(AppMsg::Quit);
+            }
+        )));
+
+        group.add_action::<Library>(RelmAction::new_stateless(clone!(
+            #[strong]
+            sender,
+            move |_| {
+                sender.input(AppMsg::OpenLibrary);
+            }
+        )));
+
+        group.add_action::<PlayPause>(RelmAction::new_stateless(clone!(
+            #[strong]
+            sender,
+            move |_| {
+                sender.input(AppMsg::PlayPause);
+            }
+        )));
+
+        group.add_action::<Fullscreen>(RelmAction::new_stateless(clone!(
+            #[strong]
+            sender,
+            move |_| {
+                sender.input(AppMsg::Fullscreen);
+            }
+        )));
+
+        group.add_action::<SeekForwards>(RelmAction::new_stateless(clone!(
+            #[strong]
+            sender,
+            move |_| {
+                sender.input(AppMsg::SeekForwards);
+            }
+        )));
+
+        group.add_action::<SeekBackwards>(RelmAction::new_stateless(clone!(
+            #[strong]
+            sender,
+            move |_| {
+                sender.input(AppMsg::SeekBackwards);
+            }
+        )));
+
+        group.add_action::<SeekShortForwards>(RelmAction::new_stateless(clone!(
+            #[strong]
+            sender,
+            move |_| {
+                sender.input(AppMsg::SeekShortForwards);
+            }
+        )));
+
+        group.add_action::<SeekShortBackwards>(RelmAction::new_stateless(clone!(
+            #[strong]
+            sender,
+            move |_| {
+                sender.input(AppMsg::SeekShortBackwards);
+            }
+        )));
+
+        group.add_action::<SeekLongForwards>(RelmAction::new_stateless(clone!(
+            #[strong]
+            sender,
+            move |_| {
+                sender.input(AppMsg::SeekLongForwards);
+            }
+        )));
+
+        group.add_action::<SeekLongBackwards>(RelmAction::new_stateless(clone!(
+            #[strong]
+            sender,
+            move |_| {
+                sender.input(AppMsg::SeekLongBackwards);
+            }
+        )));
+
+        group.add_action::<SeekPercent>(RelmAction::new_with_target_value(clone!(
+            #[strong]
+            sender,
+            move |_, percent: String| {
+                sender.input(AppMsg::SeekPercent(percent));
+            }
+        )));
+
+        group.add_action::<GoTo>(RelmAction::new_stateless(clone!(
+            #[strong]
+            sender,
+            move |_| {
+                sender.input(AppMsg::GoTo);
+            }
+        )));
+
+        group.add_action::<AddBookmark>(RelmAction::new_stateless(clone!(
+            #[strong]
+            sender,
+            move |_| {
+                sender.input(AppMsg::AddBookmark);
+            }
+        )));
+
+        group.add_action::<Bookmarks>(RelmAction::new_stateless(clone!(
+            #[strong]
+            sender,
+            move |_| {
+                sender.input(AppMsg::OpenBookmarks);
+            }
+        )));
+
+        group.add_action::<NextBookmark>(RelmAction::new_stateless(clone!(
+            #[strong]
+            sender,
+            move |_| {
+                sender.input(AppMsg::NextBookmark);
+            }
+        )));
+
+        group.add_action::<PreviousBookmark>(RelmAction::new_stateless(clone!(
+            #[strong]
+            sender,
+            move |_| {
+                sender.input(AppMsg::PreviousBookmark);
+            }
+        )));
+
+        group.add_action::<Review>(RelmAction::new_stateless(clone!(
+            #[strong]
+            sender,
+            move |_| {
+                sender.input(AppMsg::OpenReview);
+            }
+        )));
+
+        group.add_action::<MarkIn>(RelmAction::new_stateless(clone!(
+            #[strong]
+            sender,
+            move |_| {
+                sender.input(AppMsg::Review(ReviewPoint::In));
+            }
+        )));
+
+        group.add_action::<MarkOut>(RelmAction::new_stateless(clone!(
+            #[strong]
+            sender,
+            move |_| {
+                sender.input(AppMsg::Review(ReviewPoint::Out));
+            }
+        )));
+
+        group.add_action::<AddComment>(RelmAction::new_stateless(clone!(
+            #[strong]
+            sender,
+            move |_| {
+                sender.input(AppMsg::Review(ReviewPoint::Comment));
+            }
+        )));
+
+        group.add_action::<Chapters>(RelmAction::new_stateless(clone!(
+            #[strong]
+            sender,
+            move |_| {
+                sender.input(AppMsg::OpenChapters);
+            }
+        )));
+
+        group.add_action::<ShortSeek>(RelmAction::new_stateful_with_target_value(
+            &model.settings.short_seek.to_string(),
+            clone!(
+                #[strong]
+                sender,
+                move |_, state: &mut String, value: String| {
+                    *state = value.clone();
+                    sender.input(AppMsg::SetShortSeek(value));
+                }
+            ),
+        ));
+
+        group.add_action::<LongSeek>(RelmAction::new_stateful_with_target_value(
+            &model.settings.long_seek.to_string(),
+            clone!(
+                #[strong]
+                sender,
+                move |_, state: &mut String, value: String| {
+                    *state = value.clone();
+                    sender.input(AppMsg::SetLongSeek(value));
+                }
+            ),
+        ));
+
+        group.add_action::<SeekAccuracy>(RelmAction::new_stateful_with_target_value(
+            &String::from(model.settings.seek_mode.id()),
+            clone!(
+                #[strong]
+                sender,
+                move |_, state: &mut String, value: String| {
+                    *state = value.clone();
+                    sender.input(AppMsg::SetSeekMode(value));
+                }
+            ),
+        ));
+
+        group.add_action::<VolumeUp>(RelmAction::new_stateless(clone!(
+            #[strong]
+            sender,
+            move |_| {
+                sender.input(AppMsg::VolumeUp);
+            }
+        )));
+
+        group.add_action::<VolumeDown>(RelmAction::new_stateless(clone!(
+            #[strong]
+            sender,
+            move |_| {
+                sender.input(AppMsg::VolumeDown);
+            }
+        )));
+
+        group.add_action::<Mute>(RelmAction::new_stateless(clone!(
+            #[strong]
+            sender,
+            move |_| {
+                sender.input(AppMsg::ToggleMute);
+            }
+        )));
+
+        group.add_action::<VolumeStep>(RelmAction::new_stateful_with_target_value(
+            &format!("{:.0}", model.settings.volume.step * 100.0),
+            clone!(
+                #[strong]
+                sender,
+                move |_, state: &mut String, value: String| {
+                    *state = value.clone();
+                    sender.input(AppMsg::SetVolumeStep(value));
+                }
+            ),
+        ));
+
+        group.add_action::<VolumeBoost>(RelmAction::new_stateful(
+            &model.settings.volume.boost,
+            clone!(
+                #[strong]
+                sender,
+                move |_, state: &mut bool| {
+                    *state = !*state;
+                    sender.input(AppMsg::SetBoost(*state));
+                }
+            ),
+        ));
+
+        group.add_action::<OutputChannels>(RelmAction::new_stateful_with_target_value(
+            &String::from(model.settings.routing.layout.id()),
+            clone!(
+                #[strong]
+                sender,
+                move |_, state: &mut String, value: String| {
+                    *state = value.clone();
+                    sender.input(AppMsg::OutputLayout(value));
+                }
+            ),
+        ));
+
+        group.add_action::<CenterBoost>(RelmAction::new_stateful_with_target_value(
+            &format!("{:.0}", model.settings.routing.center_boost),
+            clone!(
+                #[strong]
+                sender,
+                move |_, state: &mut String, value: String| {
+                    *state = value.clone();
+                    sender.input(AppMsg::CenterBoost(value));
+                }
+            ),
+        ));
+
+        group.add_action::<ChannelRoute>(RelmAction::new_stateful_with_target_value(
+            &String::from(model.settings.routing.mode.id()),
+            clone!(
+                #[strong]
+                sender,
+                move |_, state: &mut String, value: String| {
+                    *state = value.clone();
+                    sender.input(AppMsg::ChannelMode(value));
+                }
+            ),
+        ));
+
+        group.add_action::<VisualizerMode>(RelmAction::new_stateful_with_target_value(
+            &String::from(model.settings.visualization.id()),
+            clone!(
+                #[strong]
+                sender,
+                move |_, state: &mut String, value: String| {
+                    *state = value.clone();
+                    sender.input(AppMsg::Visualization(value));
+                }
+            ),
+        ));
+
+        group.add_action::<NormalizationMode>(RelmAction::new_stateful_with_target_value(
+            &model.settings.normalization.id(),
+            move |_, state: &mut String, value: String| {
+                *state = value.clone();
+                sender.input(AppMsg::Normalization(value));
+            },
+        ));
+
+        widgets
+            .window
+            .insert_action_group("win", Some(&group.into_action_group()));
+
+        AsyncComponentParts { model, widgets }
+    }
+
+    async fn update(&mut self, msg: AppMsg, sender: AsyncComponentSender<Self>, root: &Self::Root) {
+        match msg {
+            AppMsg::SelectFile => {
+                let dialog = rfd::AsyncFileDialog::new()
+                    .set_title("Select a File")
+                    .add_filter(
+                        "Media",
+                        &[playlist::VIDEO_EXTENSIONS, playlist::AUDIO_EXTENSIONS].concat(),
+                    )
+                    .add_filter("Video", playlist::VIDEO_EXTENSIONS)
+                    .add_filter("Audio", playlist::AUDIO_EXTENSIONS)
+                    .pick_file();
+                if let Some(path) = dialog.await {
+                    self.open(vec![path.path().to_path_buf()], &sender);
+                }
+            }
+            AppMsg::OpenUrl => {
+                let entry = gtk::Entry::builder()
+                    .placeholder_text("https://, rtsp://, .m3u8 or .mpd")
+                    .activates_default(true)
+                    .build();
+                let dialog = adw::AlertDialog::builder()
+                    .heading("Open URL")
+                    .body("Enter the address of a network stream")
+                    .extra_child(&entry)
+                    .default_response("open")
+                    .close_response("cancel")
+                    .build();
+                dialog.add_responses(&[("cancel", "Cancel"), ("open", "Open")]);
+                dialog.set_response_appearance("open", adw::ResponseAppearance::Suggested);
+
+                if dialog.choose_future(root).await == "open" && !entry.text().is_empty() {
+                    self.open(vec![PathBuf::from(entry.text().trim())], &sender);
+                }
+            }
+            AppMsg::Open(paths) => {
+                self.open(paths, &sender);
+            }
+            AppMsg::Paste => {
+                let clipboard = root.clipboard();
+                let paths = match clipboard
+                    .read_value_future(gdk::FileList::static_type(), glib::Priority::DEFAULT)
+                    .await
+                    .ok()
+                    .and_then(|value| value.get::<gdk::FileList>().ok())
+                {
+                    Some(files) => files.files().iter().map(file_location).collect(),
+                    None => match clipboard.read_text_future().await {
+                        Ok(Some(text)) => text_locations(&text),
+                        _ => return,
+                    },
+                };
+                self.open(paths, &sender);
+            }
+            AppMsg::Next => {
+                if let Some(path) = self.playlist.next().cloned() {
+                    self.load(path, &sender);
+                }
+            }
+            AppMsg::Position(position) => {
+                if let Some(path) = self.playlist.current() {
+                    self.history.set_position(path, position);
+                }
+            }
+            AppMsg::VideoSize(width, height) => {
+                if self.settings.auto_resize && !root.is_maximized() && !root.is_fullscreen() {
+                    self.resize_to_video(root, width, height);
+                }
+            }
+            AppMsg::SetAutoResize(auto_resize) => {
+                self.settings.auto_resize = auto_resize;
+            }
+            AppMsg::Aspect(choice) => {
+                let aspect = match choice.as_str() {
+                    "auto" => None,
+                    "custom" => match self.ask_ratio(root).await {
+                        Some(ratio) => Some(ratio),
+                        None => {
+                            // The menu already shows "Custom…" as chosen, so put back the
+                            // choice that still applies
+                            if choice != self.aspect {
+                                let previous = self.aspect.to_variant();
+                                if let Err(e) = root.activate_action("win.aspect", Some(&previous))
+                                {
+                                    eprintln!("Error: Could not reset the aspect ratio ({e})");
+                                }
+                            }
+                            return;
+                        }
+                    },
+                    ratio => presentation::parse_ratio(ratio),
+                };
+                self.aspect = choice;
+                self.player
+                    .sender()
+                    .send(PlayerMsg::SetAspect(aspect))
+                    .unwrap();
+            }
+            AppMsg::Crop(crop) => {
+                let crop = match crop.as_str() {
+                    "none" => Crop::None,
+                    "auto" => Crop::Auto,
+                    ratio => presentation::parse_ratio(ratio).map_or(Crop::None, Crop::Ratio),
+                };
+                self.player.sender().send(PlayerMsg::SetCrop(crop)).unwrap();
+            }
+            AppMsg::Fit(fit) => {
+                let fit = match fit.as_str() {
+                    "fill" => Fit::Fill,
+                    "stretch" => Fit::Stretch,
+                    "original" => Fit::Original,
+                    _ => Fit::Fit,
+                };
+                self.player.sender().send(PlayerMsg::SetFit(fit)).unwrap();
+            }
+            AppMsg::ZoomIn => {
+                self.player
+                    .sender()
+                    .send(PlayerMsg::Zoom(player::ZOOM_STEP))
+                    .unwrap();
+            }
+            AppMsg::ZoomOut => {
+                self.player
+                    .sender()
+                    .send(PlayerMsg::Zoom(1.0 / player::ZOOM_STEP))
+                    .unwrap();
+            }
+            AppMsg::ZoomReset => {
+                self.player.sender().send(PlayerMsg::ResetZoom).unwrap();
+            }
+            AppMsg::Pan(dx, dy) => {
+                self.player.sender().send(PlayerMsg::Pan(dx, dy)).unwrap();
+            }
+            AppMsg::Orient(orientation) => {
+                self.player
+                    .sender()
+                    .send(PlayerMsg::Orient(orientation))
+                    .unwrap();
+            }
+            AppMsg::ResetOrientation => {
+                self.player
+                    .sender()
+                    .send(PlayerMsg::ResetOrientation)
+                    .unwrap();
+            }
+            AppMsg::Orientation(orientation) => {
+                self.media_info_window
+                    .sender()
+                    .send(MediaInfoMsg::SetOrientation(orientation.description()))
+                    .unwrap();
+            }
+            AppMsg::Deinterlace(mode) => {
+                let deinterlace = match mode.as_str() {
+                    "off" => Deinterlace::Off,
+                    "forced" => Deinterlace::Forced,
+                    _ => Deinterlace::Auto,
+                };
+                self.player
+                    .sender()
+                    .send(PlayerMsg::SetDeinterlace(deinterlace))
+                    .unwrap();
+            }
+            AppMsg::Deinterlacer(filter) => {
+                let deinterlacer = match filter.as_str() {
+                    "bwdif" => Deinterlacer::Bwdif,
+                    _ => Deinterlacer::Yadif,
+                };
+                self.player
+                    .sender()
+                    .send(PlayerMsg::SetDeinterlacer(deinterlacer))
+                    .unwrap();
+            }
+            AppMsg::FieldOrder(order) => {
+                let field_order = match order.as_str() {
+                    "tff" => FieldOrder::TopFirst,
+                    "bff" => FieldOrder::BottomFirst,
+                    _ => FieldOrder::Auto,
+                };
+                self.player
+                    .sender()
+                    .send(PlayerMsg::SetFieldOrder(field_order))
+                    .unwrap();
+            }
+            AppMsg::Adjust(adjustments) => {
+                self.file_settings.adjustments = adjustments;
+                self.player
+                    .sender()
+                    .send(PlayerMsg::SetAdjustments(adjustments))
+                    .unwrap();
+            }
+            AppMsg::Equalize(equalizer) => {
+                self.settings.equalizer = equalizer;
+                self.player
+                    .sender()
+                    .send(PlayerMsg::SetEqualizer(equalizer))
+                    .unwrap();
+            }
+            AppMsg::EditFilters => {
+                if let Some((video, audio)) = self.ask_filters(root).await {
+                    sender.input(AppMsg::SetVideoFilter(video));
+                    sender.input(AppMsg::SetAudioFilter(audio));
+                }
+            }
+            AppMsg::SetVideoFilter(filter) => {
+                if filter != self.video_filter && self.apply_video_filter(filter.clone()) {
+                    self.settings.video_filter = filter;
+                }
+            }
+            AppMsg::SetAudioFilter(filter) => {
+                if filter != self.audio_filter && self.apply_audio_filter(filter.clone()) {
+                    self.settings.audio_filter = filter;
+                }
+            }
+            AppMsg::Warning(message) => {
+                self.toasts.add_toast(adw::Toast::new(&message));
+            }
+            AppMsg::ShiftAudioDelay(step) => {
+                if let Some((audio, _)) = self.delays() {
+                    self.set_audio_delay(audio + step);
+                }
+            }
+            AppMsg::ShiftSubtitleDelay(step) => {
+                if let Some((_, subtitle)) = self.delays() {
+                    self.set_subtitle_delay(subtitle + step);
+                }
+            }
+            AppMsg::EditDelays => {
+                let Some((audio, subtitle)) = self.delays() else {
+                    return;
+                };
+                if let Some((new_audio, new_subtitle)) = ask_delays(root, audio, subtitle).await {
+                    if new_audio != audio {
+                        self.set_audio_delay(new_audio);
+                    }
+                    if new_subtitle != subtitle {
+                        self.set_subtitle_delay(new_subtitle);
+                    }
+                }
+            }
+            AppMsg::TogglePinned => {
+                if let Some(path) = self.playlist.current() {
+                    self.history.toggle_pinned(path);
+                    self.history.save();
+                    self.update_recent_menu();
+                }
+            }
+            AppMsg::RemoveMissing => {
+                let removed = self.history.remove_missing();
+                self.forget(removed);
+            }
+            AppMsg::ClearHistory => {
+                let removed = self.history.clear();
+                self.forget(removed);
+            }
+            AppMsg::OpenMediaInfo => {
+                self.measure_loudness(&sender);
+                self.media_info_window
+                    .sender()
+                    .send(MediaInfoMsg::Show)
+                    .unwrap();
+            }
+            AppMsg::OpenLibrary => {
+                self.library_window.sender().send(LibraryMsg::Show).unwrap();
+            }
+            AppMsg::LibraryFolders(folders) => {
+                self.settings.library_folders = folders;
+            }
+            AppMsg::PlayPause => {
+                self.player.sender().send(PlayerMsg::PlayPause).unwrap();
+            }
+            AppMsg::Fullscreen => {
+                root.set_fullscreened(!root.is_fullscreen());
+            }
+            AppMsg::SeekForwards => {
+                self.player.sender().send(PlayerMsg::SeekForwards).unwrap();
+            }
+            AppMsg::SeekBackwards => {
+                self.player.sender().send(PlayerMsg::SeekBackwards).unwrap();
+            }
+            AppMsg::SeekShortForwards => {
+                self.seek_by(self.settings.short_seek);
+            }
+            AppMsg::SeekShortBackwards => {
+                self.seek_by(-self.settings.short_seek);
+            }
+            AppMsg::SeekLongForwards => {
+                self.seek_by(self.settings.long_seek);
+            }
+            AppMsg::SeekLongBackwards => {
+                self.seek_by(-self.settings.long_seek);
+            }
+            AppMsg::SetShortSeek(seconds) => {
+                if let Ok(seconds) = seconds.parse() {
+                    self.settings.short_seek = seconds;
+                }
+            }
+            AppMsg::SetLongSeek(seconds) => {
+                if let Ok(seconds) = seconds.parse() {
+                    self.settings.long_seek = seconds;
+                }
+            }
+            AppMsg::SetSeekMode(mode) => {
+                self.settings.seek_mode = SeekMode::parse(&mode);
+                self.player
+                    .sender()
+                    .send(PlayerMsg::SetSeekMode(self.settings.seek_mode))
+                    .unwrap();
+            }
+            AppMsg::SeekPercent(percent) => match percent.parse::<i64>() {
+                Ok(percent) if self.duration > 0 => {
+                    self.player
+                        .sender()
+                        .send(PlayerMsg::SeekTo(self.duration * percent / 100))
+                        .unwrap();
+                }
+                _ => {}
+            },
+            AppMsg::GoTo => {
+                if self.playlist.current().is_none() {
+                    return;
+                }
+                match ask_seek_target(root, self.duration, self.frame_rate).await {
+                    Some(SeekTarget::Absolute(position)) => {
+                        self.player
+                            .sender()
+                            .send(PlayerMsg::SeekTo(position))
+                            .unwrap();
+                    }
+                    Some(SeekTarget::Relative(step)) => {
+                        self.player.sender().send(PlayerMsg::SeekBy(step)).unwrap();
+                    }
+                    None => {}
+                }
+            }
+            AppMsg::Timing(duration, frame_rate) => {
+                self.duration = duration;
+                self.frame_rate = frame_rate;
+                self.bookmarks_window
+                    .sender()
+                    .send(BookmarksMsg::SetDuration(duration))
+                    .unwrap();
+                self.review_window
+                    .sender()
+                    .send(ReviewMsg::SetTiming(duration, frame_rate))
+                    .unwrap();
+                self.chapters_window
+                    .sender()
+                    .send(ChaptersMsg::SetDuration(duration))
+                    .unwrap();
+            }
+            AppMsg::SeekTo(position) => {
+                self.player
+                    .sender()
+                    .send(PlayerMsg::SeekTo(position))
+                    .unwrap();
+            }
+            AppMsg::AddBookmark => {
+                self.player.sender().send(PlayerMsg::Bookmark).unwrap();
+            }
+            AppMsg::Bookmark(position) => {
+                self.bookmarks_window
+                    .sender()
+                    .send(BookmarksMsg::Add(position))
+                    .unwrap();
+            }
+            AppMsg::OpenBookmarks => {
+                self.bookmarks_window
+                    .sender()
+                    .send(BookmarksMsg::Show)
+                    .unwrap();
+            }
+            AppMsg::NextBookmark => {
+                self.player.sender().send(PlayerMsg::NextMark).unwrap();
+            }
+            AppMsg::PreviousBookmark => {
+                self.player.sender().send(PlayerMsg::PreviousMark).unwrap();
+            }
+            AppMsg::Marks(marks) => {
+                self.player
+                    .sender()
+                    .send(PlayerMsg::SetMarks(marks))
+                    .unwrap();
+            }
+            AppMsg::Review(point) => {
+                self.player.sender().send(PlayerMsg::Review(point)).unwrap();
+            }
+            AppMsg::ReviewAt(point, position) => {
+                self.review_window
+                    .sender()
+                    .send(ReviewMsg::Point(point, position))
+                    .unwrap();
+            }
+            AppMsg::OpenReview => {
+                self.review_window.sender().send(ReviewMsg::Show).unwrap();
+            }
+            AppMsg::SetReviewAuthor(author) => {
+                self.settings.review_author = author;
+            }
+            AppMsg::OpenChapters => {
+                self.chapters_window
+                    .sender()
+                    .send(ChaptersMsg::Show)
+                    .unwrap();
+            }
+            AppMsg::RequestPlayhead => {
+                self.player.sender().send(PlayerMsg::Playhead).unwrap();
+            }
+            AppMsg::Playhead(position) => {
+                self.chapters_window
+                    .sender()
+                    .send(ChaptersMsg::Playhead(position))
+                    .unwrap();
+            }
+            AppMsg::VolumeUp => {
+                self.player.sender().send(PlayerMsg::VolumeUp).unwrap();
+            }
+            AppMsg::VolumeDown => {
+                self.player.sender().send(PlayerMsg::VolumeDown).unwrap();
+            }
+            AppMsg::ToggleMute => {
+                self.player.sender().send(PlayerMsg::ToggleMute).unwrap();
+            }
+            AppMsg::SetVolumeStep(step) => {
+                if let Ok(percent) = step.parse::<f64>() {
+                    self.player
+                        .sender()
+                        .send(PlayerMsg::SetVolumeStep(percent / 100.0))
+                        .unwrap();
+                }
+            }
+            AppMsg::SetBoost(boost) => {
+                self.player
+                    .sender()
+                    .send(PlayerMsg::SetBoost(boost))
+                    .unwrap();
+            }
+            AppMsg::Volume(volume) => {
+                self.settings.volume = volume;
+            }
+            AppMsg::Normalization(mode) => {
+                self.settings.normalization = Normalization::parse(&mode);
+                self.measure_loudness(&sender);
+                self.apply_loudness();
+            }
+            AppMsg::OutputLayout(layout) => {
+                self.settings.routing.layout = OutputLayout::parse(&layout);
+                self.apply_routing();
+            }
+            AppMsg::CenterBoost(boost) => {
+                self.settings.routing.center_boost = boost.parse().unwrap_or_default();
+                self.apply_routing();
+            }
+            AppMsg::ChannelMode(mode) => {
+                self.settings.routing.mode = ChannelMode::parse(&mode);
+                self.apply_routing();
+            }
+            AppMsg::Visualization(visualization) => {
+                self.settings.visualization = Visualization::parse(&visualization);
+                self.player
+                    .sender()
+                    .send(PlayerMsg::SetVisualization(self.settings.visualization))
+                    .unwrap();
+            }
+            AppMsg::Channels(source, output) => {
+                self.channels = Some((source, output));
+                self.show_channel_layouts();
+            }
+            AppMsg::Quit => {
+                let (width, height) = root.default_size();
+                self.settings.width = width;
+                self.settings.height = height;
+                self.settings.maximized = root.is_maximized();
+                self.settings.fullscreen = root.is_fullscreen();
+                let (width, height) = self.media_info_window.widget().default_size();
+                self.settings.media_info_width = width;
+                self.settings.media_info_height = height;
+                self.save_settings();
+                self.store_file_settings();
+                self.history.save();
+                relm4::main_application().quit();
+            }
+            AppMsg::SaveSettings => {
+                self.saving_settings = false;
+                if self.settings != self.saved_settings {
+                    self.save_settings();
+                }
+                self.store_file_settings();
+            }
+        }
+        // Settings change in many places, and those of a file with every slider step, so they
+        // are compared after every message rather than saved by each one. Messages that return
+        // early above leave them alone.
+        let changed =
+            self.settings != self.saved_settings || self.file_settings != self.saved_file_settings;
+        if changed && !self.saving_settings {
+            self.saving_settings = true;
+            glib::timeout_add_local_once(SAVE_DELAY, move || {
+                sender.input(AppMsg::SaveSettings);
+            });
+        }
+    }
+
+    async fn update_cmd(
+        &mut self,
+        msg: AppCommand,
+        _sender: AsyncComponentSender<Self>,
+        _root: &Self::Root,
+    ) {
+        match msg {
+            AppCommand::Thumbnail(location, Some(thumbnail)) => {
+                let path = thumbnail::path_for(&location);
+                if thumbnail.save(&path) {
+                    self.history.set_thumbnail(&location, path);
+                    self.history.save();
+                    self.update_recent_menu();
+                }
+            }
+            AppCommand::Thumbnail(_, None) => {}
+            AppCommand::Loudness(location, cancel, measured) => {
+                if let Some(measured) = &measured {
+                    loudness::store(&location, measured);
+                }
+                if self
+                    .measuring
+                    .as_ref()
+                    .is_some_and(|m| Arc::ptr_eq(m, &cancel))
+                {
+                    self.measuring = None;
+                    if measured.is_some() {
+                        self.loudness = measured;
+                        self.apply_loudness();
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl App {
+    fn load(&mut self, path: PathBuf, sender: &AsyncComponentSender<Self>) {
+        self.file = Some(path.display().to_string());
+        self.player
+            .sender()
+            .send(PlayerMsg::SetVideo(path.clone()))
+            .unwrap();
+
+        // Changes still waiting to be written belong to the previous file
+        self.store_file_settings();
+        let file_settings = FileSettings::load(&path);
+        self.file_settings = file_settings;
+        self.saved_file_settings = file_settings;
+        self.file_settings_location = Some(path.clone());
+        self.adjustments
+            .sender()
+            .send(AdjustmentsMsg::Set(file_settings.adjustments))
+            .unwrap();
+        self.player
+            .sender()
+            .send(PlayerMsg::SetAdjustments(file_settings.adjustments))
+            .unwrap();
+        self.player
+            .sender()
+            .send(PlayerMsg::SetAudioDelay(file_settings.audio_delay))
+            .unwrap();
+        self.player
+            .sender()
+            .send(PlayerMsg::SetSubtitleDelay(file_settings.subtitle_delay))
+            .unwrap();
+
+        self.history.add(&path);
+        if let Some(recent) = self.history.get(&path) {
+            gtk::RecentManager::default().add_item(&recent.uri());
+            if recent.position > 0 {
+                self.player
+                    .sender()
+                    .send(PlayerMsg::SeekTo(recent.position))
+                    .unwrap();
+            }
+            if recent.thumbnail.is_none() && !playlist::is_url(&path) {
+                let location = path.clone();
+                sender.spawn_oneshot_command(move || {
+                    let thumbnail = thumbnail::capture(&location);
+                    AppCommand::Thumbnail(location, thumbnail)
+                });
+            }
+        }
+        self.history.save();
+        self.update_recent_menu();
+
+        if let Some(cancel) = self.measuring.take() {
+            cancel.store(true, Ordering::Relaxed);
+        }
+        self.loudness = loudness::cached(&path);
+        self.channels = None;
+        self.duration = 0;
+        self.frame_rate = None;
+        self.apply_loudness();
+        if self.settings.normalization != Normalization::Off {
+            self.measure_loudness(sender);
+        }
+
+        // Media Info can show any file of the playlist or the history
+        let files = self
+            .playlist
+            .entries()
+            .iter()
+            .cloned()
+            .chain(self.history.files().iter().map(|f| f.location.clone()))
+            .collect();
+        self.media_info_window
+            .sender()
+            .send(MediaInfoMsg::SetFiles(files))
+            .unwrap();
+        self.bookmarks_window
+            .sender()
+            .send(BookmarksMsg::SetFile(path.clone()))
+            .unwrap();
+        self.review_window
+            .sender()
+            .send(ReviewMsg::SetFile(path.clone()))
+            .unwrap();
+        self.chapters_window
+            .sender()
+            .send(ChaptersMsg::SetFile(path.clone()))
+            .unwrap();
+        self.media_info_window
+            .sender()
+            .send(MediaInfoMsg::GetInfo(path))
+            .unwrap();
+    }
+
+    /// Opens dropped or pasted locations. Folders are searched for media, subtitle files are
+    /// attached to the current video and everything else is appended to the playlist. Playback
+    /// switches to the first new entry when a single file is opened or nothing is playing.
+    fn open(&mut self, paths: Vec<PathBuf>, sender: &AsyncComponentSender<Self>) {
+        let mut media = Vec::new();
+        let mut subtitles = None;
+        for path in paths {
+            if path.is_dir() {
+                media.extend(playlist::collect_media(&path));
+            } else if playlist::is_subtitle(&path) {
+                subtitles = Some(path);
+            } else {
+                media.push(path);
+            }
+        }
+
+        if !media.is_empty() {
+            let switch = media.len() == 1 || self.playlist.current().is_none();
+            let first = self.playlist.append(media);
+            if switch {
+                if let Some(path) = self.playlist.select(first).cloned() {
+                    self.load(path, sender);
+                }
+            }
+        }
+
+        // Attached last, as loading a file clears the subtitles of the previous one
+        if let Some(path) = subtitles.filter(|_| self.file.is_some()) {
+            self.player
+                .sender()
+                .send(PlayerMsg::SetSubtitles(path))
+                .unwrap();
+        }
+    }
+
+    /// Asks for a custom aspect ratio such as `21:9` or `1.85`.
+    async fn ask_ratio(&self, root: &adw::Window) -> Option<f64> {
+        let entry = gtk::Entry::builder()
+            .placeholder_text("21:9 or 1.85")
+            .activates_default(true)
+            .build();
+        let dialog = adw::AlertDialog::builder()
+            .heading("Custom Aspect Ratio")
+            .body("Enter the display aspect ratio of the video")
+            .extra_child(&entry)
+            .default_response("apply")
+            .close_response("cancel")
+            .build();
+        dialog.add_responses(&[("cancel", "Cancel"), ("apply", "Apply")]);
+        dialog.set_response_appearance("apply", adw::ResponseAppearance::Suggested);
+
+        if dialog.choose_future(root).await != "apply" {
+            return None;
+        }
+        let ratio = presentation::parse_ratio(&entry.text());
+        if ratio.is_none() {
+            eprintln!("Error: Invalid aspect ratio ({})", entry.text());
+        }
+        ratio
+    }
+
+    /// Asks for custom video and audio filter chains.
+    async fn ask_filters(&self, root: &adw::Window) -> Option<(String, String)> {
+        let video = gtk::Entry::builder()
+            .text(self.video_filter.as_str())
+            .placeholder_text("Video filter, e.g. hflip,unsharp")
+            .activates_default(true)
+            .build();
+        let audio = gtk::Entry::builder()
+            .text(self.audio_filter.as_str())
+            .placeholder_text("Audio filter, e.g. atempo=1.25")
+            .activates_default(true)
+            .build();
+        let entries = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .spacing(12)
+            .build();
+        entries.append(&video);
+        entries.append(&audio);
+
+        let dialog = adw::AlertDialog::builder()
+            .heading("Custom Filters")
+            .body("Enter ffmpeg filter chains in -vf and -af syntax, or leave them empty")
+            .extra_child(&entries)
+            .default_response("apply")
+            .close_response("cancel")
+            .build();
+        dialog.add_responses(&[("cancel", "Cancel"), ("apply", "Apply")]);
+        dialog.set_response_appearance("apply", adw::ResponseAppearance::Suggested);
+
+        if dialog.choose_future(root).await != "apply" {
+            return None;
+        }
+        Some((video.text().trim().into(), audio.text().trim().into()))
+    }
+
+    /// Measures the loudness of the current file in the background, which decodes all of its
+    /// audio, so this is only done when normalization or Media Info needs it.
+    fn measure_loudness(&mut self, sender: &AsyncComponentSender<Self>) {
+        let Some(location) = self.playlist.current().cloned() else {
+            return;
+        };
+        if self.loudness.is_some() || self.measuring.is_some() || playlist::is_url(&location) {
+            return;
+        }
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.measuring = Some(cancel.clone());
+        sender.spawn_oneshot_command(move || {
+            let measured = loudness::analyze(&location, &cancel)
+                .inspect_err(|e| {
+                    if *e != ffmpeg_next::Error::Exit {
+                        eprintln!("Error: Could not measure loudness ({e})");
+                    }
+                })
+                .ok();
+            AppCommand::Loudness(location, cancel, measured)
+        });
+    }
+
+    /// Applies the normalization gain for the current file and shows its loudness.
+    fn apply_loudness(&self) {
+        let gain = self
+            .loudness
+            .map_or(0.0, |l| self.settings.normalization.gain(&l));
+        self.player.sender().send(PlayerMsg::SetGain(gain)).unwrap();
+        self.media_info_window
+            .sender()
+            .send(MediaInfoMsg::SetLoudness(self.loudness))
+            .unwrap();
+    }
+
+    fn apply_routing(&self) {
+        self.player
+            .sender()
+            .send(PlayerMsg::SetRouting(self.settings.routing))
+            .unwrap();
+        self.show_channel_layouts();
+    }
+
+    /// Shows the source and output channel layouts of the current file in Media Info.
+    fn show_channel_layouts(&self) {
+        let Some((source, output)) = self.channels else {
+            return;
+        };
+        self.media_info_window
+            .sender()
+            .send(MediaInfoMsg::SetChannelLayouts(
+                decoder::channel_layout_name(source),
+                self.settings.routing.describe(output),
+            ))
+            .unwrap();
+    }
+
+    /// Returns the audio and subtitle delays of the current file.
+    fn delays(&self) -> Option<(i64, i64)> {
+        self.playlist.current()?;
+        let file_settings = &self.file_settings;
+        Some((file_settings.audio_delay, file_settings.subtitle_delay))
+    }
+
+    /// Writes the settings now, remembering what was written.
+    fn save_settings(&mut self) {
+        self.settings.save();
+        self.saved_settings = self.settings.clone();
+    }
+
+    /// Writes the settings of the current file now if they changed.
+    fn store_file_settings(&mut self) {
+        if self.file_settings == self.saved_file_settings {
+            return;
+        }
+        if let Some(location) = &self.file_settings_location {
+            self.file_settings.store(location);
+        }
+        self.saved_file_settings = self.file_settings;
+    }
+
+    fn set_audio_delay(&mut self, delay: i64) {
+        self.file_settings.audio_delay = delay;
+        self.player
+            .sender()
+            .send(PlayerMsg::SetAudioDelay(delay))
+            .unwrap();
+        self.player
+            .sender()
+            .send(PlayerMsg::ShowOsd(format!(
+                "Audio delay: {}",
+                format_delay(delay)
+            )))
+            .unwrap();
+    }
+
+    fn set_subtitle_delay(&mut self, delay: i64) {
+        self.file_settings.subtitle_delay = delay;
+        self.player
+            .sender()
+            .send(PlayerMsg::SetSubtitleDelay(delay))
+            .unwrap();
+        self.player
+            .sender()
+            .send(PlayerMsg::ShowOsd(format!(
+                "Subtitle delay: {}",
+                format_delay(delay)
+            )))
+            .unwrap();
+    }
+
+    /// Checks a custom video filter and passes it on to the player. Invalid filters are
+    /// reported with a toast and leave the current one in place.
+    fn apply_video_filter(&mut self, filter: String) -> bool {
+        if let Err(e) = decoder::validate_video_filter(&filter) {
+            self.show_error(&format!("Invalid video filter \"{filter}\" ({e})"));
+            return false;
+        }
+        self.video_filter = filter.clone();
+        self.player
+            .sender()
+            .send(PlayerMsg::SetVideoFilter(filter))
+            .unwrap();
+        true
+    }
+
+    fn apply_audio_filter(&mut self, filter: String) -> bool {
+        if let Err(e) = decoder::validate_audio_filter(&filter) {
+            self.show_error(&format!("Invalid audio filter \"{filter}\" ({e})"));
+            return false;
+        }
+        self.audio_filter = filter.clone();
+        self.player
+            .sender()
+            .send(PlayerMsg::SetAudioFilter(filter))
+            .unwrap();
+        true
+    }
+
+    fn show_error(&self, message: &str) {
+        eprintln!("Error: {message}");
+        self.toasts.add_toast(adw::Toast::new(message));
+    }
+
+    /// Resizes the window so the video is shown at its display size, scaled down to fit the
+    /// monitor if needed.
+    fn resize_to_video(&self, root: &adw::Window, width: i32, height: i32) {
+        // Space taken by the header bar and anything else around the video
+        let chrome = (root.height() - self.player.widget().height()).max(0);
+        let mut scale = 1.0;
+        if let Some(monitor) = root
+            .surface()
+            .and_then(|surface| root.display().monitor_at_surface(&surface))
+        {
+            let area = monitor.geometry();
+            let fit_width = area.width() as f64 * 0.9 / width as f64;
+            let fit_height = (area.height() as f64 * 0.9 - chrome as f64) / height as f64;
+            scale = fit_width.min(fit_height).min(1.0);
+        }
+        root.set_default_size(
+            (width as f64 * scale).round() as i32,
+            (height as f64 * scale).round() as i32 + chrome,
+        );
+    }
+
+    /// Rebuilds the Recent submenu with pinned files first, followed by the history actions.
+    fn update_recent_menu(&self) {
+        self.recent_menu.remove_all();
+
+        let pinned = gio::Menu::new();
+        let recent = gio::Menu::new();
+        // Popover menus ignore item icons, so files with thumbnails get a custom widget
+        let mut buttons = Vec::new();
+        for (i, file) in self.history.files().iter().enumerate() {
+            let item = gio::MenuItem::new(Some(&file.label()), None);
+            item.set_action_and_target_value(
+                Some("win.recent"),
+                Some(&file.location.to_string_lossy().to_variant()),
+            );
+            if let Some(thumbnail) = &file.thumbnail {
+                let id = format!("recent-{i}");
+                item.set_attribute_value("custom", Some(&id.to_variant()));
+                buttons.push((id, recent_button(file, thumbnail, &self.main_popover)));
+            }
+            if file.pinned {
+                pinned.append_item(&item);
+            } else {
+                recent.append_item(&item);
+            }
+        }
+        if pinned.n_items() > 0 {
+            self.recent_menu.append_section(Some("Pinned"), &pinned);
+        }
+        self.recent_menu.append_section(None, &recent);
+
+        let pinned = self
+            .playlist
+            .current()
+            .and_then(|path| self.history.get(path))
+            .is_some_and(|recent| recent.pinned);
+        let pin = if pinned {
+            "Unpin Current File"
+        } else {
+            "Pin Current File"
+        };
+        let actions = gio::Menu::new();
+        actions.append(Some(pin), Some("win.pin"));
+        actions.append(Some("Remove Missing Files"), Some("win.removemissing"));
+        actions.append(Some("Clear History"), Some("win.clearhistory"));
+        self.recent_menu.append_section(None, &actions);
+
+        for (id, button) in buttons {
+            if !self.main_popover.add_child(&button, &id) {
+                eprintln!("Error: Could not add {id} to the Recent menu");
+            }
+        }
+    }
+
+    fn seek_by(&self, seconds: i64) {
+        self.player
+            .sender()
+            .send(PlayerMsg::SeekBy(seconds * 1_000_000))
+            .unwrap();
+    }
+
+    /// Drops removed history entries from the desktop's recent files as well.
+    fn forget(&self, removed: Vec<history::RecentFile>) {
+        let manager = gtk::RecentManager::default();
+        for recent in removed {
+            let _ = manager.remove_item(&recent.uri());
+        }
+        self.history.save();
+        self.update_recent_menu();
+    }
+}
+
+/// Builds the Recent menu entry of a file with a thumbnail, which opens it like a plain item.
+fn recent_button(
+    file: &history::RecentFile,
+    thumbnail: &Path,
+    popover: &gtk::PopoverMenu,
+) -> gtk::Button {
+    let picture = gtk::Picture::builder()
+        .content_fit(gtk::ContentFit::Cover)
+        .width_request(48)
+        .height_request(27)
+        .build();
+    picture.set_filename(Some(thumbnail));
+    let label = gtk::Label::builder()
+        .label(file.label())
+        .ellipsize(gtk::pango::EllipsizeMode::Middle)
+        .max_width_chars(40)
+        .xalign(0.0)
+        .build();
+    let content = gtk::Box::builder().spacing(12).build();
+    content.append(&picture);
+    content.append(&label);
+
+    let button = gtk::Button::builder()
+        .child(&content)
+        .action_name("win.recent")
+        .action_target(&file.location.to_string_lossy().to_variant())
+        .css_classes(["flat"])
+        .build();
+    // Unlike generated items, custom widgets do not close the menu
+    button.connect_clicked(clone!(
+        #[weak]
+        popover,
+        move |_| popover.popdown()
+    ));
+    button
+}
+
+/// Sets or clears the shortcuts that text fields use themselves: single keys, paste, select all
+/// and moving by words. They have to be cleared while a text field has focus.
+fn set_editing_accels(enabled: bool) {
+    let app = relm4::main_application();
+    let keys = |keys: &'static [&'static str]| if enabled { keys } else { &[] };
+    app.set_accelerators_for_action::<Paste>(keys(&["<Ctrl>V"]));
+    app.set_accelerators_for_action::<About>(keys(&["<Ctrl>A"]));
+    app.set_accelerators_for_action::<SeekLongForwards>(keys(&["<Ctrl>Right"]));
+    app.set_accelerators_for_action::<SeekLongBackwards>(keys(&["<Ctrl>Left"]));
+    app.set_accelerators_for_action::<PlayPause>(keys(&["space"]));
+    app.set_accelerators_for_action::<Fullscreen>(keys(&["F"]));
+    app.set_accelerators_for_action::<SeekForwards>(keys(&["Right"]));
+    app.set_accelerators_for_action::<SeekBackwards>(keys(&["Left"]));
+    app.set_accelerators_for_action::<SeekShortForwards>(keys(&["<Shift>Right"]));
+    app.set_accelerators_for_action::<SeekShortBackwards>(keys(&["<Shift>Left"]));
+    app.set_accelerators_for_action::<VolumeUp>(keys(&["Up"]));
+    app.set_accelerators_for_action::<VolumeDown>(keys(&["Down"]));
+    app.set_accelerators_for_action::<Mute>(keys(&["m"]));
+    app.set_accelerators_for_action::<AudioDelayUp>(keys(&["k"]));
+    app.set_accelerators_for_action::<AudioDelayDown>(keys(&["j"]));
+    app.set_accelerators_for_action::<SubtitleDelayUp>(keys(&["h"]));
+    app.set_accelerators_for_action::<SubtitleDelayDown>(keys(&["g"]));
+    app.set_accelerators_for_action::<NextBookmark>(keys(&["bracketright"]));
+    app.set_accelerators_for_action::<PreviousBookmark>(keys(&["bracketleft"]));
+    app.set_accelerators_for_action::<MarkIn>(keys(&["i"]));
+    app.set_accelerators_for_action::<MarkOut>(keys(&["o"]));
+    // Number keys jump to tenths of the duration
+    for digit in 0..=9 {
+        let key = digit.to_string();
+        let keys: &[&str] = if enabled { &[key.as_str()] } else { &[] };
+        app.set_accels_for_action(&format!("win.seekpercent('{digit}0')"), keys);
+    }
+}
+
+/// Asks for exact audio and subtitle delays, entered in milliseconds.
+async fn ask_delays(root: &adw::Window, audio: i64, subtitle: i64) -> Option<(i64, i64)> {
+    let row = |title, delay: i64| {
+        let row = adw::SpinRow::with_range(-60_000.0, 60_000.0, 10.0);
+        row.set_title(title);
+        row.set_value(delay as f64 / 1000.0);
+        row
+    };
+    let audio_row = row("Audio Delay (ms)", audio);
+    let subtitle_row = row("Subtitle Delay (ms)", subtitle);
+    let rows = gtk::ListBox::builder()
+        .selection_mode(gtk::SelectionMode::None)
+        .css_classes(["boxed-list"])
+        .build();
+    rows.append(&audio_row);
+    rows.append(&subtitle_row);
+
+    let dialog = adw::AlertDialog::builder()
+        .heading("Synchronization")
+        .body("Positive values play audio or subtitles later, negative values earlier")
+        .extra_child(&rows)
+        .default_response("apply")
+        .close_response("cancel")
+        .build();
+    dialog.add_responses(&[("cancel", "Cancel"), ("apply", "Apply")]);
+    dialog.set_response_appearance("apply", adw::ResponseAppearance::Suggested);
+
+    if dialog.choose_future(root).await != "apply" {
+        return None;
+    }
+    let micros = |row: &adw::SpinRow| (row.value() * 1000.0).round() as i64;
+    Some((micros(&audio_row), micros(&subtitle_row)))
+}
+
+/// Where the Go to dialog seeks.
+enum SeekTarget {
+    Absolute(i64),
+    Relative(i64),
+}
+
+/// Parses a position in any timecode format, a frame number or a percentage of the duration.
+/// Positions starting with + or - are relative to the current one.
+fn parse_seek_target(text: &str, duration: i64, rate: Option<FrameRate>) -> Option<SeekTarget> {
+    let text = text.trim();
+    if let Some(percent) = text.strip_suffix('%') {
+        let percent = percent.trim_end().parse::<f64>().ok()?;
+        return (duration > 0 && (0.0..=100.0).contains(&percent))
+            .then(|| SeekTarget::Absolute((duration as f64 * percent / 100.0) as i64));
+    }
+    if let Some(step) = text.strip_prefix('+') {
+        return timecode::parse(step, rate).map(SeekTarget::Relative);
+    }
+    let position = timecode::parse(text, rate)?;
+    Some(if text.starts_with('-') {
+        SeekTarget::Relative(position)
+    } else if duration > 0 {
+        SeekTarget::Absolute(position.min(duration))
+    } else {
+        SeekTarget::Absolute(position)
+    })
+}
+
+/// Asks where to seek, only allowing input that parses.
+async fn ask_seek_target(
+    root: &adw::Window,
+    duration: i64,
+    rate: Option<FrameRate>,
+) -> Option<SeekTarget> {
+    let entry = gtk::Entry::builder()
+        .placeholder_text(if rate.is_some() {
+            "1:02:03.5, 01:02:03:12, 1500f or 25%"
+        } else {
+            "1:02:03.5 or 25%"
+        })
+        .activates_default(true)
+        .build();
+    let mut body = String::from("Start with + or - to seek from the current position");
+    if duration > 0 {
+        body = format!(
+            "{body}, or end with % for a part of {}",
+            timecode::format_precise(duration)
+        );
+    }
+    let dialog = adw::AlertDialog::builder()
+        .heading("Go to")
+        .body(body)
+        .extra_child(&entry)
+        .default_response("go")
+        .close_response("cancel")
+        .build();
+    dialog.add_responses(&[("cancel", "Cancel"), ("go", "Go")]);
+    dialog.set_response_appearance("go", adw::ResponseAppearance::Suggested);
+    dialog.set_response_enabled("go", false);
+    entry.connect_changed(clone!(
+        #[weak]
+        dialog,
+        move |entry| {
+            let valid = parse_seek_target(&entry.text(), duration, rate).is_some();
+            dialog.set_response_enabled("go", valid);
+        }
+    ));
+
+    if dialog.choose_future(root).await != "go" {
+        return None;
+    }
+    parse_seek_target(&entry.text(), duration, rate)
+}
+
+fn format_delay(delay: i64) -> String {
+    format!("{:+} ms", delay / 1000)
+}
+
+/// Returns the local path of `file`, or its URI for remote locations.
+fn file_location(file: &gtk::gio::File) -> PathBuf {
+    file.path()
+        .unwrap_or_else(|| PathBuf::from(file.uri().as_str()))
+}
+
+/// Parses a `text/uri-list` or plain text with one path or URL per line.
+fn text_locations(text: &str) -> Vec<PathBuf> {
+    text.lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(|l| file_location(&gtk::gio::File::for_commandline_arg(l)))
+        .collect()
+}
+
+/// Parses `--vf FILTER`, `--af FILTER`, `--report FORMAT`, `--output FILE` (or `--vf=FILTER`)
+/// and locations to open.
+fn parse_args(mut args: impl Iterator<Item = String>) -> Options {
+    let mut options = Options::default();
+    while let Some(arg) = args.next() {
+        let (flag, value) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value.into())),
+            _ => (arg.clone(), None),
+        };
+        match flag.as_str() {
+            "--vf" => options.video_filter = value.or_else(|| args.next()),
+            "--af" => options.audio_filter = value.or_else(|| args.next()),
+            "--report" => options.report = value.or_else(|| args.next()),
+            "--output" => options.output = value.or_else(|| args.next()).map(PathBuf::from),
+            _ => options
+                .locations
+                .push(file_location(&gtk::gio::File::for_commandline_arg(arg))),
+        }
+    }
+    options
+}
+
+fn main() {
+    ffmpeg_next::format::network::init();
+
+    let mut args = std::env::args();
+    let program = args.next().unwrap_or_default();
+    let options = parse_args(args);
+
+    if let Some(format) = &options.report {
+        let Some(format) = BatchFormat::parse(format) else {
+            eprintln!("Error: Unknown report format {format} (expected csv or jsonl)");
+            std::process::exit(2);
+        };
+        if let Err(e) = report::batch(&options.locations, format, options.output.as_deref()) {
+            eprintln!("Error: Could not write report ({e})");
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // Arguments are handled here rather than by GApplication, which rejects unknown options
+    let app = RelmApp::new("dy-tea.simplevideo.player").with_args(vec![program]);
+    app.run_async::<App>(options);
 }
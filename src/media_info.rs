@@ -1,57 +1,199 @@
+use std::path::{Path, PathBuf};
+
 use adw::prelude::*;
-use relm4::{factory::FactoryVecDeque, prelude::*};
+use gtk::{glib, glib::clone};
+use relm4::{
+    actions::{RelmAction, RelmActionGroup},
+    prelude::*,
+};
+
+use crate::loudness::Loudness;
+use crate::playlist;
+use crate::probe::{self, Probe};
+use crate::report::{self, Report, ReportFormat};
+use crate::timecode;
+
+relm4::new_action_group!(InfoActionGroup, "info");
+relm4::new_stateful_action!(CopyReport, InfoActionGroup, "copy", String, ());
+relm4::new_stateless_action!(ExportReport, InfoActionGroup, "export");
 
-use ffmpeg_next::format;
+/// Groups in the order they are shown
+const GROUPS: [&str; 5] = ["Container", "Video", "Audio", "Loudness", "Metadata"];
 
+/// Everything known about one file. Orientation, channel layouts and loudness are only known
+/// for files that were played.
 #[derive(Debug)]
-struct Metadata {
-    key: String,
-    value: String,
+struct FileInfo {
+    path: PathBuf,
+    probe: Option<Probe>,
+    loading: bool,
+    orientation: Option<String>,
+    layouts: Option<(String, String)>,
+    loudness: Option<Loudness>,
 }
 
-#[relm4::factory]
-impl FactoryComponent for Metadata {
-    type Init = Metadata;
-    type Input = ();
-    type Output = ();
-    type CommandOutput = ();
-    type ParentWidget = adw::PreferencesGroup;
+impl FileInfo {
+    fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            probe: None,
+            loading: false,
+            orientation: None,
+            layouts: None,
+            loudness: None,
+        }
+    }
 
-    view! {
-        #[root]
-        root = adw::ActionRow {
-            set_title: &self.key,
-            add_suffix = &gtk::Label {
-                set_text: &self.value
-            }
-        },
+    fn name(&self) -> String {
+        match self.path.file_name() {
+            Some(name) if !playlist::is_url(&self.path) => name.to_string_lossy().into(),
+            _ => self.path.display().to_string(),
+        }
     }
 
-    fn init_model(init: Self::Init, _index: &DynamicIndex, _sender: FactorySender<Self>) -> Self {
-        init
+    /// Returns the group, name and value of every field, with "N/A" for unknown values.
+    fn fields(&self) -> Vec<(&'static str, String, String)> {
+        let na = || String::from("N/A");
+        let probe = self.probe.as_ref();
+        let loudness = self.loudness;
+        let mut fields = vec![
+            (
+                "Container",
+                String::from("Format"),
+                probe.map_or_else(na, |p| p.format.clone()),
+            ),
+            (
+                "Container",
+                String::from("Duration"),
+                probe.map_or_else(na, |p| timecode::format_precise(p.duration)),
+            ),
+            (
+                "Container",
+                String::from("Bitrate"),
+                probe.map_or_else(na, |p| {
+                    format!("{:.2} Mbps", p.bit_rate as f64 / 1_000_000.0)
+                }),
+            ),
+            (
+                "Video",
+                String::from("Resolution"),
+                probe
+                    .filter(|p| p.width > 0 && p.height > 0)
+                    .map_or_else(na, |p| format!("{}×{}", p.width, p.height)),
+            ),
+            (
+                "Video",
+                String::from("Orientation"),
+                self.orientation.clone().unwrap_or_else(na),
+            ),
+            (
+                "Audio",
+                String::from("Source Layout"),
+                self.layouts.as_ref().map_or_else(na, |(s, _)| s.clone()),
+            ),
+            (
+                "Audio",
+                String::from("Output Layout"),
+                self.layouts.as_ref().map_or_else(na, |(_, o)| o.clone()),
+            ),
+            (
+                "Loudness",
+                String::from("Integrated Loudness"),
+                loudness.map_or_else(na, |l| format!("{:.1} LUFS", l.integrated)),
+            ),
+            (
+                "Loudness",
+                String::from("True Peak"),
+                loudness.map_or_else(na, |l| format!("{:.1} dBTP", l.true_peak)),
+            ),
+            (
+                "Loudness",
+                String::from("Loudness Range"),
+                loudness.map_or_else(na, |l| format!("{:.1} LU", l.range)),
+            ),
+            (
+                "Loudness",
+                String::from("ReplayGain"),
+                loudness
+                    .and_then(|l| l.replaygain)
+                    .map_or_else(na, |g| format!("{g:+.2} dB")),
+            ),
+        ];
+        for (key, value) in probe.map(|p| p.tags.as_slice()).unwrap_or_default() {
+            let first_char = key.chars().next().unwrap_or(' ').to_uppercase().to_string();
+            fields.push((
+                "Metadata",
+                format! {"{}{}", first_char, &key[1..].to_lowercase()},
+                value.clone(),
+            ));
+        }
+        fields
     }
 }
 
 pub struct MediaInfoWindow {
     visible: bool,
-    format: Option<String>,
-    duration: Option<String>,
-    bitrate: Option<String>,
-    metadata: FactoryVecDeque<Metadata>,
+    files: Vec<FileInfo>,
+    /// File being played, which orientation, layouts and loudness apply to
+    playing: Option<PathBuf>,
+    /// File shown, and the one it is compared with
+    selected: Option<PathBuf>,
+    compared: Option<PathBuf>,
+    comparing: bool,
+    names: gtk::StringList,
+    file_dropdown: (gtk::DropDown, glib::SignalHandlerId),
+    compare_dropdown: (gtk::DropDown, glib::SignalHandlerId),
+    page: adw::PreferencesPage,
+    groups: Vec<adw::PreferencesGroup>,
 }
 
 #[derive(Debug)]
 pub enum MediaInfoMsg {
-    GetInfo(std::path::PathBuf),
+    /// Shows the file that started playing
+    GetInfo(PathBuf),
+    /// Sets the files that can be picked, such as the playlist and recent files
+    SetFiles(Vec<PathBuf>),
+    SetOrientation(String),
+    /// Sets the channel layout of the audio stream and what is sent to the output device
+    SetChannelLayouts(String, String),
+    SetLoudness(Option<Loudness>),
+    Select(u32),
+    SetComparing(bool),
+    CompareWith(u32),
+    /// Copies the full report of the shown file to the clipboard
+    Copy(ReportFormat),
+    /// Asks where to save the full report of the shown file
+    Export,
     Show,
     Hide,
 }
 
+#[derive(Debug)]
+pub enum MediaInfoCommand {
+    Probed(PathBuf, Result<Probe, ffmpeg_next::Error>),
+    Report(ReportFormat, Result<Report, ffmpeg_next::Error>),
+    Exported(PathBuf, Result<(), String>),
+}
+
 #[relm4::component(async, pub)]
-impl SimpleAsyncComponent for MediaInfoWindow {
+impl AsyncComponent for MediaInfoWindow {
     type Init = adw::Window;
     type Input = MediaInfoMsg;
     type Output = ();
+    type CommandOutput = MediaInfoCommand;
+
+    menu! {
+        report_menu: {
+            section! {
+                "Copy as Text" => CopyReport(String::from("text")),
+                "Copy as Markdown" => CopyReport(String::from("markdown")),
+                "Copy as JSON" => CopyReport(String::from("json")),
+            },
+            section! {
+                "Export…" => ExportReport,
+            }
+        }
+    }
 
     view! {
         #[root]
@@ -62,39 +204,47 @@ impl SimpleAsyncComponent for MediaInfoWindow {
             set_visible: model.visible,
             gtk::Box {
                 set_orientation: gtk::Orientation::Vertical,
-                adw::HeaderBar,
-                adw::PreferencesPage {
-                    add = &adw::PreferencesGroup {
-                        set_title: "Container",
-                        adw::ActionRow {
-                            set_title: "Format",
-                            add_suffix = &gtk::Label {
-                                #[watch]
-                                set_text: model.format.clone().unwrap_or(String::from("N/A")).as_ref(),
-                            }
+                adw::HeaderBar {
+                    #[wrap(Some)]
+                    set_title_widget = &gtk::Box {
+                        set_spacing: 6,
+                        #[local_ref]
+                        file_dropdown -> gtk::DropDown {
+                            set_tooltip_text: Some("File"),
+                        },
+                        #[local_ref]
+                        compare_dropdown -> gtk::DropDown {
+                            set_tooltip_text: Some("Compare With"),
+                            #[watch]
+                            set_visible: model.comparing,
                         },
-                        adw::ActionRow {
-                            set_title: "Duration",
-                            add_suffix = &gtk::Label {
-                                #[watch]
-                                set_text: model.duration.clone().unwrap_or(String::from("N/A")).as_ref(),
-                            }
+                    },
+                    pack_end = &gtk::MenuButton {
+                        set_icon_name: "document-send-symbolic",
+                        set_tooltip_text: Some("Copy or Export Report"),
+                        set_menu_model: Some(&report_menu),
+                    },
+                    pack_end = &gtk::ToggleButton {
+                        set_icon_name: "view-dual-symbolic",
+                        set_tooltip_text: Some("Compare Two Files"),
+                        connect_toggled[sender] => move |button| {
+                            sender.input(MediaInfoMsg::SetComparing(button.is_active()));
                         },
-                        adw::ActionRow {
-                            set_title: "Bitrate",
-                            add_suffix = &gtk::Label {
-                                #[watch]
-                                set_text: model.bitrate.clone().unwrap_or(String::from("N/A")).as_ref(),
-                            }
-                        }
                     },
-                    #[local_ref]
-                    metadata_view -> adw::PreferencesGroup {
-                        set_title: "Metadata"
+                    pack_end = &gtk::Spinner {
+                        set_tooltip_text: Some("Reading file"),
+                        #[watch]
+                        set_visible: model.is_loading(),
+                        #[watch]
+                        set_spinning: model.is_loading(),
                     }
+                },
+                #[local_ref]
+                page -> adw::PreferencesPage {
+                    set_vexpand: true,
                 }
             },
-            connect_close_request[sender] => move |_̱| {
+            connect_close_request[sender] => move |_| {
                 sender.input(MediaInfoMsg::Hide);
                 gtk::glib::Propagation::Proceed
             }
@@ -106,63 +256,165 @@ impl SimpleAsyncComponent for MediaInfoWindow {
         root: Self::Root,
         sender: AsyncComponentSender<Self>,
     ) -> AsyncComponentParts<Self> {
-        let metadata = FactoryVecDeque::builder()
-            .launch(adw::PreferencesGroup::new())
-            .detach();
+        let names = gtk::StringList::new(&[]);
+        let dropdown = |message: fn(u32) -> MediaInfoMsg| {
+            let dropdown = gtk::DropDown::builder().model(&names).build();
+            let sender = sender.clone();
+            let handler = dropdown.connect_selected_notify(move |d| {
+                sender.input(message(d.selected()));
+            });
+            (dropdown, handler)
+        };
 
-        let model = Self {
+        let mut model = Self {
             visible: false,
-            format: None,
-            duration: None,
-            bitrate: None,
-            metadata,
+            files: Vec::new(),
+            playing: None,
+            selected: None,
+            compared: None,
+            comparing: false,
+            file_dropdown: dropdown(MediaInfoMsg::Select),
+            compare_dropdown: dropdown(MediaInfoMsg::CompareWith),
+            names,
+            page: adw::PreferencesPage::new(),
+            groups: Vec::new(),
         };
+        model.render();
 
-        let metadata_view = model.metadata.widget();
+        let file_dropdown = &model.file_dropdown.0;
+        let compare_dropdown = &model.compare_dropdown.0;
+        let page = &model.page;
         let widgets = view_output!();
         widgets.window.set_transient_for(Some(&init));
 
+        let mut group = RelmActionGroup::<InfoActionGroup>::new();
+        group.add_action::<CopyReport>(RelmAction::new_with_target_value(clone!(
+            #[strong]
+            sender,
+            move |_, format: String| {
+                sender.input(MediaInfoMsg::Copy(ReportFormat::parse(&format)));
+            }
+        )));
+        group.add_action::<ExportReport>(RelmAction::new_stateless(clone!(
+            #[strong]
+            sender,
+            move |_| {
+                sender.input(MediaInfoMsg::Export);
+            }
+        )));
+        group.register_for_widget(&widgets.window);
+
         AsyncComponentParts { model, widgets }
     }
 
-    async fn update(&mut self, msg: Self::Input, _sender: AsyncComponentSender<Self>) {
-        let mut metadata_guard = self.metadata.guard();
-
+    async fn update(
+        &mut self,
+        msg: Self::Input,
+        sender: AsyncComponentSender<Self>,
+        _root: &Self::Root,
+    ) {
         match msg {
             MediaInfoMsg::GetInfo(path) => {
-                ffmpeg_next::init().unwrap();
-                let context = match format::input(&path) {
-                    Ok(c) => c,
-                    Err(e) => {
-                        eprintln!("Error: Could not create context ({e})");
-                        panic!();
+                if self.index_of(&path).is_none() {
+                    self.files.push(FileInfo::new(path.clone()));
+                    self.update_names();
+                }
+                self.playing = Some(path.clone());
+                self.selected = Some(path);
+                self.update_selection();
+            }
+            MediaInfoMsg::SetFiles(paths) => {
+                // Files that stay keep what is known about them
+                let mut files = std::mem::take(&mut self.files);
+                for path in paths.into_iter().chain(self.playing.clone()) {
+                    if self.index_of(&path).is_some() {
+                        continue;
                     }
-                };
-
-                self.format = Some(format!("{}", context.format().description()));
-
-                let seconds = context.duration() as f64 / 1_000_000.0;
-                let duration = format!(
-                    "{:02}:{:02}:{:02}",
-                    (seconds / 3600.0) as u64 % 60,
-                    (seconds / 60.0) as u64 % 60,
-                    seconds as u64 % 60
-                );
-                self.duration = Some(duration);
-
-                self.bitrate = Some(format!(
-                    "{:.2} Mbps",
-                    context.bit_rate() as f64 / 1_000_000.0
-                ));
-
-                for (a, b) in context.metadata().iter() {
-                    let first_char = a.chars().next().unwrap_or(' ').to_uppercase().to_string();
-                    metadata_guard.push_back(Metadata {
-                        key: format! {"{}{}", first_char, &a[1..].to_lowercase()},
-                        value: b.to_string(),
+                    let info = match files.iter().position(|f| f.path == path) {
+                        Some(index) => files.swap_remove(index),
+                        None => FileInfo::new(path),
+                    };
+                    self.files.push(info);
+                }
+                if !self.is_known(self.selected.as_ref()) {
+                    self.selected = self.playing.clone();
+                }
+                if !self.is_known(self.compared.as_ref()) {
+                    self.compared = None;
+                }
+                self.update_names();
+                self.update_selection();
+            }
+            MediaInfoMsg::SetOrientation(orientation) => {
+                if let Some(file) = self.playing_mut() {
+                    file.orientation = Some(orientation);
+                }
+            }
+            MediaInfoMsg::SetChannelLayouts(source, output) => {
+                if let Some(file) = self.playing_mut() {
+                    file.layouts = Some((source, output));
+                }
+            }
+            MediaInfoMsg::SetLoudness(loudness) => {
+                if let Some(file) = self.playing_mut() {
+                    file.loudness = loudness;
+                }
+            }
+            MediaInfoMsg::Select(index) => {
+                self.selected = self.files.get(index as usize).map(|f| f.path.clone());
+            }
+            MediaInfoMsg::SetComparing(comparing) => {
+                self.comparing = comparing;
+                if comparing && self.compared.is_none() {
+                    // Start with another file, so there is something to compare
+                    self.compared = self
+                        .files
+                        .iter()
+                        .map(|f| &f.path)
+                        .find(|p| Some(*p) != self.selected.as_ref())
+                        .or(self.selected.as_ref())
+                        .cloned();
+                    self.update_selection();
+                }
+            }
+            MediaInfoMsg::CompareWith(index) => {
+                self.compared = self.files.get(index as usize).map(|f| f.path.clone());
+            }
+            MediaInfoMsg::Copy(format) => {
+                if let Some(path) = self.selected.clone() {
+                    sender.spawn_oneshot_command(move || {
+                        MediaInfoCommand::Report(format, report::read(&path))
                     });
                 }
             }
+            MediaInfoMsg::Export => {
+                if let Some(path) = self.selected.clone() {
+                    let name = path
+                        .file_stem()
+                        .map_or_else(|| String::from("media"), |s| s.to_string_lossy().into());
+                    let dialog = rfd::AsyncFileDialog::new()
+                        .set_title("Export Media Info")
+                        .set_file_name(format!("{name}.{}", ReportFormat::Text.extension()))
+                        .add_filter("Text", &[ReportFormat::Text.extension()])
+                        .add_filter("Markdown", &[ReportFormat::Markdown.extension()])
+                        .add_filter("JSON", &[ReportFormat::Json.extension()])
+                        .save_file();
+                    if let Some(file) = dialog.await {
+                        let output = file.path().to_path_buf();
+                        sender.spawn_oneshot_command(move || {
+                            let format = ReportFormat::from_path(&output);
+                            let written =
+                                report::read(&path)
+                                    .map_err(|e| e.to_string())
+                                    .and_then(|report| {
+                                        std::fs::write(&output, report.render(format))
+                                            .map_err(|e| e.to_string())
+                                    });
+                            MediaInfoCommand::Exported(output, written)
+                        });
+                    }
+                }
+            }
             MediaInfoMsg::Show => {
                 self.visible = true;
             }
@@ -170,5 +422,178 @@ impl SimpleAsyncComponent for MediaInfoWindow {
                 self.visible = false;
             }
         }
+        self.probe_shown(&sender);
+        self.render();
+    }
+
+    async fn update_cmd(
+        &mut self,
+        msg: Self::CommandOutput,
+        _sender: AsyncComponentSender<Self>,
+        root: &Self::Root,
+    ) {
+        match msg {
+            MediaInfoCommand::Probed(path, probe) => {
+                // Results for a file that was dropped in the meantime are discarded
+                let Some(index) = self.index_of(&path) else {
+                    return;
+                };
+                let file = &mut self.files[index];
+                file.loading = false;
+                file.probe = probe
+                    .inspect_err(|e| eprintln!("Error: Could not create context ({e})"))
+                    .ok();
+                self.render();
+            }
+            MediaInfoCommand::Report(format, report) => match report {
+                Ok(report) => root.clipboard().set_text(&report.render(format)),
+                Err(e) => eprintln!("Error: Could not read report ({e})"),
+            },
+            MediaInfoCommand::Exported(path, written) => {
+                if let Err(e) = written {
+                    eprintln!("Error: Could not export {} ({e})", path.display());
+                }
+            }
+        }
+    }
+}
+
+impl MediaInfoWindow {
+    fn index_of(&self, path: &Path) -> Option<usize> {
+        self.files.iter().position(|f| f.path == path)
+    }
+
+    fn is_known(&self, path: Option<&PathBuf>) -> bool {
+        path.is_some_and(|p| self.index_of(p).is_some())
+    }
+
+    fn file(&self, path: Option<&PathBuf>) -> Option<&FileInfo> {
+        path.and_then(|p| self.index_of(p)).map(|i| &self.files[i])
+    }
+
+    fn playing_mut(&mut self) -> Option<&mut FileInfo> {
+        let index = self.index_of(self.playing.as_ref()?)?;
+        Some(&mut self.files[index])
+    }
+
+    fn is_loading(&self) -> bool {
+        [&self.selected, &self.compared]
+            .into_iter()
+            .filter_map(|p| self.file(p.as_ref()))
+            .any(|f| f.loading)
+    }
+
+    /// Probes the shown files in the background unless that was done already. Probing can take
+    /// long on network mounts, so it never runs on the main loop.
+    fn probe_shown(&mut self, sender: &AsyncComponentSender<Self>) {
+        let mut shown = vec![self.selected.clone()];
+        if self.comparing {
+            shown.push(self.compared.clone());
+        }
+        for path in shown.into_iter().flatten() {
+            let Some(index) = self.index_of(&path) else {
+                continue;
+            };
+            let file = &mut self.files[index];
+            if file.probe.is_some() || file.loading {
+                continue;
+            }
+            file.loading = true;
+            sender.spawn_oneshot_command(move || {
+                let probe = probe::cached(&path).map(Ok).unwrap_or_else(|| {
+                    probe::probe(&path).inspect(|probe| probe::store(&path, probe))
+                });
+                MediaInfoCommand::Probed(path, probe)
+            });
+        }
+    }
+
+    fn update_names(&self) {
+        let names = self.files.iter().map(FileInfo::name).collect::<Vec<_>>();
+        let names = names.iter().map(String::as_str).collect::<Vec<_>>();
+        for (dropdown, handler) in [&self.file_dropdown, &self.compare_dropdown] {
+            dropdown.block_signal(handler);
+        }
+        self.names.splice(0, self.names.n_items(), &names);
+        for (dropdown, handler) in [&self.file_dropdown, &self.compare_dropdown] {
+            dropdown.unblock_signal(handler);
+        }
+    }
+
+    /// Shows the selected and compared files in the dropdowns.
+    fn update_selection(&self) {
+        for ((dropdown, handler), path) in [
+            (&self.file_dropdown, &self.selected),
+            (&self.compare_dropdown, &self.compared),
+        ] {
+            let position = path
+                .as_ref()
+                .and_then(|p| self.index_of(p))
+                .map_or(gtk::INVALID_LIST_POSITION, |i| i as u32);
+            dropdown.block_signal(handler);
+            dropdown.set_selected(position);
+            dropdown.unblock_signal(handler);
+        }
+    }
+
+    /// Rebuilds the rows for the selected file. When comparing, the compared file's values are
+    /// shown next to them and rows that differ are highlighted.
+    fn render(&mut self) {
+        for group in self.groups.drain(..) {
+            self.page.remove(&group);
+        }
+
+        let fields_of = |path: Option<&PathBuf>| {
+            self.file(path)
+                .map(FileInfo::fields)
+                .unwrap_or_else(|| FileInfo::new(PathBuf::new()).fields())
+        };
+        let fields = fields_of(self.selected.as_ref());
+        let other = self.comparing.then(|| fields_of(self.compared.as_ref()));
+
+        for name in GROUPS {
+            let mut rows = fields
+                .iter()
+                .filter(|(group, _, _)| *group == name)
+                .map(|(_, key, value)| (key.clone(), value.clone()))
+                .collect::<Vec<_>>();
+            // Fields only the compared file has, such as extra tags, go at the end
+            for (group, key, _) in other.iter().flatten() {
+                if *group == name && !rows.iter().any(|(k, _)| k == key) {
+                    rows.push((key.clone(), String::from("N/A")));
+                }
+            }
+            if rows.is_empty() {
+                continue;
+            }
+
+            let group = adw::PreferencesGroup::builder().title(name).build();
+            for (key, value) in rows {
+                let row = adw::ActionRow::builder().title(&key).build();
+                let label = gtk::Label::builder().label(&value).wrap(true).build();
+                row.add_suffix(&label);
+
+                if let Some(other) = &other {
+                    let other_value = other
+                        .iter()
+                        .find(|(g, k, _)| *g == name && *k == key)
+                        .map_or_else(|| String::from("N/A"), |(_, _, v)| v.clone());
+                    let other_label = gtk::Label::builder()
+                        .label(&other_value)
+                        .wrap(true)
+                        .width_chars(12)
+                        .build();
+                    if other_value != value {
+                        label.add_css_class("warning");
+                        other_label.add_css_class("warning");
+                    }
+                    row.add_suffix(&gtk::Separator::new(gtk::Orientation::Vertical));
+                    row.add_suffix(&other_label);
+                }
+                group.add(&row);
+            }
+            self.page.add(&group);
+            self.groups.push(group);
+        }
     }
 }
@@ -0,0 +1,410 @@
+use std::path::Path;
+use std::time::Duration;
+
+use adw::prelude::*;
+use gtk::{gdk, gio, glib, glib::clone, graphene, subclass::prelude::*};
+
+use crate::decoder::{AudioFilters, Decoder, Event, SeekMode, VideoFilters};
+use crate::presentation::{self, Bars, Crop, Orientation, Presentation};
+use crate::timecode::FrameRate;
+
+/// Frames between black bar detections
+const DETECT_INTERVAL: u32 = 12;
+
+mod imp {
+    use std::cell::{Cell, RefCell};
+
+    use super::*;
+
+    #[derive(Default, glib::Properties)]
+    #[properties(wrapper_type = super::Playback)]
+    pub struct Playback {
+        #[property(get)]
+        buffering: Cell<bool>,
+        /// Why playback last went on differently than requested
+        #[property(get)]
+        warning: RefCell<String>,
+        pub(super) decoder: RefCell<Option<Decoder>>,
+        texture: RefCell<Option<gdk::Texture>>,
+        pixel_aspect: Cell<f64>,
+        /// Software gain applied on top of the stream volume
+        pub(super) boost: Cell<f64>,
+        pub(super) presentation: Cell<Presentation>,
+        pub(super) video_filters: RefCell<VideoFilters>,
+        pub(super) audio_filters: RefCell<AudioFilters>,
+        /// Rotation and mirroring stored in the video stream
+        stream_orientation: Cell<Orientation>,
+        /// Channels of the audio stream and of the output device
+        pub(super) channels: Cell<Option<(u16, u16)>>,
+        pub(super) frame_rate: Cell<Option<FrameRate>>,
+        pub(super) seek_mode: Cell<SeekMode>,
+        /// Whether a button or finger is held on the seek bar, so seeks come from dragging it
+        pub(super) scrubbing: Cell<bool>,
+        /// Last position scrubbed to, sought again exactly once the drag ends
+        pub(super) scrubbed: Cell<Option<i64>>,
+        /// Black bars detected so far, for automatic cropping
+        pub(super) bars: Cell<Option<Bars>>,
+        frames: Cell<u32>,
+        pub(super) overflow: Cell<(f64, f64)>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for Playback {
+        const NAME: &'static str = "SimpleVideoPlayerPlayback";
+        type Type = super::Playback;
+        type ParentType = gtk::MediaStream;
+        type Interfaces = (gdk::Paintable,);
+    }
+
+    #[glib::derived_properties]
+    impl ObjectImpl for Playback {}
+
+    impl MediaStreamImpl for Playback {
+        fn play(&self) -> bool {
+            if let Some(decoder) = self.decoder.borrow().as_ref() {
+                decoder.play();
+            }
+            true
+        }
+
+        fn pause(&self) {
+            if let Some(decoder) = self.decoder.borrow().as_ref() {
+                decoder.pause();
+            }
+        }
+
+        fn seek(&self, timestamp: i64) {
+            let mode = if self.scrubbing.get() {
+                self.scrubbed.set(Some(timestamp));
+                SeekMode::Fast
+            } else {
+                self.seek_mode.get()
+            };
+            match self.decoder.borrow().as_ref() {
+                Some(decoder) => decoder.seek(timestamp, mode),
+                None => self.obj().seek_failed(),
+            }
+        }
+
+        fn update_audio(&self, muted: bool, volume: f64) {
+            if let Some(decoder) = self.decoder.borrow().as_ref() {
+                decoder.set_volume(if muted {
+                    0.0
+                } else {
+                    volume * self.boost.get()
+                });
+            }
+        }
+    }
+
+    impl PaintableImpl for Playback {
+        fn current_image(&self) -> gdk::Paintable {
+            match self.texture.borrow().as_ref() {
+                Some(texture) => texture.clone().upcast(),
+                None => gdk::Paintable::new_empty(0, 0),
+            }
+        }
+
+        // No intrinsic size is reported, so the whole area is available to the presentation
+        // modes and they do their own letterboxing.
+        fn snapshot(&self, snapshot: &gdk::Snapshot, width: f64, height: f64) {
+            let texture = self.texture.borrow();
+            let Some(texture) = texture.as_ref() else {
+                return;
+            };
+            let snapshot = snapshot.downcast_ref::<gtk::Snapshot>().unwrap();
+
+            let (texture_width, texture_height) = (texture.width() as f64, texture.height() as f64);
+            let presentation = self.effective_presentation();
+            let layout = presentation.layout(
+                (texture_width, texture_height),
+                self.pixel_aspect.get(),
+                self.bars.get(),
+                (width, height),
+            );
+            self.overflow.set(layout.overflow);
+
+            let (source, target) = (layout.source, layout.target);
+            let orientation = presentation.orientation;
+            // Size of the target before rotation
+            let (target_width, target_height) = if orientation.is_transposed() {
+                (target.height(), target.width())
+            } else {
+                (target.width(), target.height())
+            };
+            let scale_x = target_width / source.width();
+            let scale_y = target_height / source.height();
+
+            snapshot.push_clip(&graphene::Rect::new(0.0, 0.0, width as f32, height as f32));
+            snapshot.save();
+            snapshot.translate(&target.center());
+            if orientation.mirrored {
+                snapshot.scale(-1.0, 1.0);
+            }
+            snapshot.rotate(orientation.rotation as f32);
+
+            let (left, top) = (-target_width / 2.0, -target_height / 2.0);
+            snapshot.push_clip(&graphene::Rect::new(left, top, target_width, target_height));
+            snapshot.append_texture(
+                texture,
+                &graphene::Rect::new(
+                    left - source.x() * scale_x,
+                    top - source.y() * scale_y,
+                    texture_width as f32 * scale_x,
+                    texture_height as f32 * scale_y,
+                ),
+            );
+            snapshot.pop();
+            snapshot.restore();
+            snapshot.pop();
+        }
+    }
+
+    impl Playback {
+        /// Returns the presentation with the stream's own orientation applied first.
+        pub(super) fn effective_presentation(&self) -> Presentation {
+            let mut presentation = self.presentation.get();
+            presentation.orientation = self.stream_orientation.get().then(presentation.orientation);
+            presentation
+        }
+
+        /// Forwards decoder events to the stream and presents the next due frame.
+        pub(super) fn tick(&self) {
+            let obj = self.obj();
+            let decoder = self.decoder.borrow();
+            let Some(decoder) = decoder.as_ref() else {
+                return;
+            };
+
+            for event in decoder.events() {
+                match event {
+                    Event::Prepared {
+                        has_audio,
+                        has_video,
+                        seekable,
+                        duration,
+                        orientation,
+                        channels,
+                        frame_rate,
+                    } => {
+                        self.stream_orientation.set(orientation);
+                        self.channels.set(channels);
+                        self.frame_rate.set(frame_rate);
+                        obj.stream_prepared(has_audio, has_video, seekable, duration);
+                    }
+                    Event::Seeked => {
+                        // Seeks overlap while dragging, and the stream waits for one of them
+                        if obj.is_seeking() {
+                            obj.seek_success();
+                        }
+                        obj.update(decoder.position().max(0));
+                    }
+                    Event::Error(message) => {
+                        eprintln!("Error: Playback failed ({message})");
+                        obj.set_error(glib::Error::new(gio::IOErrorEnum::Failed, &message));
+                    }
+                    Event::Warning(message) => {
+                        eprintln!("Error: {message}");
+                        self.warning.replace(message);
+                        obj.notify_warning();
+                    }
+                }
+            }
+
+            if !obj.is_prepared() {
+                return;
+            }
+
+            let buffering = decoder.sync();
+            if buffering != self.buffering.get() {
+                self.buffering.set(buffering);
+                obj.notify_buffering();
+            }
+
+            if let Some(frame) = decoder.take_frame() {
+                if self.presentation.get().crop == Crop::Auto
+                    && self.frames.get() % DETECT_INTERVAL == 0
+                {
+                    let detected = presentation::detect_bars(
+                        &frame.data,
+                        frame.width as usize,
+                        frame.height as usize,
+                        frame.stride,
+                    );
+                    if let Some(detected) = detected {
+                        let bars = self.bars.get().map_or(detected, |b| b.min(detected));
+                        if self.bars.replace(Some(bars)) != Some(bars) {
+                            obj.invalidate_size();
+                        }
+                    }
+                }
+                self.frames.set(self.frames.get().wrapping_add(1));
+
+                let resized = self.texture.borrow().as_ref().map_or(true, |t| {
+                    t.width() != frame.width as i32 || t.height() != frame.height as i32
+                }) || self.pixel_aspect.get() != frame.pixel_aspect;
+
+                let texture = gdk::MemoryTexture::new(
+                    frame.width as i32,
+                    frame.height as i32,
+                    gdk::MemoryFormat::R8g8b8a8,
+                    &glib::Bytes::from_owned(frame.data),
+                    frame.stride,
+                );
+                self.texture.replace(Some(texture.upcast()));
+                self.pixel_aspect.set(frame.pixel_aspect);
+
+                if resized {
+                    obj.invalidate_size();
+                }
+                obj.invalidate_contents();
+            }
+
+            if obj.is_playing() {
+                obj.update(decoder.position().max(0));
+            }
+
+            if decoder.is_finished() && !obj.is_ended() {
+                obj.stream_ended();
+            }
+        }
+    }
+}
+
+glib::wrapper! {
+    /// A media stream decoded with ffmpeg, supporting local files as well as network streams.
+    pub struct Playback(ObjectSubclass<imp::Playback>)
+        @extends gtk::MediaStream,
+        @implements gdk::Paintable;
+}
+
+impl Playback {
+    pub fn new(location: &Path, presentation: Presentation) -> Self {
+        let obj: Self = glib::Object::new();
+        obj.imp().decoder.replace(Some(Decoder::open(location)));
+        obj.imp().presentation.set(presentation);
+        obj.imp().boost.set(1.0);
+
+        glib::timeout_add_local(
+            Duration::from_millis(5),
+            clone!(
+                #[weak]
+                obj,
+                #[upgrade_or]
+                glib::ControlFlow::Break,
+                move || {
+                    obj.imp().tick();
+                    glib::ControlFlow::Continue
+                }
+            ),
+        );
+
+        obj
+    }
+    pub fn set_presentation(&self, presentation: Presentation) {
+        let imp = self.imp();
+        let previous = imp.presentation.replace(presentation);
+        if previous == presentation {
+            return;
+        }
+        if previous.crop != presentation.crop {
+            imp.bars.set(None);
+            imp.frames.set(0);
+        }
+        self.invalidate_size();
+        self.invalidate_contents();
+    }
+
+    pub fn set_video_filters(&self, filters: &VideoFilters) {
+        let imp = self.imp();
+        if imp.video_filters.replace(filters.clone()) != *filters {
+            if let Some(decoder) = imp.decoder.borrow().as_ref() {
+                decoder.set_video_filters(filters.clone());
+            }
+        }
+    }
+
+    /// Amplifies audio beyond the stream's maximum volume of 1.
+    pub fn set_boost(&self, boost: f64) {
+        let imp = self.imp();
+        if imp.boost.replace(boost) != boost {
+            imp.update_audio(self.is_muted(), self.volume());
+        }
+    }
+
+    pub fn set_audio_delay(&self, delay: i64) {
+        if let Some(decoder) = self.imp().decoder.borrow().as_ref() {
+            decoder.set_audio_delay(delay);
+        }
+    }
+
+    pub fn set_audio_filters(&self, filters: &AudioFilters) {
+        let imp = self.imp();
+        if imp.audio_filters.replace(filters.clone()) != *filters {
+            if let Some(decoder) = imp.decoder.borrow().as_ref() {
+                decoder.set_audio_filters(filters.clone());
+            }
+        }
+    }
+
+    pub fn set_seek_mode(&self, mode: SeekMode) {
+        self.imp().seek_mode.set(mode);
+    }
+
+    /// Seeks to keyframes while the seek bar is dragged, which keeps up with the pointer, then
+    /// to the exact position it was released at.
+    pub fn set_scrubbing(&self, scrubbing: bool) {
+        let imp = self.imp();
+        if imp.scrubbing.replace(scrubbing) == scrubbing || scrubbing {
+            return;
+        }
+        if let Some(timestamp) = imp.scrubbed.take() {
+            if imp.seek_mode.get() == SeekMode::Exact {
+                self.seek(timestamp);
+            }
+        }
+    }
+
+    /// Returns the orientation the video is shown in, including the stream's own rotation.
+    pub fn orientation(&self) -> Orientation {
+        self.imp().effective_presentation().orientation
+    }
+
+    /// Returns the channel count of the audio stream and of the output device, once prepared.
+    pub fn channels(&self) -> Option<(u16, u16)> {
+        self.imp().channels.get()
+    }
+
+    /// Returns the average frame rate of the video, once prepared.
+    pub fn frame_rate(&self) -> Option<FrameRate> {
+        self.imp().frame_rate.get()
+    }
+
+    /// Returns the most recently played audio, mixed to mono, with its sample rate.
+    pub fn scope(&self) -> (Vec<f32>, u32) {
+        self.imp()
+            .decoder
+            .borrow()
+            .as_ref()
+            .map_or((Vec::new(), 0), Decoder::scope)
+    }
+
+    /// Returns how far the video extends past the widget, as last drawn.
+    pub fn overflow(&self) -> (f64, f64) {
+        self.imp().overflow.get()
+    }
+
+    /// Returns the size of the video as displayed, after aspect ratio overrides and cropping.
+    pub fn display_size(&self) -> (i32, i32) {
+        let imp = self.imp();
+        imp.texture.borrow().as_ref().map_or((0, 0), |texture| {
+            let (width, height) = imp.effective_presentation().display_size(
+                texture.width() as f64,
+                texture.height() as f64,
+                imp.pixel_aspect.get(),
+                imp.bars.get(),
+            );
+            (width.round() as i32, height.round() as i32)
+        })
+    }
+}
@@ -1,51 +1,559 @@
+use std::cell::{Cell, RefCell};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::Duration;
+
 use adw::prelude::*;
+use gtk::{gdk, glib, glib::clone};
 use relm4::prelude::*;
 
+use crate::artwork::{self, AudioInfo};
+use crate::decoder::{
+    AudioEqualizer, AudioFilters, ChannelRouting, Deinterlace, Deinterlacer, FieldOrder, SeekMode,
+    VideoAdjustments, VideoFilters,
+};
+use crate::playback::Playback;
+use crate::playlist;
+use crate::presentation::{Crop, Fit, Orientation, Presentation};
+use crate::review::ReviewPoint;
+use crate::subtitles::Subtitles;
+use crate::timecode::{self, FrameRate};
+use crate::visualizer::{Visualization, Visualizer};
+
+/// How often the resume position is reported, in microseconds
+const POSITION_INTERVAL: i64 = 5_000_000;
+/// Seek step of the arrow keys, in microseconds
+const SEEK_STEP: i64 = 10_000_000;
+/// Bookmarks this close to the position are skipped when jumping, in microseconds
+const MARK_TOLERANCE: i64 = 500_000;
+/// Size of the bookmark ticks along the bottom of the video, in pixels
+const MARK_HEIGHT: i32 = 4;
+const MARK_WIDTH: f64 = 3.0;
+/// Zoom factor per key press or scroll step
+pub const ZOOM_STEP: f64 = 1.25;
+/// Files are resumed from the start when stopped this close to either end, in microseconds
+const RESUME_MARGIN: i64 = 10_000_000;
+/// How long on-screen messages stay visible
+const OSD_DURATION: Duration = Duration::from_millis(1500);
+/// Highest volume with boost enabled
+const MAX_BOOST: f64 = 2.0;
+
+/// Volume settings, kept across files and sessions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Volume {
+    /// From 0 to 1, or up to 2 with boost enabled
+    pub level: f64,
+    pub muted: bool,
+    /// Change per key press or scroll step
+    pub step: f64,
+    /// Allow levels above 100%, amplified in software with a limiter
+    pub boost: bool,
+}
+
+impl Default for Volume {
+    fn default() -> Self {
+        Self {
+            level: 1.0,
+            muted: false,
+            step: 0.1,
+            boost: false,
+        }
+    }
+}
+
+impl Volume {
+    fn max(&self) -> f64 {
+        if self.boost {
+            MAX_BOOST
+        } else {
+            1.0
+        }
+    }
+
+    pub fn description(&self) -> String {
+        if self.muted {
+            String::from("Muted")
+        } else {
+            format!("Volume: {:.0}%", self.level * 100.0)
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct Player {
-    path: Option<std::path::PathBuf>,
+    path: Option<PathBuf>,
+    subtitles: Option<PathBuf>,
     playing: bool,
-    seek: i8,
-    volume: i8,
+    seek_to: Option<i64>,
+    seek_by: Option<i64>,
+    seek_mode: SeekMode,
+    volume: Volume,
+    /// Level last set on the stream, which its controls report back without any change by the
+    /// user
+    stream_volume: Rc<Cell<f64>>,
+    presentation: Presentation,
+    video_filters: VideoFilters,
+    audio_filters: AudioFilters,
+    /// In microseconds, positive values play audio and subtitles later
+    audio_delay: i64,
+    subtitle_delay: i64,
+    osd: Option<String>,
+    visualization: Visualization,
+    /// Bookmarks of the current file, with their names
+    marks: Vec<(i64, String)>,
+    bookmark: bool,
+    /// Jump to the next bookmark, or the previous one when false
+    jump_mark: Option<bool>,
+    review: Option<ReviewPoint>,
+    playhead: bool,
+}
+
+/// Cover art, tags and a visualizer shown in place of the video for audio files.
+#[derive(Clone)]
+struct AudioView {
+    root: gtk::Box,
+    cover: gtk::Picture,
+    title: gtk::Label,
+    tags: gtk::Label,
+    visualizer: gtk::DrawingArea,
+}
+
+impl AudioView {
+    fn new(player: &gtk::Video, visualization: Rc<Cell<Visualization>>) -> Self {
+        let root = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .spacing(12)
+            .valign(gtk::Align::Center)
+            .margin_start(24)
+            .margin_end(24)
+            // Leave room for the playback controls, which stay visible for audio
+            .margin_bottom(72)
+            .can_target(false)
+            .visible(false)
+            .build();
+
+        let cover = gtk::Picture::builder()
+            .content_fit(gtk::ContentFit::Contain)
+            .height_request(240)
+            .vexpand(true)
+            .build();
+        root.append(&cover);
+
+        let title = gtk::Label::builder()
+            .css_classes(["title-2"])
+            .ellipsize(gtk::pango::EllipsizeMode::End)
+            .build();
+        root.append(&title);
+
+        let tags = gtk::Label::builder()
+            .css_classes(["dim-label"])
+            .ellipsize(gtk::pango::EllipsizeMode::End)
+            .build();
+        root.append(&tags);
+
+        let visualizer = gtk::DrawingArea::builder()
+            .height_request(96)
+            .hexpand(true)
+            .build();
+        let state = RefCell::new(Visualizer::default());
+        visualizer.set_draw_func(clone!(
+            #[weak]
+            player,
+            move |_, cr, width, height| {
+                let Some(media) = player.media_stream().and_downcast::<Playback>() else {
+                    return;
+                };
+                let (samples, rate) = media.scope();
+                state.borrow_mut().draw(
+                    cr,
+                    visualization.get(),
+                    (&samples, rate),
+                    width as f64,
+                    height as f64,
+                );
+            }
+        ));
+        visualizer.add_tick_callback(|area, _| {
+            if area.is_mapped() {
+                area.queue_draw();
+            }
+            glib::ControlFlow::Continue
+        });
+        root.append(&visualizer);
+
+        Self {
+            root,
+            cover,
+            title,
+            tags,
+            visualizer,
+        }
+    }
+
+    fn show(&self, location: &Path) {
+        let info = if playlist::is_url(location) {
+            AudioInfo::default()
+        } else {
+            artwork::probe(location)
+        };
+        let name = location.file_name().map_or_else(
+            || location.display().to_string(),
+            |n| n.to_string_lossy().to_string(),
+        );
+        self.title.set_text(info.title.as_deref().unwrap_or(&name));
+        let subtitle = info.subtitle();
+        self.tags.set_text(&subtitle);
+        self.tags.set_visible(!subtitle.is_empty());
+        self.cover.set_paintable(info.cover.as_ref());
+        self.cover.set_visible(info.cover.is_some());
+        self.root.set_visible(true);
+    }
+
+    fn hide(&self) {
+        self.root.set_visible(false);
+        self.cover.set_paintable(None::<&gdk::Paintable>);
+    }
 }
 
 pub struct PlayerWidgets {
     player: gtk::Video,
+    subtitle: gtk::Label,
+    subtitle_delay: Rc<Cell<i64>>,
+    spinner: gtk::Spinner,
+    osd: gtk::Label,
+    /// Incremented for every message, so only the latest one's timeout hides the OSD
+    osd_generation: Rc<Cell<u32>>,
+    path: Option<PathBuf>,
+    subtitles_path: Option<PathBuf>,
+    subtitles: Rc<RefCell<Option<Subtitles>>>,
+    /// Seek requested before the stream was prepared
+    pending_seek: Rc<Cell<Option<i64>>>,
+    /// Manual orientation last applied to the stream
+    orientation: Orientation,
+    audio_view: AudioView,
+    visualization: Rc<Cell<Visualization>>,
+    /// Bookmark positions, drawn along the bottom edge of the video
+    marks: Rc<RefCell<Vec<i64>>>,
+    marks_strip: gtk::DrawingArea,
 }
 
 #[derive(Debug)]
 pub enum PlayerMsg {
-    SetVideo(std::path::PathBuf),
+    SetVideo(PathBuf),
+    SetSubtitles(PathBuf),
+    SeekTo(i64),
+    /// Seeks relative to the current position, in microseconds
+    SeekBy(i64),
+    SetSeekMode(SeekMode),
+    /// Sets the bookmarks shown on the seek bar, as positions and names
+    SetMarks(Vec<(i64, String)>),
+    /// Bookmarks the current position
+    Bookmark,
+    NextMark,
+    PreviousMark,
+    /// Takes the current frame for a review comment
+    Review(ReviewPoint),
+    /// Reports the current position, for editing chapters
+    Playhead,
+    SetAspect(Option<f64>),
+    SetCrop(Crop),
+    SetFit(Fit),
+    /// Multiplies the zoom level by a factor
+    Zoom(f64),
+    ResetZoom,
+    /// Pans by a fraction of the video's overflow
+    Pan(f64, f64),
+    /// Rotates or flips on top of the current orientation
+    Orient(Orientation),
+    ResetOrientation,
+    SetDeinterlace(Deinterlace),
+    SetDeinterlacer(Deinterlacer),
+    SetFieldOrder(FieldOrder),
+    SetAdjustments(VideoAdjustments),
+    /// Sets the custom video filter chain, in `-vf` syntax
+    SetVideoFilter(String),
+    /// Sets the custom audio filter chain, in `-af` syntax
+    SetAudioFilter(String),
+    /// Sets the loudness normalization gain in dB
+    SetGain(f64),
+    SetEqualizer(AudioEqualizer),
+    SetRouting(ChannelRouting),
+    SetAudioDelay(i64),
+    SetSubtitleDelay(i64),
+    /// Shows a short message over the video
+    ShowOsd(String),
+    SetVisualization(Visualization),
     PlayPause,
     SeekForwards,
     SeekBackwards,
     VolumeUp,
     VolumeDown,
+    ToggleMute,
+    SetVolumeStep(f64),
+    SetBoost(bool),
+    /// Volume changed with the video controls
+    SyncVolume(f64, bool),
+}
+
+#[derive(Debug)]
+pub enum PlayerOutput {
+    Ended,
+    /// Playback position to resume from, reported every few seconds
+    Position(i64),
+    /// Display size of the video, reported once its first frame is decoded
+    VideoSize(i32, i32),
+    /// Orientation the video is shown in, including the stream's own rotation
+    Orientation(Orientation),
+    /// Playback went on differently than requested, such as when a custom filter was dropped
+    Warning(String),
+    Volume(Volume),
+    /// Channels of the audio stream and of the output device, reported once prepared
+    Channels(u16, u16),
+    /// Duration and video frame rate, reported once prepared
+    Timing(i64, Option<FrameRate>),
+    /// Position to bookmark
+    Bookmark(i64),
+    /// Start of the frame taken for a review comment
+    Review(ReviewPoint, i64),
+    Playhead(i64),
+}
+
+/// Whether a press lands on the seek bar of `player`'s controls, the only horizontal scale
+/// inside it. The widget under the pointer is picked rather than the controls searched, as
+/// their layout is private.
+fn on_seek_bar(player: &gtk::Video, event: &gdk::Event) -> bool {
+    let (Some(surface), Some((x, y))) = (event.surface(), event.position()) else {
+        return false;
+    };
+    let Some(native) = gtk::Native::for_surface(&surface) else {
+        return false;
+    };
+    let (dx, dy) = native.surface_transform();
+    native
+        .pick(x - dx, y - dy, gtk::PickFlags::DEFAULT)
+        .and_then(|widget| widget.ancestor(gtk::Scale::static_type()))
+        .and_downcast::<gtk::Scale>()
+        .is_some_and(|scale| {
+            scale.orientation() == gtk::Orientation::Horizontal && scale.is_ancestor(player)
+        })
+}
+
+fn resume_position(media: &gtk::MediaStream) -> i64 {
+    let position = media.timestamp();
+    let duration = media.duration();
+    if position < RESUME_MARGIN || (duration > 0 && position > duration - RESUME_MARGIN) {
+        0
+    } else {
+        position
+    }
 }
 
 impl SimpleComponent for Player {
-    type Init = ();
+    type Init = Volume;
     type Input = PlayerMsg;
-    type Output = ();
+    type Output = PlayerOutput;
     type Root = gtk::Box;
     type Widgets = PlayerWidgets;
 
     fn init(
         _init: Self::Init,
         root: Self::Root,
-        _sender: ComponentSender<Self>,
+        sender: ComponentSender<Self>,
     ) -> ComponentParts<Self> {
         let model = Self {
             playing: false,
             path: None,
-            seek: 1,
-            volume: 1,
+            subtitles: None,
+            seek_to: None,
+            seek_by: None,
+            seek_mode: SeekMode::default(),
+            volume: init,
+            stream_volume: Rc::default(),
+            presentation: Presentation::default(),
+            video_filters: VideoFilters::default(),
+            audio_filters: AudioFilters::default(),
+            audio_delay: 0,
+            subtitle_delay: 0,
+            osd: None,
+            visualization: Visualization::default(),
+            marks: Vec::new(),
+            bookmark: false,
+            jump_mark: None,
+            review: None,
+            playhead: false,
         };
 
+        let overlay = gtk::Overlay::new();
+        root.append(&overlay);
+
         let player = gtk::Video::builder().vexpand(true).hexpand(true).build();
-        root.append(&player);
+        overlay.set_child(Some(&player));
+
+        let visualization = Rc::new(Cell::new(Visualization::default()));
+        let audio_view = AudioView::new(&player, visualization.clone());
+        overlay.add_overlay(&audio_view.root);
 
-        let widgets = PlayerWidgets { player };
+        let subtitle = gtk::Label::builder()
+            .halign(gtk::Align::Center)
+            .valign(gtk::Align::End)
+            .margin_bottom(64)
+            .justify(gtk::Justification::Center)
+            .wrap(true)
+            .visible(false)
+            .css_classes(["osd", "title-3"])
+            .build();
+        overlay.add_overlay(&subtitle);
+
+        let spinner = gtk::Spinner::builder()
+            .halign(gtk::Align::Center)
+            .valign(gtk::Align::Center)
+            .width_request(48)
+            .height_request(48)
+            .visible(false)
+            .build();
+        overlay.add_overlay(&spinner);
+
+        // Bookmarks are drawn in a strip of their own, spread over the duration like the seek
+        // bar, which is private to the controls
+        let marks = Rc::new(RefCell::new(Vec::<i64>::new()));
+        let marks_strip = gtk::DrawingArea::builder()
+            .height_request(MARK_HEIGHT)
+            .hexpand(true)
+            .valign(gtk::Align::End)
+            .can_target(false)
+            .build();
+        marks_strip.set_draw_func(clone!(
+            #[weak]
+            player,
+            #[strong]
+            marks,
+            move |_, cr, width, height| {
+                let duration = player.media_stream().map_or(0, |media| media.duration());
+                if duration <= 0 {
+                    return;
+                }
+                cr.set_source_rgba(0.96, 0.76, 0.07, 0.9);
+                for position in marks.borrow().iter() {
+                    let x = width as f64 * (*position as f64 / duration as f64).clamp(0.0, 1.0);
+                    cr.rectangle(x - MARK_WIDTH / 2.0, 0.0, MARK_WIDTH, height as f64);
+                }
+                let _ = cr.fill();
+            }
+        ));
+        overlay.add_overlay(&marks_strip);
+
+        let osd = gtk::Label::builder()
+            .halign(gtk::Align::Start)
+            .valign(gtk::Align::Start)
+            .margin_start(24)
+            .margin_top(24)
+            .visible(false)
+            .css_classes(["osd", "title-4"])
+            .build();
+        overlay.add_overlay(&osd);
+
+        let scroll = gtk::EventControllerScroll::new(gtk::EventControllerScrollFlags::VERTICAL);
+        scroll.connect_scroll(clone!(
+            #[strong]
+            sender,
+            move |scroll, _, dy| {
+                if scroll
+                    .current_event_state()
+                    .contains(gdk::ModifierType::CONTROL_MASK)
+                {
+                    sender.input(PlayerMsg::Zoom(if dy < 0.0 {
+                        ZOOM_STEP
+                    } else {
+                        1.0 / ZOOM_STEP
+                    }));
+                } else if dy < 0.0 {
+                    sender.input(PlayerMsg::VolumeUp);
+                } else if dy > 0.0 {
+                    sender.input(PlayerMsg::VolumeDown);
+                }
+                glib::Propagation::Stop
+            }
+        ));
+        overlay.add_controller(scroll);
+
+        let drag = gtk::GestureDrag::new();
+        let dragged = Rc::new(Cell::new((0.0, 0.0)));
+        drag.connect_drag_begin(clone!(
+            #[strong]
+            dragged,
+            move |_, _, _| dragged.set((0.0, 0.0))
+        ));
+        drag.connect_drag_update(clone!(
+            #[weak]
+            player,
+            move |_, x, y| {
+                let Some(media) = player.media_stream().and_downcast::<Playback>() else {
+                    return;
+                };
+                let (last_x, last_y) = dragged.replace((x, y));
+                let (overflow_x, overflow_y) = media.overflow();
+                let pan = |delta: f64, overflow: f64| {
+                    if overflow > 0.0 {
+                        delta / (overflow / 2.0)
+                    } else {
+                        0.0
+                    }
+                };
+                sender.input(PlayerMsg::Pan(
+                    pan(x - last_x, overflow_x),
+                    pan(y - last_y, overflow_y),
+                ));
+            }
+        ));
+        overlay.add_controller(drag);
+
+        // Seeks made while a button or finger is down on the seek bar come from dragging or
+        // clicking it. Raw events are watched rather than the scale itself, which is private to
+        // the controls, and a gesture would be cancelled once the scale claims the sequence.
+        let scrub = gtk::EventControllerLegacy::new();
+        scrub.set_propagation_phase(gtk::PropagationPhase::Capture);
+        scrub.connect_event(clone!(
+            #[weak]
+            player,
+            #[upgrade_or]
+            glib::Propagation::Proceed,
+            move |_, event| {
+                let Some(media) = player.media_stream().and_downcast::<Playback>() else {
+                    return glib::Propagation::Proceed;
+                };
+                match event.event_type() {
+                    gdk::EventType::ButtonPress | gdk::EventType::TouchBegin
+                        if on_seek_bar(&player, event) =>
+                    {
+                        media.set_scrubbing(true);
+                    }
+                    gdk::EventType::ButtonRelease
+                    | gdk::EventType::TouchEnd
+                    | gdk::EventType::TouchCancel => media.set_scrubbing(false),
+                    _ => {}
+                }
+                glib::Propagation::Proceed
+            }
+        ));
+        player.add_controller(scrub);
+
+        let widgets = PlayerWidgets {
+            player,
+            subtitle,
+            subtitle_delay: Rc::new(Cell::new(0)),
+            spinner,
+            osd,
+            osd_generation: Rc::new(Cell::new(0)),
+            path: None,
+            subtitles_path: None,
+            subtitles: Rc::new(RefCell::new(None)),
+            pending_seek: Rc::new(Cell::new(None)),
+            orientation: Orientation::default(),
+            audio_view,
+            visualization,
+            marks,
+            marks_strip,
+        };
 
         ComponentParts { model, widgets }
     }
@@ -56,40 +564,428 @@ impl SimpleComponent for Player {
             .build()
     }
 
-    fn update_view(&self, widgets: &mut Self::Widgets, _sender: ComponentSender<Self>) {
-        if widgets.player.file().is_none() {
-            widgets.player.set_filename(self.path.as_ref());
+    fn update_view(&self, widgets: &mut Self::Widgets, sender: ComponentSender<Self>) {
+        if widgets.subtitles_path != self.subtitles {
+            widgets.subtitles_path = self.subtitles.clone();
+            *widgets.subtitles.borrow_mut() = self.subtitles.as_ref().and_then(|path| {
+                Subtitles::load(path)
+                    .inspect_err(|e| eprintln!("Error: Could not load subtitles ({e})"))
+                    .ok()
+            });
+            widgets.subtitle.set_visible(false);
+        }
+        widgets.subtitle_delay.set(self.subtitle_delay);
+        widgets.visualization.set(self.visualization);
+        widgets
+            .audio_view
+            .visualizer
+            .set_visible(self.visualization != Visualization::Off);
+
+        if let Some(text) = &self.osd {
+            widgets.show_osd(text);
+        }
+
+        let marks = self.marks.iter().map(|(p, _)| *p).collect::<Vec<_>>();
+        if *widgets.marks.borrow() != marks {
+            *widgets.marks.borrow_mut() = marks;
+            widgets.marks_strip.queue_draw();
+        }
+
+        if widgets.path != self.path {
+            widgets.path = self.path.clone();
+            if let Some(path) = &self.path {
+                let media = Playback::new(path, self.presentation);
+                widgets.audio_view.hide();
+
+                media.connect_buffering_notify(clone!(
+                    #[weak(rename_to = spinner)]
+                    widgets.spinner,
+                    move |media| {
+                        spinner.set_visible(media.buffering());
+                        spinner.set_spinning(media.buffering());
+                    }
+                ));
+
+                widgets.pending_seek.set(None);
+                let location = path.clone();
+                media.connect_prepared_notify(clone!(
+                    #[strong(rename_to = pending_seek)]
+                    widgets.pending_seek,
+                    #[strong]
+                    sender,
+                    #[weak(rename_to = player)]
+                    widgets.player,
+                    #[strong(rename_to = audio_view)]
+                    widgets.audio_view,
+                    #[weak(rename_to = marks_strip)]
+                    widgets.marks_strip,
+                    move |media| {
+                        if !media.is_prepared() {
+                            return;
+                        }
+                        // Marks are placed from the duration, only known from now
+                        marks_strip.queue_draw();
+                        let audio_only = media.has_audio() && !media.has_video();
+                        if audio_only {
+                            audio_view.show(&location);
+                        }
+                        player.set_autohide(!audio_only);
+                        let _ = sender.output(PlayerOutput::Orientation(media.orientation()));
+                        if let Some((source, output)) = media.channels() {
+                            let _ = sender.output(PlayerOutput::Channels(source, output));
+                        }
+                        let _ = sender
+                            .output(PlayerOutput::Timing(media.duration(), media.frame_rate()));
+                        if let Some(timestamp) = pending_seek.take() {
+                            media.seek(timestamp);
+                        }
+                    }
+                ));
+
+                media.connect_ended_notify(clone!(
+                    #[strong]
+                    sender,
+                    move |media| {
+                        if media.is_ended() {
+                            let _ = sender.output(PlayerOutput::Position(0));
+                            let _ = sender.output(PlayerOutput::Ended);
+                        }
+                    }
+                ));
+
+                let sized = Cell::new(false);
+                media.connect_invalidate_size(clone!(
+                    #[strong]
+                    sender,
+                    move |media| {
+                        let (width, height) = media.display_size();
+                        if !sized.get() && width > 0 && height > 0 {
+                            sized.set(true);
+                            let _ = sender.output(PlayerOutput::VideoSize(width, height));
+                        }
+                    }
+                ));
+
+                let sync_volume = clone!(
+                    #[strong]
+                    sender,
+                    move |media: &Playback| {
+                        sender.input(PlayerMsg::SyncVolume(media.volume(), media.is_muted()));
+                    }
+                );
+                media.connect_volume_notify(sync_volume.clone());
+                media.connect_muted_notify(sync_volume);
+
+                media.connect_warning_notify(clone!(
+                    #[strong]
+                    sender,
+                    move |media| {
+                        let _ = sender.output(PlayerOutput::Warning(media.warning()));
+                    }
+                ));
+
+                let reported = Cell::new(0);
+                media.connect_timestamp_notify(clone!(
+                    #[strong]
+                    sender,
+                    move |media| {
+                        let position = resume_position(media.upcast_ref());
+                        if (position - reported.get()).abs() >= POSITION_INTERVAL {
+                            reported.set(position);
+                            let _ = sender.output(PlayerOutput::Position(position));
+                        }
+                    }
+                ));
+
+                media.connect_timestamp_notify(clone!(
+                    #[weak(rename_to = label)]
+                    widgets.subtitle,
+                    #[strong(rename_to = subtitles)]
+                    widgets.subtitles,
+                    #[strong(rename_to = delay)]
+                    widgets.subtitle_delay,
+                    move |media| {
+                        let text = subtitles
+                            .borrow()
+                            .as_ref()
+                            .and_then(|s| s.text_at(media.timestamp() - delay.get()));
+                        label.set_visible(text.is_some());
+                        label.set_text(text.as_deref().unwrap_or_default());
+                    }
+                ));
+
+                widgets.player.set_media_stream(Some(&media));
+            }
         }
         if let Some(stream) = widgets.player.media_stream() {
             stream.set_playing(self.playing);
-            if self.seek != 1 {
-                stream.seek(stream.timestamp() + (10000000 * (self.seek - 1) as i64));
+            if let Some(media) = stream.downcast_ref::<Playback>() {
+                media.set_presentation(self.presentation);
+                media.set_seek_mode(self.seek_mode);
+                media.set_video_filters(&self.video_filters);
+                media.set_audio_filters(&self.audio_filters);
+                media.set_audio_delay(self.audio_delay);
+                if widgets.orientation != self.presentation.orientation {
+                    widgets.orientation = self.presentation.orientation;
+                    if media.is_prepared() {
+                        let _ = sender.output(PlayerOutput::Orientation(media.orientation()));
+                    }
+                }
+            }
+            if let Some(timestamp) = self.seek_to {
+                if stream.is_prepared() {
+                    stream.seek(timestamp);
+                } else {
+                    widgets.pending_seek.set(Some(timestamp));
+                }
+            }
+            if let Some(step) = self.seek_by {
+                let timestamp = (stream.timestamp() + step).max(0);
+                stream.seek(timestamp);
+                widgets.show_osd(&format!(
+                    "{} / {}",
+                    timecode::format_clock(timestamp),
+                    timecode::format_clock(stream.duration())
+                ));
+            }
+            if let Some(forwards) = self.jump_mark {
+                let position = stream.timestamp();
+                let mark = if forwards {
+                    self.marks
+                        .iter()
+                        .find(|(p, _)| *p > position + MARK_TOLERANCE)
+                } else {
+                    self.marks
+                        .iter()
+                        .rev()
+                        .find(|(p, _)| *p < position - MARK_TOLERANCE)
+                };
+                if let Some((position, name)) = mark {
+                    stream.seek(*position);
+                    widgets.show_osd(&format!("{name} ({})", timecode::format_clock(*position)));
+                }
+            }
+            if self.bookmark && stream.is_prepared() {
+                let position = stream.timestamp();
+                let _ = sender.output(PlayerOutput::Bookmark(position));
+                widgets.show_osd(&format!("Bookmarked {}", timecode::format_clock(position)));
+            }
+            if let Some(point) = self.review.filter(|_| stream.is_prepared()) {
+                let rate = stream
+                    .downcast_ref::<Playback>()
+                    .and_then(|media| media.frame_rate());
+                // Comments are frame accurate, so they start where the shown frame does
+                let (position, label) = match rate {
+                    Some(rate) => {
+                        let position = rate.frame_start(rate.frame_at(stream.timestamp()));
+                        (position, timecode::format_smpte(position, rate))
+                    }
+                    None => (
+                        stream.timestamp(),
+                        timecode::format_clock(stream.timestamp()),
+                    ),
+                };
+                let _ = sender.output(PlayerOutput::Review(point, position));
+                let action = match point {
+                    ReviewPoint::In => "In",
+                    ReviewPoint::Out => "Out",
+                    ReviewPoint::Comment => "Comment at",
+                };
+                widgets.show_osd(&format!("{action} {label}"));
+            }
+            if self.playhead && stream.is_prepared() {
+                let _ = sender.output(PlayerOutput::Playhead(stream.timestamp()));
+            }
+            self.stream_volume.set(self.volume.level.min(1.0));
+            stream.set_volume(self.stream_volume.get());
+            stream.set_muted(self.volume.muted);
+            if let Some(media) = stream.downcast_ref::<Playback>() {
+                media.set_boost(self.volume.level.max(1.0));
             }
-            stream.set_volume(stream.volume() + (10.0 * (self.volume - 1) as f64));
         }
     }
 
-    fn update(&mut self, msg: Self::Input, _sender: ComponentSender<Self>) {
-        self.seek = 1;
+    fn update(&mut self, msg: Self::Input, sender: ComponentSender<Self>) {
+        let volume = self.volume;
+        self.seek_to = None;
+        self.seek_by = None;
+        self.bookmark = false;
+        self.jump_mark = None;
+        self.review = None;
+        self.playhead = false;
+        self.osd = None;
         match msg {
             PlayerMsg::SetVideo(path) => {
                 self.path = Some(path);
+                self.subtitles = None;
+            }
+            PlayerMsg::SetSubtitles(path) => {
+                self.subtitles = Some(path);
+            }
+            PlayerMsg::SeekTo(timestamp) => {
+                self.seek_to = Some(timestamp);
+            }
+            PlayerMsg::SeekBy(step) => {
+                self.seek_by = Some(step);
+            }
+            PlayerMsg::SetSeekMode(mode) => {
+                self.seek_mode = mode;
+            }
+            PlayerMsg::SetMarks(marks) => {
+                self.marks = marks;
+            }
+            PlayerMsg::Bookmark => {
+                self.bookmark = true;
+            }
+            PlayerMsg::NextMark => {
+                self.jump_mark = Some(true);
+            }
+            PlayerMsg::PreviousMark => {
+                self.jump_mark = Some(false);
+            }
+            PlayerMsg::Review(point) => {
+                self.review = Some(point);
+            }
+            PlayerMsg::Playhead => {
+                self.playhead = true;
+            }
+            PlayerMsg::SetAspect(aspect) => {
+                self.presentation.aspect = aspect;
+            }
+            PlayerMsg::SetCrop(crop) => {
+                self.presentation.crop = crop;
+            }
+            PlayerMsg::SetFit(fit) => {
+                self.presentation.fit = fit;
+                self.presentation.pan = (0.0, 0.0);
+            }
+            PlayerMsg::Zoom(factor) => {
+                self.presentation.zoom_by(factor);
+            }
+            PlayerMsg::ResetZoom => {
+                self.presentation.zoom = 1.0;
+                self.presentation.pan = (0.0, 0.0);
+            }
+            PlayerMsg::Pan(dx, dy) => {
+                self.presentation.pan_by(dx, dy);
+            }
+            PlayerMsg::Orient(orientation) => {
+                self.presentation.orientation = self.presentation.orientation.then(orientation);
+            }
+            PlayerMsg::ResetOrientation => {
+                self.presentation.orientation = Orientation::default();
+            }
+            PlayerMsg::SetDeinterlace(deinterlace) => {
+                self.video_filters.deinterlace = deinterlace;
+            }
+            PlayerMsg::SetDeinterlacer(deinterlacer) => {
+                self.video_filters.deinterlacer = deinterlacer;
+            }
+            PlayerMsg::SetFieldOrder(field_order) => {
+                self.video_filters.field_order = field_order;
+            }
+            PlayerMsg::SetAdjustments(adjustments) => {
+                self.video_filters.adjustments = adjustments;
+            }
+            PlayerMsg::SetVideoFilter(filter) => {
+                self.video_filters.custom = filter;
+            }
+            PlayerMsg::SetAudioFilter(filter) => {
+                self.audio_filters.custom = filter;
+            }
+            PlayerMsg::SetGain(gain) => {
+                self.audio_filters.gain = gain;
+            }
+            PlayerMsg::SetEqualizer(equalizer) => {
+                self.audio_filters.equalizer = equalizer;
+            }
+            PlayerMsg::SetRouting(routing) => {
+                self.audio_filters.routing = routing;
+            }
+            PlayerMsg::SetAudioDelay(delay) => {
+                self.audio_delay = delay;
+            }
+            PlayerMsg::SetSubtitleDelay(delay) => {
+                self.subtitle_delay = delay;
+            }
+            PlayerMsg::ShowOsd(text) => {
+                self.osd = Some(text);
+            }
+            PlayerMsg::SetVisualization(visualization) => {
+                self.visualization = visualization;
             }
             PlayerMsg::PlayPause => {
                 self.playing = !self.playing;
             }
             PlayerMsg::SeekForwards => {
-                self.seek = 2;
+                self.seek_by = Some(SEEK_STEP);
             }
             PlayerMsg::SeekBackwards => {
-                self.seek = 0;
+                self.seek_by = Some(-SEEK_STEP);
             }
             PlayerMsg::VolumeUp => {
-                self.volume = 2;
+                self.change_volume(self.volume.step);
             }
             PlayerMsg::VolumeDown => {
-                self.volume = 0;
+                self.change_volume(-self.volume.step);
+            }
+            PlayerMsg::ToggleMute => {
+                self.volume.muted = !self.volume.muted;
+                self.osd = Some(self.volume.description());
+            }
+            PlayerMsg::SetVolumeStep(step) => {
+                self.volume.step = step;
+            }
+            PlayerMsg::SetBoost(boost) => {
+                self.volume.boost = boost;
+                self.volume.level = self.volume.level.min(self.volume.max());
+            }
+            PlayerMsg::SyncVolume(level, muted) => {
+                // The controls only go up to 100%, so a boosted level shows as full. Only levels
+                // other than the one set last come from the user, and those replace any boost.
+                if level != self.stream_volume.get() {
+                    self.volume.level = level;
+                }
+                self.volume.muted = muted;
             }
         }
+
+        if self.volume != volume {
+            let _ = sender.output(PlayerOutput::Volume(self.volume));
+        }
+    }
+}
+
+impl PlayerWidgets {
+    /// Shows `text` over the video until no other message came for a while.
+    fn show_osd(&self, text: &str) {
+        self.osd.set_text(text);
+        self.osd.set_visible(true);
+        let generation = self.osd_generation.get().wrapping_add(1);
+        self.osd_generation.set(generation);
+        glib::timeout_add_local_once(
+            OSD_DURATION,
+            clone!(
+                #[weak(rename_to = osd)]
+                self.osd,
+                #[strong(rename_to = current)]
+                self.osd_generation,
+                move || {
+                    if current.get() == generation {
+                        osd.set_visible(false);
+                    }
+                }
+            ),
+        );
+    }
+}
+
+impl Player {
+    fn change_volume(&mut self, delta: f64) {
+        // Round to the step so levels stay on whole percentages
+        let level = ((self.volume.level + delta) * 100.0).round() / 100.0;
+        self.volume.level = level.clamp(0.0, self.volume.max());
+        self.volume.muted = false;
+        self.osd = Some(self.volume.description());
     }
 }
@@ -0,0 +1,96 @@
+use std::path::{Path, PathBuf};
+
+pub const VIDEO_EXTENSIONS: &[&str] = &[
+    "mp4", "mkv", "mka", "mk3d", "mks", "mov", "avi", "wmv", "flv", "f4v", "webm", "ogv",
+];
+
+pub const AUDIO_EXTENSIONS: &[&str] = &["mp3", "flac", "opus", "ogg", "oga", "m4a", "wav"];
+
+pub const SUBTITLE_EXTENSIONS: &[&str] = &["srt", "vtt"];
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| extensions.contains(&e.to_lowercase().as_str()))
+}
+
+/// Network locations are kept as URLs in place of a local path.
+pub fn is_url(path: &Path) -> bool {
+    path.to_str().is_some_and(|p| p.contains("://"))
+}
+
+pub fn is_media(path: &Path) -> bool {
+    has_extension(path, VIDEO_EXTENSIONS) || is_audio(path)
+}
+
+pub fn is_audio(path: &Path) -> bool {
+    has_extension(path, AUDIO_EXTENSIONS)
+}
+
+pub fn is_subtitle(path: &Path) -> bool {
+    has_extension(path, SUBTITLE_EXTENSIONS)
+}
+
+/// Recursively collects all media files below `dir`, sorted by path.
+pub fn collect_media(dir: &Path) -> Vec<PathBuf> {
+    let mut media = Vec::new();
+    let entries = match std::fs::read_dir(dir) {
+        Ok(e) => e,
+        Err(e) => {
+            eprintln!("Error: Could not read directory {} ({e})", dir.display());
+            return media;
+        }
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            media.extend(collect_media(&path));
+        } else if is_media(&path) {
+            media.push(path);
+        }
+    }
+
+    media.sort();
+    media
+}
+
+#[derive(Debug, Default)]
+pub struct Playlist {
+    entries: Vec<PathBuf>,
+    current: Option<usize>,
+}
+
+impl Playlist {
+    /// Appends `paths` and returns the index of the first appended entry.
+    pub fn append(&mut self, paths: impl IntoIterator<Item = PathBuf>) -> usize {
+        let first = self.entries.len();
+        self.entries.extend(paths);
+        first
+    }
+
+    pub fn select(&mut self, index: usize) -> Option<&PathBuf> {
+        if index < self.entries.len() {
+            self.current = Some(index);
+        }
+        self.current()
+    }
+
+    pub fn next(&mut self) -> Option<&PathBuf> {
+        let index = self.current.map_or(0, |i| i + 1);
+        if index < self.entries.len() {
+            self.current = Some(index);
+            self.current()
+        } else {
+            None
+        }
+    }
+
+    pub fn current(&self) -> Option<&PathBuf> {
+        self.current.and_then(|i| self.entries.get(i))
+    }
+
+    pub fn entries(&self) -> &[PathBuf] {
+        &self.entries
+    }
+}
@@ -0,0 +1,488 @@
+use gtk::graphene;
+
+/// Brightest channel value still counted as black when detecting bars
+const BLACK: u8 = 32;
+/// Largest part of the frame a detected bar may cover
+const MAX_BAR: f64 = 0.35;
+const MIN_ZOOM: f64 = 0.1;
+const MAX_ZOOM: f64 = 10.0;
+
+/// How the video is scaled into the available space.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Fit {
+    /// Show the whole frame, letterboxed if needed
+    #[default]
+    Fit,
+    /// Cover the whole area, cutting off what does not fit
+    Fill,
+    /// Cover the whole area, ignoring the aspect ratio
+    Stretch,
+    /// One video pixel per display pixel
+    Original,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Crop {
+    #[default]
+    None,
+    /// Cut off black bars detected in decoded frames
+    Auto,
+    /// Crop the centre of the frame to an aspect ratio
+    Ratio(f64),
+}
+
+/// Fractions of the frame cut off at each side.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Bars {
+    pub left: f64,
+    pub top: f64,
+    pub right: f64,
+    pub bottom: f64,
+}
+
+impl Bars {
+    /// Keeps the smaller bar on each side, so dark scenes do not crop into the picture.
+    pub fn min(self, other: Self) -> Self {
+        Self {
+            left: self.left.min(other.left),
+            top: self.top.min(other.top),
+            right: self.right.min(other.right),
+            bottom: self.bottom.min(other.bottom),
+        }
+    }
+}
+
+/// A clockwise rotation by a multiple of 90°, followed by an optional horizontal mirror.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Orientation {
+    pub rotation: u32,
+    pub mirrored: bool,
+}
+
+impl Orientation {
+    pub const FLIP_HORIZONTAL: Self = Self {
+        rotation: 0,
+        mirrored: true,
+    };
+    pub const FLIP_VERTICAL: Self = Self {
+        rotation: 180,
+        mirrored: true,
+    };
+
+    pub fn rotated(degrees: i64) -> Self {
+        Self {
+            rotation: ((degrees as f64 / 90.0).round() as i64 * 90).rem_euclid(360) as u32,
+            mirrored: false,
+        }
+    }
+
+    /// Returns the orientation of applying `self` and then `other`.
+    pub fn then(self, other: Self) -> Self {
+        // Mirroring reverses the direction of any rotation applied afterwards
+        let rotation = if self.mirrored {
+            self.rotation as i64 - other.rotation as i64
+        } else {
+            self.rotation as i64 + other.rotation as i64
+        };
+        Self {
+            rotation: rotation.rem_euclid(360) as u32,
+            mirrored: self.mirrored != other.mirrored,
+        }
+    }
+
+    /// Whether width and height are swapped.
+    pub fn is_transposed(&self) -> bool {
+        self.rotation % 180 != 0
+    }
+
+    pub fn description(&self) -> String {
+        match (self.rotation, self.mirrored) {
+            (0, false) => String::from("Normal"),
+            (0, true) => String::from("Mirrored"),
+            (rotation, false) => format!("Rotated {rotation}°"),
+            (rotation, true) => format!("Rotated {rotation}°, mirrored"),
+        }
+    }
+}
+
+/// Where a frame is drawn: the visible part of the texture and the area it is scaled into.
+#[derive(Debug, Clone, Copy)]
+pub struct Layout {
+    pub source: graphene::Rect,
+    pub target: graphene::Rect,
+    /// How far the scaled video extends past the viewport on each axis
+    pub overflow: (f64, f64),
+}
+
+/// Aspect ratio, crop, scaling and panning applied when drawing frames.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Presentation {
+    /// Display aspect ratio of the whole frame, overriding the stream's
+    pub aspect: Option<f64>,
+    pub crop: Crop,
+    pub fit: Fit,
+    pub zoom: f64,
+    /// Offset of the video within its overflow, from -1 to 1 on each axis
+    pub pan: (f64, f64),
+    /// Applied on top of the orientation stored in the stream
+    pub orientation: Orientation,
+}
+
+impl Default for Presentation {
+    fn default() -> Self {
+        Self {
+            aspect: None,
+            crop: Crop::None,
+            fit: Fit::Fit,
+            zoom: 1.0,
+            pan: (0.0, 0.0),
+            orientation: Orientation::default(),
+        }
+    }
+}
+
+/// Parses ratios such as `16:9`, `2.35:1` or `1.85`.
+pub fn parse_ratio(text: &str) -> Option<f64> {
+    let ratio = match text.trim().split_once([':', '/', 'x']) {
+        Some((w, h)) => w.trim().parse::<f64>().ok()? / h.trim().parse::<f64>().ok()?,
+        None => text.trim().parse().ok()?,
+    };
+    (ratio.is_finite() && ratio > 0.0).then_some(ratio)
+}
+
+impl Presentation {
+    pub fn zoom_by(&mut self, factor: f64) {
+        self.zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+    }
+
+    pub fn pan_by(&mut self, dx: f64, dy: f64) {
+        self.pan = (
+            (self.pan.0 + dx).clamp(-1.0, 1.0),
+            (self.pan.1 + dy).clamp(-1.0, 1.0),
+        );
+    }
+
+    /// Returns the part of a `width`×`height` frame that is shown and its size in display
+    /// pixels before scaling.
+    fn visible(
+        &self,
+        width: f64,
+        height: f64,
+        pixel_aspect: f64,
+        bars: Option<Bars>,
+    ) -> (graphene::Rect, f64, f64) {
+        let aspect = self.aspect.unwrap_or(width * pixel_aspect / height);
+        // Horizontal display size of a texture pixel
+        let pixel_width = aspect * height / width;
+
+        let (mut x, mut y, mut w, mut h) = (0.0, 0.0, width, height);
+        match self.crop {
+            Crop::None => {}
+            Crop::Auto => {
+                if let Some(bars) = bars {
+                    x = bars.left * width;
+                    y = bars.top * height;
+                    w = width * (1.0 - bars.left - bars.right);
+                    h = height * (1.0 - bars.top - bars.bottom);
+                }
+            }
+            Crop::Ratio(ratio) if aspect > ratio => {
+                w = width * ratio / aspect;
+                x = (width - w) / 2.0;
+            }
+            Crop::Ratio(ratio) => {
+                h = height * aspect / ratio;
+                y = (height - h) / 2.0;
+            }
+        }
+
+        let source = graphene::Rect::new(x as f32, y as f32, w as f32, h as f32);
+        (source, w * pixel_width, h)
+    }
+
+    /// Returns the size of the visible part of the frame in display pixels, after rotation.
+    pub fn display_size(
+        &self,
+        width: f64,
+        height: f64,
+        pixel_aspect: f64,
+        bars: Option<Bars>,
+    ) -> (f64, f64) {
+        let (_, w, h) = self.visible(width, height, pixel_aspect, bars);
+        if self.orientation.is_transposed() {
+            (h, w)
+        } else {
+            (w, h)
+        }
+    }
+
+    /// Lays out a frame in the viewport. The target is on screen, after `orientation` has been
+    /// applied; its width and height are swapped relative to the source when transposed.
+    pub fn layout(
+        &self,
+        frame: (f64, f64),
+        pixel_aspect: f64,
+        bars: Option<Bars>,
+        viewport: (f64, f64),
+    ) -> Layout {
+        let (source, w, h) = self.visible(frame.0, frame.1, pixel_aspect, bars);
+        let (w, h) = if self.orientation.is_transposed() {
+            (h, w)
+        } else {
+            (w, h)
+        };
+        let (scale_x, scale_y) = match self.fit {
+            Fit::Fit => {
+                let scale = (viewport.0 / w).min(viewport.1 / h);
+                (scale, scale)
+            }
+            Fit::Fill => {
+                let scale = (viewport.0 / w).max(viewport.1 / h);
+                (scale, scale)
+            }
+            Fit::Stretch => (viewport.0 / w, viewport.1 / h),
+            Fit::Original => (1.0, 1.0),
+        };
+
+        let (w, h) = (w * scale_x * self.zoom, h * scale_y * self.zoom);
+        let overflow = ((w - viewport.0).max(0.0), (h - viewport.1).max(0.0));
+        let x = (viewport.0 - w) / 2.0 + self.pan.0 * overflow.0 / 2.0;
+        let y = (viewport.1 - h) / 2.0 + self.pan.1 * overflow.1 / 2.0;
+
+        Layout {
+            source,
+            target: graphene::Rect::new(x as f32, y as f32, w as f32, h as f32),
+            overflow,
+        }
+    }
+}
+
+fn is_black(pixel: &[u8]) -> bool {
+    pixel[..3].iter().all(|&c| c <= BLACK)
+}
+
+/// Detects black bars around an RGBA frame. Returns `None` for frames that are entirely dark.
+pub fn detect_bars(data: &[u8], width: usize, height: usize, stride: usize) -> Option<Bars> {
+    let pixel = |x: usize, y: usize| &data[y * stride + x * 4..y * stride + x * 4 + 4];
+    let row_black = |y: usize| (0..width).step_by(4).all(|x| is_black(pixel(x, y)));
+
+    let top = (0..height).take_while(|&y| row_black(y)).count();
+    if top == height {
+        return None;
+    }
+    let bottom = (0..height).rev().take_while(|&y| row_black(y)).count();
+
+    let column_black = |x: usize| {
+        (top..height - bottom)
+            .step_by(4)
+            .all(|y| is_black(pixel(x, y)))
+    };
+    let left = (0..width).take_while(|&x| column_black(x)).count();
+    let right = (0..width).rev().take_while(|&x| column_black(x)).count();
+
+    let fraction = |bar: usize, size: usize| (bar as f64 / size as f64).min(MAX_BAR);
+    Some(Bars {
+        left: fraction(left, width),
+        top: fraction(top, height),
+        right: fraction(right, width),
+        bottom: fraction(bottom, height),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FULL_HD: (f64, f64) = (1920.0, 1080.0);
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-3,
+            "{actual} is not {expected}"
+        );
+    }
+
+    fn assert_rect(rect: graphene::Rect, expected: (f64, f64, f64, f64)) {
+        assert_close(rect.x() as f64, expected.0);
+        assert_close(rect.y() as f64, expected.1);
+        assert_close(rect.width() as f64, expected.2);
+        assert_close(rect.height() as f64, expected.3);
+    }
+
+    /// Builds an RGBA frame with padded rows, white where `bright` holds and black elsewhere.
+    fn frame(width: usize, height: usize, bright: impl Fn(usize, usize) -> bool) -> Vec<u8> {
+        let stride = width * 4 + 12;
+        let mut data = vec![0; stride * height];
+        for y in 0..height {
+            for x in 0..width {
+                if bright(x, y) {
+                    data[y * stride + x * 4..][..4].copy_from_slice(&[255, 255, 255, 255]);
+                }
+            }
+        }
+        data
+    }
+
+    fn bars(data: &[u8], width: usize, height: usize) -> Option<Bars> {
+        detect_bars(data, width, height, width * 4 + 12)
+    }
+
+    #[test]
+    fn parses_ratios() {
+        assert_close(parse_ratio("16:9").unwrap(), 16.0 / 9.0);
+        assert_close(parse_ratio(" 2.35 : 1 ").unwrap(), 2.35);
+        assert_close(parse_ratio("4/3").unwrap(), 4.0 / 3.0);
+        assert_close(parse_ratio("1920x1080").unwrap(), 16.0 / 9.0);
+        assert_close(parse_ratio("1.85").unwrap(), 1.85);
+        for invalid in [
+            "16:0", "0:9", "0:0", "0", "-1.85", "-16:9", "16:-9", "inf", "", "wide",
+        ] {
+            assert_eq!(parse_ratio(invalid), None, "{invalid}");
+        }
+    }
+
+    #[test]
+    fn fits_fills_stretches_and_keeps_original_size() {
+        let viewport = (1000.0, 1000.0);
+        let layout = |fit| {
+            let presentation = Presentation {
+                fit,
+                ..Presentation::default()
+            };
+            presentation.layout(FULL_HD, 1.0, None, viewport)
+        };
+
+        let fit = layout(Fit::Fit);
+        assert_rect(fit.source, (0.0, 0.0, 1920.0, 1080.0));
+        assert_rect(fit.target, (0.0, 218.75, 1000.0, 562.5));
+        assert_close(fit.overflow.0, 0.0);
+        assert_close(fit.overflow.1, 0.0);
+
+        let fill = layout(Fit::Fill);
+        assert_rect(fill.target, (-388.889, 0.0, 1777.778, 1000.0));
+        assert_close(fill.overflow.0, 777.778);
+
+        assert_rect(layout(Fit::Stretch).target, (0.0, 0.0, 1000.0, 1000.0));
+
+        let original = layout(Fit::Original);
+        assert_rect(original.target, (-460.0, -40.0, 1920.0, 1080.0));
+        assert_eq!(original.overflow, (920.0, 80.0));
+    }
+
+    #[test]
+    fn pans_and_zooms_within_the_overflow() {
+        let mut presentation = Presentation {
+            fit: Fit::Fill,
+            ..Presentation::default()
+        };
+        presentation.pan_by(1.5, -0.5);
+        assert_eq!(presentation.pan, (1.0, -0.5));
+        let layout = presentation.layout(FULL_HD, 1.0, None, (1000.0, 1000.0));
+        assert_close(layout.target.x() as f64, 0.0);
+
+        presentation.zoom_by(1000.0);
+        assert_eq!(presentation.zoom, MAX_ZOOM);
+        presentation.zoom_by(0.0);
+        assert_eq!(presentation.zoom, MIN_ZOOM);
+    }
+
+    #[test]
+    fn crops_to_ratios() {
+        let crop = |ratio| Presentation {
+            crop: Crop::Ratio(ratio),
+            ..Presentation::default()
+        };
+        let (source, _, _) = crop(4.0 / 3.0).visible(FULL_HD.0, FULL_HD.1, 1.0, None);
+        assert_rect(source, (240.0, 0.0, 1440.0, 1080.0));
+        let (source, w, h) = crop(2.35).visible(FULL_HD.0, FULL_HD.1, 1.0, None);
+        assert_rect(source, (0.0, 131.489, 1920.0, 817.021));
+        assert_close(w / h, 2.35);
+    }
+
+    #[test]
+    fn applies_pixel_aspect_overrides_and_rotation() {
+        let presentation = Presentation::default();
+        assert_eq!(
+            presentation.display_size(720.0, 576.0, 64.0 / 45.0, None),
+            (1024.0, 576.0)
+        );
+        let overridden = Presentation {
+            aspect: Some(4.0 / 3.0),
+            ..presentation
+        };
+        assert_eq!(
+            overridden.display_size(720.0, 576.0, 64.0 / 45.0, None),
+            (768.0, 576.0)
+        );
+        let rotated = Presentation {
+            orientation: Orientation::rotated(90),
+            ..presentation
+        };
+        assert_eq!(
+            rotated.display_size(FULL_HD.0, FULL_HD.1, 1.0, None),
+            (1080.0, 1920.0)
+        );
+    }
+
+    #[test]
+    fn combines_orientations() {
+        assert_eq!(Orientation::rotated(-90).rotation, 270);
+        assert_eq!(Orientation::rotated(450).rotation, 90);
+        assert_eq!(Orientation::rotated(44).rotation, 0);
+        let flipped = Orientation::FLIP_VERTICAL.then(Orientation::FLIP_VERTICAL);
+        assert_eq!(flipped, Orientation::default());
+        let turned = Orientation::FLIP_HORIZONTAL.then(Orientation::rotated(90));
+        assert_eq!((turned.rotation, turned.mirrored), (270, true));
+        assert!(turned.is_transposed());
+    }
+
+    #[test]
+    fn detects_letterboxes_and_pillarboxes() {
+        let letterbox = frame(64, 36, |_, y| (4..32).contains(&y));
+        let found = bars(&letterbox, 64, 36).unwrap();
+        assert_close(found.top, 4.0 / 36.0);
+        assert_close(found.bottom, 4.0 / 36.0);
+        assert_eq!((found.left, found.right), (0.0, 0.0));
+
+        // Odd sizes, with the sampled rows and columns not reaching the edges
+        let pillarbox = frame(33, 17, |x, _| (3..30).contains(&x));
+        let found = bars(&pillarbox, 33, 17).unwrap();
+        assert_close(found.left, 3.0 / 33.0);
+        assert_close(found.right, 3.0 / 33.0);
+        assert_eq!((found.top, found.bottom), (0.0, 0.0));
+
+        let auto = Presentation {
+            crop: Crop::Auto,
+            ..Presentation::default()
+        };
+        let (source, _, _) = auto.visible(33.0, 17.0, 1.0, Some(found));
+        assert_rect(source, (3.0, 0.0, 27.0, 17.0));
+    }
+
+    #[test]
+    fn limits_bars_and_skips_dark_frames() {
+        let mostly_dark = frame(10, 10, |_, y| y == 9);
+        let found = bars(&mostly_dark, 10, 10).unwrap();
+        assert_eq!((found.top, found.bottom), (MAX_BAR, 0.0));
+
+        assert_eq!(bars(&frame(16, 9, |_, _| false), 16, 9), None);
+        assert_eq!(bars(&[], 16, 0), None);
+
+        let dark = Bars {
+            top: 0.3,
+            ..Bars::default()
+        };
+        let bright = Bars {
+            top: 0.1,
+            left: 0.2,
+            ..Bars::default()
+        };
+        assert_eq!(
+            dark.min(bright),
+            Bars {
+                top: 0.1,
+                ..Bars::default()
+            }
+        );
+    }
+}
@@ -0,0 +1,149 @@
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use ffmpeg_next::{codec, format, Error};
+
+use crate::decoder::best_video;
+use crate::storage;
+
+/// Cached probes kept at most, the least recently written being dropped first
+const MAX_CACHED: usize = 2000;
+const GROUP: &str = "Probe";
+
+/// Container level facts about a media file, read without decoding it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Probe {
+    /// Long name of the container format
+    pub format: String,
+    /// Duration in microseconds, or 0 when unknown
+    pub duration: i64,
+    /// Overall bitrate in bits per second
+    pub bit_rate: i64,
+    /// Size of the video, or 0 for audio files
+    pub width: u32,
+    pub height: u32,
+    /// Container tags in file order
+    pub tags: Vec<(String, String)>,
+}
+
+impl Probe {
+    /// Returns the value of the first tag called `name`, ignoring case.
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Modification time in seconds and size of a file, to tell whether a cached probe is current.
+pub fn file_stamp(location: &Path) -> Option<(i64, i64)> {
+    let metadata = std::fs::metadata(location).ok()?;
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_secs() as i64);
+    Some((modified, metadata.len() as i64))
+}
+
+/// Returns where the probe of `location` is cached. Each file has its own, so probes made at
+/// the same time on several threads do not overwrite each other.
+fn cache_path(location: &Path) -> PathBuf {
+    storage::cache_dir("probe").join(format!("{}.ini", storage::key_for(location)))
+}
+
+/// Returns the probe cached for `location`, unless the file changed since.
+pub fn cached(location: &Path) -> Option<Probe> {
+    let (modified, size) = file_stamp(location)?;
+    let file = storage::load(&cache_path(location));
+    if file.int64(GROUP, "modified").ok()? != modified || file.int64(GROUP, "size").ok()? != size {
+        return None;
+    }
+    let keys = file.string_list(GROUP, "tag-keys").unwrap_or_default();
+    let values = file.string_list(GROUP, "tag-values").unwrap_or_default();
+    Some(Probe {
+        format: file.string(GROUP, "format").ok()?.to_string(),
+        duration: file.int64(GROUP, "duration").ok()?,
+        bit_rate: file.int64(GROUP, "bit-rate").ok()?,
+        width: file.integer(GROUP, "width").ok()? as u32,
+        height: file.integer(GROUP, "height").ok()? as u32,
+        tags: keys
+            .iter()
+            .zip(values.iter())
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect(),
+    })
+}
+
+/// Caches the probe of a local file, keyed by its path, modification time and size.
+pub fn store(location: &Path, probe: &Probe) {
+    let Some((modified, size)) = file_stamp(location) else {
+        return;
+    };
+    let path = cache_path(location);
+    let file = gtk::glib::KeyFile::new();
+    file.set_string(GROUP, "location", &location.to_string_lossy());
+    file.set_int64(GROUP, "modified", modified);
+    file.set_int64(GROUP, "size", size);
+    file.set_string(GROUP, "format", &probe.format);
+    file.set_int64(GROUP, "duration", probe.duration);
+    file.set_int64(GROUP, "bit-rate", probe.bit_rate);
+    file.set_integer(GROUP, "width", probe.width as i32);
+    file.set_integer(GROUP, "height", probe.height as i32);
+    let (keys, values): (Vec<&str>, Vec<&str>) = probe
+        .tags
+        .iter()
+        .map(|(key, value)| (key.as_str(), value.as_str()))
+        .unzip();
+    file.set_string_list(GROUP, "tag-keys", &keys);
+    file.set_string_list(GROUP, "tag-values", &values);
+    storage::save(&file, &path);
+    prune();
+}
+
+/// Drops the oldest cached probes beyond `MAX_CACHED`.
+fn prune() {
+    let Ok(entries) = std::fs::read_dir(storage::cache_dir("probe")) else {
+        return;
+    };
+    let mut files = entries
+        .flatten()
+        .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+        .collect::<Vec<_>>();
+    if files.len() <= MAX_CACHED {
+        return;
+    }
+    files.sort();
+    for (_, path) in &files[..files.len() - MAX_CACHED] {
+        // Another thread may have removed it already
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+/// Opens `location` and reads its format, duration, bitrate, video size and tags.
+pub fn probe(location: &Path) -> Result<Probe, Error> {
+    ffmpeg_next::init()?;
+    let input = format::input(location)?;
+
+    let (width, height) = best_video(&input)
+        .and_then(|stream| {
+            codec::Context::from_parameters(stream.parameters())
+                .and_then(|c| c.decoder().video())
+                .ok()
+        })
+        .map_or((0, 0), |decoder| (decoder.width(), decoder.height()));
+
+    Ok(Probe {
+        format: input.format().description().to_string(),
+        duration: input.duration().max(0),
+        bit_rate: input.bit_rate(),
+        width,
+        height,
+        tags: input
+            .metadata()
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect(),
+    })
+}
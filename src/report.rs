@@ -0,0 +1,665 @@
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use ffmpeg_next::{codec, format, media, rescale, Error, Rescale, Stream};
+use serde_json::{json, Map, Value};
+
+use crate::csv;
+use crate::decoder;
+use crate::playlist;
+use crate::timecode::{self, FrameRate};
+
+/// Width of the field names in the text report
+const LABEL_WIDTH: usize = 32;
+
+const CSV_COLUMNS: &[&str] = &[
+    "path",
+    "format",
+    "duration",
+    "size",
+    "bit_rate",
+    "video_codec",
+    "width",
+    "height",
+    "frame_rate",
+    "audio_codec",
+    "sample_rate",
+    "channels",
+    "streams",
+    "chapters",
+    "title",
+];
+
+/// How a single report is written, for copying or exporting from Media Info.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum ReportFormat {
+    /// Aligned fields in sections, laid out like the `mediainfo` tool
+    #[default]
+    Text,
+    /// Tables, for pasting into tickets
+    Markdown,
+    Json,
+}
+
+impl ReportFormat {
+    pub fn parse(id: &str) -> Self {
+        match id {
+            "markdown" => Self::Markdown,
+            "json" => Self::Json,
+            _ => Self::Text,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Text => "txt",
+            Self::Markdown => "md",
+            Self::Json => "json",
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("md" | "markdown") => Self::Markdown,
+            Some("json") => Self::Json,
+            _ => Self::Text,
+        }
+    }
+}
+
+/// How the reports of many files are written from the command line, one line per file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BatchFormat {
+    Csv,
+    JsonLines,
+}
+
+impl BatchFormat {
+    pub fn parse(id: &str) -> Option<Self> {
+        match id {
+            "csv" => Some(Self::Csv),
+            "jsonl" | "json" => Some(Self::JsonLines),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct VideoInfo {
+    pub width: u32,
+    pub height: u32,
+    pub pixel_format: String,
+    /// Average frame rate, unknown for some variable frame rate streams
+    pub frame_rate: Option<FrameRate>,
+    pub color_space: Option<String>,
+    pub color_range: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct AudioInfo {
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub sample_format: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct StreamInfo {
+    pub index: usize,
+    /// "video", "audio", "subtitle", "data" or "attachment"
+    pub kind: &'static str,
+    pub codec: String,
+    /// Duration in microseconds, or 0 when unknown
+    pub duration: i64,
+    /// Bits per second, or 0 when unknown
+    pub bit_rate: usize,
+    pub default: bool,
+    pub video: Option<VideoInfo>,
+    pub audio: Option<AudioInfo>,
+    pub tags: Vec<(String, String)>,
+}
+
+impl StreamInfo {
+    fn tag(&self, name: &str) -> Option<&str> {
+        tag(&self.tags, name)
+    }
+
+    fn title(&self) -> String {
+        let kind = match self.kind {
+            "subtitle" => "Text",
+            "video" => "Video",
+            "audio" => "Audio",
+            "attachment" => "Attachment",
+            _ => "Other",
+        };
+        format!("{kind} #{}", self.index)
+    }
+
+    /// Returns the human readable fields of the stream.
+    fn fields(&self) -> Vec<(String, String)> {
+        let mut fields = vec![(String::from("Codec"), self.codec.clone())];
+        if let Some(video) = &self.video {
+            fields.push((
+                String::from("Resolution"),
+                format!("{}×{}", video.width, video.height),
+            ));
+            fields.push((String::from("Pixel format"), video.pixel_format.clone()));
+            if let Some(rate) = video.frame_rate {
+                fields.push((String::from("Frame rate"), format!("{:.3} fps", rate.fps())));
+                if self.duration > 0 {
+                    fields.push((
+                        String::from("Frame count"),
+                        timecode::format_frames(self.duration, rate),
+                    ));
+                    fields.push((
+                        String::from("Timecode"),
+                        timecode::format_smpte(self.duration, rate),
+                    ));
+                }
+            }
+            if let Some(space) = &video.color_space {
+                fields.push((String::from("Color space"), space.clone()));
+            }
+            if let Some(range) = &video.color_range {
+                fields.push((String::from("Color range"), range.clone()));
+            }
+        }
+        if let Some(audio) = &self.audio {
+            fields.push((
+                String::from("Sample rate"),
+                format!("{} Hz", audio.sample_rate),
+            ));
+            fields.push((
+                String::from("Channels"),
+                format!(
+                    "{} ({})",
+                    audio.channels,
+                    decoder::channel_layout_name(audio.channels)
+                ),
+            ));
+            fields.push((String::from("Sample format"), audio.sample_format.clone()));
+        }
+        if self.duration > 0 {
+            fields.push((
+                String::from("Duration"),
+                timecode::format_precise(self.duration),
+            ));
+        }
+        if self.bit_rate > 0 {
+            fields.push((
+                String::from("Bitrate"),
+                format_bit_rate(self.bit_rate as i64),
+            ));
+        }
+        if let Some(language) = self.tag("language") {
+            fields.push((String::from("Language"), language.to_string()));
+        }
+        fields.push((
+            String::from("Default"),
+            String::from(if self.default { "Yes" } else { "No" }),
+        ));
+        fields.extend(
+            self.tags
+                .iter()
+                .filter(|(key, _)| !key.eq_ignore_ascii_case("language"))
+                .map(|(key, value)| (key.clone(), value.clone())),
+        );
+        fields
+    }
+
+    fn to_json(&self) -> Value {
+        let mut stream = json!({
+            "index": self.index,
+            "type": self.kind,
+            "codec": self.codec,
+            "duration": seconds(self.duration),
+            "bit_rate": self.bit_rate,
+            "default": self.default,
+            "tags": tags_json(&self.tags),
+        });
+        if let Some(video) = &self.video {
+            stream["width"] = json!(video.width);
+            stream["height"] = json!(video.height);
+            stream["pixel_format"] = json!(video.pixel_format);
+            stream["frame_rate"] = json!(video.frame_rate.map(|r| r.fps()));
+            stream["color_space"] = json!(video.color_space);
+            stream["color_range"] = json!(video.color_range);
+        }
+        if let Some(audio) = &self.audio {
+            stream["sample_rate"] = json!(audio.sample_rate);
+            stream["channels"] = json!(audio.channels);
+            stream["channel_layout"] = json!(decoder::channel_layout_name(audio.channels));
+            stream["sample_format"] = json!(audio.sample_format);
+        }
+        stream
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ChapterInfo {
+    /// Start and end in microseconds
+    pub start: i64,
+    pub end: i64,
+    pub title: Option<String>,
+}
+
+/// A file embedded in the container, such as a font for subtitles.
+#[derive(Debug, Clone)]
+pub struct AttachmentInfo {
+    pub index: usize,
+    pub filename: Option<String>,
+    pub mimetype: Option<String>,
+    pub size: usize,
+}
+
+/// Everything the container says about a file: its format, all streams, chapters, tags and
+/// attachments.
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub path: PathBuf,
+    pub format: String,
+    pub format_name: String,
+    /// Duration in microseconds, or 0 when unknown
+    pub duration: i64,
+    /// File size in bytes, unknown for network streams
+    pub size: Option<u64>,
+    pub bit_rate: i64,
+    pub streams: Vec<StreamInfo>,
+    pub chapters: Vec<ChapterInfo>,
+    pub tags: Vec<(String, String)>,
+    pub attachments: Vec<AttachmentInfo>,
+}
+
+fn tag<'a>(tags: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    tags.iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+fn read_tags(metadata: ffmpeg_next::DictionaryRef) -> Vec<(String, String)> {
+    metadata
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+fn read_stream(stream: &Stream) -> StreamInfo {
+    let parameters = stream.parameters();
+    let medium = parameters.medium();
+    let kind = match medium {
+        media::Type::Video => "video",
+        media::Type::Audio => "audio",
+        media::Type::Subtitle => "subtitle",
+        media::Type::Attachment => "attachment",
+        _ => "data",
+    };
+    let duration = match stream.duration() {
+        d if d > 0 => d.rescale(stream.time_base(), rescale::TIME_BASE),
+        _ => 0,
+    };
+    let mut info = StreamInfo {
+        index: stream.index(),
+        kind,
+        codec: parameters.id().name().to_string(),
+        duration,
+        bit_rate: 0,
+        default: stream
+            .disposition()
+            .contains(format::stream::Disposition::DEFAULT),
+        video: None,
+        audio: None,
+        tags: read_tags(stream.metadata()),
+    };
+
+    // Opening a decoder is the safe way to read the codec parameters. Streams without one, such
+    // as unknown data streams, are reported with their codec only.
+    let context = codec::Context::from_parameters(parameters);
+    match medium {
+        media::Type::Video => {
+            if let Ok(video) = context.and_then(|c| c.decoder().video()) {
+                let rate = stream.avg_frame_rate();
+                info.bit_rate = video.bit_rate();
+                info.video = Some(VideoInfo {
+                    width: video.width(),
+                    height: video.height(),
+                    pixel_format: video
+                        .format()
+                        .descriptor()
+                        .map_or_else(|| String::from("unknown"), |d| d.name().to_string()),
+                    frame_rate: FrameRate::new(rate.numerator().into(), rate.denominator().into()),
+                    color_space: video.color_space().name().map(String::from),
+                    color_range: video.color_range().name().map(String::from),
+                });
+            }
+        }
+        media::Type::Audio => {
+            if let Ok(audio) = context.and_then(|c| c.decoder().audio()) {
+                info.bit_rate = audio.bit_rate();
+                info.audio = Some(AudioInfo {
+                    sample_rate: audio.rate(),
+                    channels: audio.channels(),
+                    sample_format: audio.format().name().to_string(),
+                });
+            }
+        }
+        _ => {}
+    }
+    info
+}
+
+/// Opens `location` and reads its full report. This blocks, so it should be run off the main
+/// thread.
+pub fn read(location: &Path) -> Result<Report, Error> {
+    ffmpeg_next::init()?;
+    let input = format::input(location)?;
+
+    let mut streams = Vec::new();
+    let mut attachments = Vec::new();
+    for stream in input.streams() {
+        if stream.parameters().medium() == media::Type::Attachment {
+            let parameters = stream.parameters();
+            // Attached files are kept as extradata, which ffmpeg-next has no accessor for
+            let size = unsafe { (*parameters.as_ptr()).extradata_size }.max(0) as usize;
+            let tags = read_tags(stream.metadata());
+            attachments.push(AttachmentInfo {
+                index: stream.index(),
+                filename: tag(&tags, "filename").map(String::from),
+                mimetype: tag(&tags, "mimetype").map(String::from),
+                size,
+            });
+        } else {
+            streams.push(read_stream(&stream));
+        }
+    }
+
+    let chapters = input
+        .chapters()
+        .map(|chapter| ChapterInfo {
+            start: chapter
+                .start()
+                .rescale(chapter.time_base(), rescale::TIME_BASE),
+            end: chapter
+                .end()
+                .rescale(chapter.time_base(), rescale::TIME_BASE),
+            title: chapter.metadata().get("title").map(String::from),
+        })
+        .collect();
+
+    Ok(Report {
+        path: location.to_path_buf(),
+        format: input.format().description().to_string(),
+        format_name: input.format().name().to_string(),
+        duration: input.duration().max(0),
+        size: (!playlist::is_url(location))
+            .then(|| std::fs::metadata(location).ok())
+            .flatten()
+            .map(|m| m.len()),
+        bit_rate: input.bit_rate(),
+        streams,
+        chapters,
+        tags: read_tags(input.metadata()),
+        attachments,
+    })
+}
+
+fn seconds(micros: i64) -> f64 {
+    micros as f64 / 1_000_000.0
+}
+
+fn format_bit_rate(bit_rate: i64) -> String {
+    if bit_rate >= 1_000_000 {
+        format!("{:.2} Mbps", bit_rate as f64 / 1_000_000.0)
+    } else {
+        format!("{} kbps", bit_rate / 1000)
+    }
+}
+
+fn format_size(size: u64) -> String {
+    match size {
+        s if s >= 1 << 30 => format!("{:.2} GiB", s as f64 / (1u64 << 30) as f64),
+        s if s >= 1 << 20 => format!("{:.1} MiB", s as f64 / (1u64 << 20) as f64),
+        s if s >= 1 << 10 => format!("{:.1} KiB", s as f64 / (1u64 << 10) as f64),
+        s => format!("{s} bytes"),
+    }
+}
+
+fn tags_json(tags: &[(String, String)]) -> Value {
+    Value::Object(
+        tags.iter()
+            .map(|(key, value)| (key.clone(), Value::from(value.as_str())))
+            .collect::<Map<_, _>>(),
+    )
+}
+
+/// Escapes text for a Markdown table cell.
+fn markdown_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', "<br>")
+}
+
+impl Report {
+    pub fn render(&self, format: ReportFormat) -> String {
+        match format {
+            ReportFormat::Text => self.to_text(),
+            ReportFormat::Markdown => self.to_markdown(),
+            ReportFormat::Json => serde_json::to_string_pretty(&self.to_json()).unwrap(),
+        }
+    }
+
+    /// Returns the container fields followed by the container tags.
+    fn general(&self) -> Vec<(String, String)> {
+        let mut fields = vec![
+            (
+                String::from("Complete name"),
+                self.path.display().to_string(),
+            ),
+            (String::from("Format"), self.format.clone()),
+        ];
+        if let Some(size) = self.size {
+            fields.push((String::from("File size"), format_size(size)));
+        }
+        if self.duration > 0 {
+            fields.push((
+                String::from("Duration"),
+                timecode::format_precise(self.duration),
+            ));
+        }
+        if self.bit_rate > 0 {
+            fields.push((
+                String::from("Overall bitrate"),
+                format_bit_rate(self.bit_rate),
+            ));
+        }
+        fields.extend(self.tags.iter().cloned());
+        fields
+    }
+
+    fn attachment_fields(attachment: &AttachmentInfo) -> Vec<(String, String)> {
+        vec![
+            (
+                String::from("File name"),
+                attachment.filename.clone().unwrap_or_default(),
+            ),
+            (
+                String::from("MIME type"),
+                attachment.mimetype.clone().unwrap_or_default(),
+            ),
+            (String::from("Size"), format_size(attachment.size as u64)),
+        ]
+    }
+
+    fn chapter_title(chapter: &ChapterInfo, index: usize) -> String {
+        chapter
+            .title
+            .clone()
+            .unwrap_or_else(|| format!("Chapter {}", index + 1))
+    }
+
+    fn to_text(&self) -> String {
+        let mut text = String::new();
+        let mut section = |title: &str, fields: Vec<(String, String)>| {
+            let _ = writeln!(text, "{title}");
+            for (key, value) in fields {
+                let _ = writeln!(text, "{key:<LABEL_WIDTH$} : {value}");
+            }
+            text.push('\n');
+        };
+
+        section("General", self.general());
+        for stream in &self.streams {
+            section(&stream.title(), stream.fields());
+        }
+        if !self.chapters.is_empty() {
+            let chapters = self
+                .chapters
+                .iter()
+                .enumerate()
+                .map(|(i, c)| (timecode::format_precise(c.start), Self::chapter_title(c, i)))
+                .collect();
+            section("Menu", chapters);
+        }
+        for attachment in &self.attachments {
+            section(
+                &format!("Attachment #{}", attachment.index),
+                Self::attachment_fields(attachment),
+            );
+        }
+        text.trim_end().to_string() + "\n"
+    }
+
+    fn to_markdown(&self) -> String {
+        let mut text = String::new();
+        let mut section = |title: &str, fields: Vec<(String, String)>| {
+            let _ = writeln!(text, "### {title}\n\n| Field | Value |\n| --- | --- |");
+            for (key, value) in fields {
+                let _ = writeln!(
+                    text,
+                    "| {} | {} |",
+                    markdown_cell(&key),
+                    markdown_cell(&value)
+                );
+            }
+            text.push('\n');
+        };
+
+        section("General", self.general());
+        for stream in &self.streams {
+            section(&stream.title(), stream.fields());
+        }
+        for attachment in &self.attachments {
+            section(
+                &format!("Attachment #{}", attachment.index),
+                Self::attachment_fields(attachment),
+            );
+        }
+        if !self.chapters.is_empty() {
+            text.push_str(
+                "### Chapters\n\n| # | Start | End | Title |\n| --- | --- | --- | --- |\n",
+            );
+            for (i, chapter) in self.chapters.iter().enumerate() {
+                let _ = writeln!(
+                    text,
+                    "| {} | {} | {} | {} |",
+                    i + 1,
+                    timecode::format_precise(chapter.start),
+                    timecode::format_precise(chapter.end),
+                    markdown_cell(&Self::chapter_title(chapter, i))
+                );
+            }
+        }
+        let title = self.path.file_name().map_or_else(
+            || self.path.display().to_string(),
+            |n| n.to_string_lossy().into(),
+        );
+        format!("## {}\n\n{}", markdown_cell(&title), text.trim_end()) + "\n"
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "path": self.path.to_string_lossy(),
+            "container": {
+                "format": self.format_name,
+                "format_long_name": self.format,
+                "duration": seconds(self.duration),
+                "size": self.size,
+                "bit_rate": self.bit_rate,
+                "tags": tags_json(&self.tags),
+            },
+            "streams": self.streams.iter().map(StreamInfo::to_json).collect::<Vec<_>>(),
+            "chapters": self.chapters.iter().map(|chapter| json!({
+                "start": seconds(chapter.start),
+                "end": seconds(chapter.end),
+                "title": chapter.title,
+            })).collect::<Vec<_>>(),
+            "attachments": self.attachments.iter().map(|attachment| json!({
+                "index": attachment.index,
+                "filename": attachment.filename,
+                "mimetype": attachment.mimetype,
+                "size": attachment.size,
+            })).collect::<Vec<_>>(),
+        })
+    }
+
+    /// Returns one CSV line with the main facts, in the order of `CSV_COLUMNS`.
+    fn csv_row(&self) -> String {
+        let video = self.streams.iter().find(|s| s.video.is_some());
+        let audio = self.streams.iter().find(|s| s.audio.is_some());
+        let video_info = video.and_then(|s| s.video.as_ref());
+        let audio_info = audio.and_then(|s| s.audio.as_ref());
+        let fields = [
+            self.path.to_string_lossy().to_string(),
+            self.format_name.clone(),
+            timecode::format_seconds(self.duration),
+            self.size.map_or_else(String::new, |s| s.to_string()),
+            self.bit_rate.to_string(),
+            video.map_or_else(String::new, |s| s.codec.clone()),
+            video_info.map_or_else(String::new, |v| v.width.to_string()),
+            video_info.map_or_else(String::new, |v| v.height.to_string()),
+            video_info
+                .and_then(|v| v.frame_rate)
+                .map_or_else(String::new, |r| format!("{:.3}", r.fps())),
+            audio.map_or_else(String::new, |s| s.codec.clone()),
+            audio_info.map_or_else(String::new, |a| a.sample_rate.to_string()),
+            audio_info.map_or_else(String::new, |a| a.channels.to_string()),
+            self.streams.len().to_string(),
+            self.chapters.len().to_string(),
+            tag(&self.tags, "title").unwrap_or_default().to_string(),
+        ];
+        csv::row(&fields)
+    }
+}
+
+/// Writes the report of every media file in `locations` to `output`, or to the standard output
+/// when there is none. Folders are searched for media. Files that cannot be read are skipped
+/// with an error.
+pub fn batch(locations: &[PathBuf], format: BatchFormat, output: Option<&Path>) -> io::Result<()> {
+    let mut writer: BufWriter<Box<dyn Write>> = BufWriter::new(match output {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(io::stdout()),
+    });
+    if format == BatchFormat::Csv {
+        writeln!(writer, "{}", CSV_COLUMNS.join(","))?;
+    }
+
+    let files = locations.iter().flat_map(|location| {
+        if location.is_dir() {
+            playlist::collect_media(location)
+        } else {
+            vec![location.clone()]
+        }
+    });
+    for path in files {
+        let report = match read(&path) {
+            Ok(report) => report,
+            Err(e) => {
+                eprintln!("Error: Could not read {} ({e})", path.display());
+                continue;
+            }
+        };
+        match format {
+            BatchFormat::Csv => writeln!(writer, "{}", report.csv_row())?,
+            BatchFormat::JsonLines => writeln!(writer, "{}", report.to_json())?,
+        }
+    }
+    writer.flush()
+}
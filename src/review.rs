@@ -0,0 +1,530 @@
+use std::fmt::Write as _;
+use std::path::Path;
+
+use crate::csv;
+use crate::storage;
+use crate::timecode::{self, FrameRate};
+
+const FILE: &str = "review.ini";
+const CSV_COLUMNS: [&str; 5] = ["in", "out", "author", "color", "comment"];
+
+/// Positions taken from the player while reviewing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReviewPoint {
+    In,
+    Out,
+    /// Adds a comment at the in and out points, or at the position when none are set
+    Comment,
+}
+
+/// Color tags, named after the marker colors NLEs share.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Color {
+    #[default]
+    Blue,
+    Cyan,
+    Green,
+    Yellow,
+    Red,
+    Pink,
+    Purple,
+}
+
+impl Color {
+    pub const ALL: [Self; 7] = [
+        Self::Blue,
+        Self::Cyan,
+        Self::Green,
+        Self::Yellow,
+        Self::Red,
+        Self::Pink,
+        Self::Purple,
+    ];
+
+    pub fn parse(id: &str) -> Self {
+        Self::ALL
+            .into_iter()
+            .find(|color| color.id().eq_ignore_ascii_case(id.trim()))
+            .unwrap_or_default()
+    }
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            Self::Blue => "blue",
+            Self::Cyan => "cyan",
+            Self::Green => "green",
+            Self::Yellow => "yellow",
+            Self::Red => "red",
+            Self::Pink => "pink",
+            Self::Purple => "purple",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Blue => "Blue",
+            Self::Cyan => "Cyan",
+            Self::Green => "Green",
+            Self::Yellow => "Yellow",
+            Self::Red => "Red",
+            Self::Pink => "Pink",
+            Self::Purple => "Purple",
+        }
+    }
+}
+
+/// A review comment on a frame, or on a range of frames when it has an end.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment {
+    /// In microseconds, at the start of a frame
+    pub start: i64,
+    /// First frame after the range
+    pub end: Option<i64>,
+    pub author: String,
+    pub text: String,
+    pub color: Color,
+}
+
+/// File formats comments can be exported to and imported from.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum ReviewFormat {
+    /// CMX3600 EDL with one marker event per comment, as read by Resolve and Premiere
+    #[default]
+    Edl,
+    /// Final Cut Pro 7 XML with the comments as clip markers. Colors are not carried.
+    FcpXml,
+    Csv,
+}
+
+impl ReviewFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Edl => "edl",
+            Self::FcpXml => "xml",
+            Self::Csv => "csv",
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("xml") => Self::FcpXml,
+            Some("csv") => Self::Csv,
+            _ => Self::Edl,
+        }
+    }
+}
+
+/// Returns the comments saved for `location`, sorted by start.
+pub fn load(location: &Path) -> Vec<Comment> {
+    let file = storage::load(&storage::data_file(FILE));
+    let group = storage::key_for(location);
+    let list = |key| file.string_list(&group, key).unwrap_or_default();
+    let (starts, ends, authors) = (list("starts"), list("ends"), list("authors"));
+    let (texts, colors) = (list("texts"), list("colors"));
+    let mut comments = (0..starts.len())
+        .filter_map(|i| {
+            Some(Comment {
+                start: starts[i].parse().ok()?,
+                end: ends.get(i)?.parse().ok(),
+                author: authors.get(i)?.to_string(),
+                text: texts.get(i)?.to_string(),
+                color: Color::parse(colors.get(i)?),
+            })
+        })
+        .collect::<Vec<_>>();
+    comments.sort_by_key(|c| c.start);
+    comments
+}
+
+/// Saves the comments on `location`, forgetting the file when there are none.
+pub fn store(location: &Path, comments: &[Comment]) {
+    let path = storage::data_file(FILE);
+    let file = storage::load(&path);
+    let group = storage::key_for(location);
+    if comments.is_empty() {
+        let _ = file.remove_group(&group);
+    } else {
+        let starts = comments
+            .iter()
+            .map(|c| c.start.to_string())
+            .collect::<Vec<_>>();
+        // Comments without an end keep an empty entry, so the lists stay aligned
+        let ends = comments
+            .iter()
+            .map(|c| c.end.map(|end| end.to_string()).unwrap_or_default())
+            .collect::<Vec<_>>();
+        let starts = starts.iter().map(String::as_str).collect::<Vec<_>>();
+        let ends = ends.iter().map(String::as_str).collect::<Vec<_>>();
+        let authors = comments
+            .iter()
+            .map(|c| c.author.as_str())
+            .collect::<Vec<_>>();
+        let texts = comments.iter().map(|c| c.text.as_str()).collect::<Vec<_>>();
+        let colors = comments.iter().map(|c| c.color.id()).collect::<Vec<_>>();
+        file.set_string(&group, "location", &location.to_string_lossy());
+        file.set_string_list(&group, "starts", &starts);
+        file.set_string_list(&group, "ends", &ends);
+        file.set_string_list(&group, "authors", &authors);
+        file.set_string_list(&group, "texts", &texts);
+        file.set_string_list(&group, "colors", &colors);
+    }
+    storage::save(&file, &path);
+}
+
+/// Returns the number of frames a comment covers: none for a marker without an end, and at
+/// least one for a range.
+fn frames(comment: &Comment, rate: FrameRate) -> i64 {
+    comment.end.map_or(0, |end| {
+        (rate.frame_at(end) - rate.frame_at(comment.start)).max(1)
+    })
+}
+
+/// EDL lines end at line breaks and markers at ` |`, so both are replaced in free text.
+fn edl_text(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace(" |", " /")
+}
+
+fn write_edl(comments: &[Comment], rate: FrameRate, title: &str) -> String {
+    let mut text = format!("TITLE: {}\n", edl_text(title));
+    let mode = if rate.is_drop_frame() {
+        "DROP FRAME"
+    } else {
+        "NON-DROP FRAME"
+    };
+    let _ = writeln!(text, "FCM: {mode}");
+    for (i, comment) in comments.iter().enumerate() {
+        let frames = frames(comment, rate);
+        let start = rate.frame_start(rate.frame_at(comment.start));
+        let end = rate.frame_start(rate.frame_at(start) + frames);
+        let (start, end) = (
+            timecode::format_smpte(start, rate),
+            timecode::format_smpte(end, rate),
+        );
+        let _ = writeln!(
+            text,
+            "\n{:03}  AX       V     C        {start} {end} {start} {end}",
+            i + 1
+        );
+        let _ = writeln!(
+            text,
+            " |C:ResolveColor{} |M:{} |D:{}",
+            comment.color.label(),
+            edl_text(&comment.text),
+            frames.max(1)
+        );
+        let _ = writeln!(text, "* FROM CLIP NAME: {}", edl_text(title));
+        if !comment.author.is_empty() {
+            let _ = writeln!(text, "* AUTHOR: {}", edl_text(&comment.author));
+        }
+    }
+    text
+}
+
+/// Reads marker events, taking their source in and out. Events whose out is after their in
+/// become ranges, so markers written with out equal to in have no end.
+fn read_edl(text: &str, rate: FrameRate) -> Result<Vec<Comment>, String> {
+    let mut comments: Vec<Comment> = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim_end();
+        let error = || format!("Line {} has an invalid timecode", number + 1);
+        let fields = line.split_whitespace().collect::<Vec<_>>();
+        if fields.len() >= 8 && fields[0].bytes().all(|b| b.is_ascii_digit()) {
+            let parse = |text| timecode::parse(text, Some(rate)).ok_or_else(error);
+            let (start, end) = (
+                parse(fields[fields.len() - 4])?,
+                parse(fields[fields.len() - 3])?,
+            );
+            comments.push(Comment {
+                start,
+                end: (rate.frame_at(end) > rate.frame_at(start)).then_some(end),
+                author: String::new(),
+                text: String::new(),
+                color: Color::default(),
+            });
+            continue;
+        }
+        let Some(comment) = comments.last_mut() else {
+            continue;
+        };
+        if let Some(author) = line.strip_prefix("* AUTHOR:") {
+            comment.author = author.trim().to_string();
+        } else if line.trim_start().starts_with('|') {
+            for part in line.split(" |").map(|p| p.trim().trim_start_matches('|')) {
+                if let Some(color) = part.strip_prefix("C:") {
+                    comment.color = Color::parse(color.trim_start_matches("ResolveColor"));
+                } else if let Some(text) = part.strip_prefix("M:") {
+                    comment.text = text.to_string();
+                }
+            }
+        }
+    }
+    Ok(comments)
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn xml_unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(index) = rest.find('&') {
+        unescaped.push_str(&rest[..index]);
+        rest = &rest[index..];
+        let Some(end) = rest.find(';') else {
+            break;
+        };
+        let entity = &rest[1..end];
+        let c = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16).ok())
+                .unwrap_or_else(|| entity.strip_prefix('#').and_then(|d| d.parse().ok()))
+                .and_then(char::from_u32),
+        };
+        match c {
+            Some(c) => {
+                unescaped.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                unescaped.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+/// Returns the contents of the first `<name>` element in `xml`, which is empty when written
+/// as `<name/>`.
+fn element<'a>(xml: &'a str, name: &str) -> Option<&'a str> {
+    let open = format!("<{name}>");
+    let start = xml.find(&open)? + open.len();
+    let end = start + xml[start..].find(&format!("</{name}>"))?;
+    Some(&xml[start..end])
+}
+
+fn write_fcp_xml(comments: &[Comment], rate: FrameRate, title: &str, duration: i64) -> String {
+    let ntsc = if rate.is_ntsc() { "TRUE" } else { "FALSE" };
+    let rate_xml = format!(
+        "<rate><timebase>{}</timebase><ntsc>{ntsc}</ntsc></rate>",
+        rate.nominal()
+    );
+    let mut text = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    text += "<!DOCTYPE xmeml>\n<xmeml version=\"5\">\n  <clip id=\"review\">\n";
+    let _ = writeln!(text, "    <name>{}</name>", xml_escape(title));
+    let _ = writeln!(text, "    <duration>{}</duration>", rate.frame_at(duration));
+    let _ = writeln!(text, "    {rate_xml}");
+    for comment in comments {
+        let start = rate.frame_at(comment.start);
+        // Markers on a single frame have no out point
+        let end = comment.end.map_or(-1, |end| rate.frame_at(end));
+        // NLEs show the name as the marker's title and the comment as its notes
+        text += "    <marker>\n";
+        let _ = writeln!(text, "      <name>{}</name>", xml_escape(&comment.author));
+        let _ = writeln!(
+            text,
+            "      <comment>{}</comment>",
+            xml_escape(&comment.text)
+        );
+        let _ = writeln!(text, "      <in>{start}</in>\n      <out>{end}</out>");
+        text += "    </marker>\n";
+    }
+    text + "  </clip>\n</xmeml>\n"
+}
+
+/// Reads the markers of every clip, at the rate of the first `<rate>` element, or `rate` when
+/// there is none. Names are taken as authors, except on markers with no comment, whose name is
+/// their only text.
+fn read_fcp_xml(text: &str, rate: FrameRate) -> Result<Vec<Comment>, String> {
+    if !text.contains("<xmeml") {
+        return Err(String::from("Not a Final Cut Pro XML file"));
+    }
+    let rate = element(text, "rate")
+        .and_then(|xml| {
+            let timebase = element(xml, "timebase")?.trim().parse::<i64>().ok()?;
+            let ntsc = element(xml, "ntsc").is_some_and(|n| n.trim().eq_ignore_ascii_case("true"));
+            if ntsc {
+                FrameRate::new(timebase * 1000, 1001)
+            } else {
+                FrameRate::new(timebase, 1)
+            }
+        })
+        .unwrap_or(rate);
+
+    let mut comments = Vec::new();
+    for (i, marker) in text.split("<marker>").skip(1).enumerate() {
+        let marker = marker.split("</marker>").next().unwrap_or_default();
+        let string = |name| xml_unescape(element(marker, name).unwrap_or_default().trim());
+        let frame = |name| element(marker, name).and_then(|f| f.trim().parse::<i64>().ok());
+        let start = frame("in").ok_or(format!("Marker {} has no valid in point", i + 1))?;
+        let (name, text) = (string("name"), string("comment"));
+        let (author, text) = if text.is_empty() {
+            (String::new(), name)
+        } else {
+            (name, text)
+        };
+        comments.push(Comment {
+            start: rate.frame_start(start),
+            end: frame("out")
+                .filter(|end| *end > start)
+                .map(|end| rate.frame_start(end)),
+            author,
+            text,
+            color: Color::default(),
+        });
+    }
+    Ok(comments)
+}
+
+fn write_csv(comments: &[Comment], rate: FrameRate) -> String {
+    let mut text = CSV_COLUMNS.join(",") + "\n";
+    for comment in comments {
+        text += &csv::row(&[
+            timecode::format_smpte(comment.start, rate),
+            comment
+                .end
+                .map(|end| timecode::format_smpte(end, rate))
+                .unwrap_or_default(),
+            comment.author.clone(),
+            comment.color.id().to_string(),
+            comment.text.clone(),
+        ]);
+        text.push('\n');
+    }
+    text
+}
+
+fn read_csv(text: &str, rate: FrameRate) -> Result<Vec<Comment>, String> {
+    let mut records = csv::parse(text).into_iter().peekable();
+    // The header is optional, but when present it decides the column order
+    let mut columns = [0, 1, 2, 3, 4];
+    if let Some(header) = records.peek() {
+        let index = |name| {
+            header
+                .iter()
+                .position(|h| h.trim().eq_ignore_ascii_case(name))
+        };
+        if let Some(start) = index("in") {
+            columns = [start, 0, 0, 0, 0];
+            for (column, name) in columns.iter_mut().zip(CSV_COLUMNS).skip(1) {
+                *column = index(name).unwrap_or(usize::MAX);
+            }
+            records.next();
+        }
+    }
+    records
+        .enumerate()
+        .map(|(i, record)| {
+            let field = |column: usize| record.get(column).map_or("", String::as_str);
+            let start = timecode::parse(field(columns[0]), Some(rate))
+                .ok_or(format!("Row {} has no valid in point", i + 1))?;
+            let end = match field(columns[1]).trim() {
+                "" => None,
+                end => Some(
+                    timecode::parse(end, Some(rate))
+                        .ok_or(format!("Row {} has an invalid out point", i + 1))?,
+                ),
+            };
+            Ok(Comment {
+                start,
+                end: end.filter(|end| *end > start),
+                author: field(columns[2]).to_string(),
+                color: Color::parse(field(columns[3])),
+                text: field(columns[4]).to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Writes comments in `format`, with timecode at `rate` counted from the start of the file.
+/// `title` names the reviewed clip.
+pub fn export(
+    comments: &[Comment],
+    format: ReviewFormat,
+    rate: FrameRate,
+    title: &str,
+    duration: i64,
+) -> String {
+    match format {
+        ReviewFormat::Edl => write_edl(comments, rate, title),
+        ReviewFormat::FcpXml => write_fcp_xml(comments, rate, title, duration),
+        ReviewFormat::Csv => write_csv(comments, rate),
+    }
+}
+
+/// Reads comments written by `export` or by an NLE. Timecode is read at `rate`, except in
+/// XML files, which state their own.
+pub fn import(text: &str, format: ReviewFormat, rate: FrameRate) -> Result<Vec<Comment>, String> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let mut comments = match format {
+        ReviewFormat::Edl => read_edl(text, rate)?,
+        ReviewFormat::FcpXml => read_fcp_xml(text, rate)?,
+        ReviewFormat::Csv => read_csv(text, rate)?,
+    };
+    comments.sort_by_key(|c| c.start);
+    Ok(comments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn comments(rate: FrameRate) -> Vec<Comment> {
+        let comment = |start, end: Option<i64>, author: &str, text: &str, color| Comment {
+            start: rate.frame_start(start),
+            end: end.map(|end| rate.frame_start(end)),
+            author: author.to_string(),
+            text: text.to_string(),
+            color,
+        };
+        vec![
+            comment(10, None, "Ana", "Flash frame", Color::Red),
+            comment(20, Some(21), "", "One frame", Color::Green),
+            comment(30, Some(55), "Ben", "Soften the grade", Color::Blue),
+        ]
+    }
+
+    #[test]
+    fn edl_round_trips_markers_and_ranges() {
+        let rate = FrameRate::new(25, 1).unwrap();
+        let comments = comments(rate);
+        let text = export(&comments, ReviewFormat::Edl, rate, "Cut", 2_000_000);
+        assert!(text.contains("00:00:00:10 00:00:00:10"));
+        assert!(text.contains("00:00:00:20 00:00:00:21"));
+        assert_eq!(import(&text, ReviewFormat::Edl, rate).unwrap(), comments);
+    }
+
+    #[test]
+    fn fcp_xml_keeps_text_in_comments() {
+        let rate = FrameRate::new(30_000, 1001).unwrap();
+        let mut comments = comments(rate);
+        let text = export(&comments, ReviewFormat::FcpXml, rate, "Cut", 2_000_000);
+        assert!(text.contains("<name>Ana</name>\n      <comment>Flash frame</comment>"));
+        // Colors are not carried
+        for comment in &mut comments {
+            comment.color = Color::default();
+        }
+        assert_eq!(import(&text, ReviewFormat::FcpXml, rate).unwrap(), comments);
+
+        let marker = "<xmeml><marker><name>Fix</name><comment></comment><in>5</in></marker>";
+        let imported = import(marker, ReviewFormat::FcpXml, rate).unwrap();
+        assert_eq!(
+            (imported[0].author.as_str(), imported[0].text.as_str()),
+            ("", "Fix")
+        );
+    }
+}
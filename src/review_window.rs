@@ -0,0 +1,465 @@
+use std::path::PathBuf;
+
+use adw::prelude::*;
+use gtk::glib::clone;
+use relm4::{
+    actions::{RelmAction, RelmActionGroup},
+    prelude::*,
+};
+
+use crate::review::{self, Color, Comment, ReviewFormat, ReviewPoint};
+use crate::timecode::{self, FrameRate};
+
+relm4::new_action_group!(ReviewActionGroup, "review");
+relm4::new_stateless_action!(ImportComments, ReviewActionGroup, "import");
+relm4::new_stateless_action!(ExportComments, ReviewActionGroup, "export");
+
+pub struct ReviewWindow {
+    visible: bool,
+    /// File under review
+    path: Option<PathBuf>,
+    duration: i64,
+    frame_rate: Option<FrameRate>,
+    /// Signs new comments
+    author: String,
+    comments: Vec<Comment>,
+    /// Range marked for the next comment
+    in_point: Option<i64>,
+    out_point: Option<i64>,
+    list: gtk::ListBox,
+}
+
+#[derive(Debug)]
+pub enum ReviewMsg {
+    SetFile(PathBuf),
+    SetTiming(i64, Option<FrameRate>),
+    /// A position of the current file taken by the player
+    Point(ReviewPoint, i64),
+    SetAuthor(String),
+    SetText(usize, String),
+    SetColor(usize, Color),
+    Remove(usize),
+    Jump(usize),
+    Import,
+    Export,
+    Show,
+    Hide,
+}
+
+#[derive(Debug)]
+pub enum ReviewOutput {
+    /// Asks for a comment at the current position
+    AddCurrent,
+    SeekTo(i64),
+    Author(String),
+}
+
+#[relm4::component(async, pub)]
+impl AsyncComponent for ReviewWindow {
+    /// Parent window and the author of new comments
+    type Init = (adw::Window, String);
+    type Input = ReviewMsg;
+    type Output = ReviewOutput;
+    type CommandOutput = ();
+
+    menu! {
+        file_menu: {
+            section! {
+                "Import…" => ImportComments,
+                "Export…" => ExportComments,
+            }
+        }
+    }
+
+    view! {
+        #[root]
+        window = adw::Window {
+            set_title: Some("Review"),
+            set_default_size: (460, 520),
+            #[watch]
+            set_visible: model.visible,
+            gtk::Box {
+                set_orientation: gtk::Orientation::Vertical,
+                adw::HeaderBar {
+                    #[wrap(Some)]
+                    set_title_widget = &adw::WindowTitle {
+                        set_title: "Review",
+                        #[watch]
+                        set_subtitle: &model.marked_range(),
+                    },
+                    pack_start = &gtk::Button {
+                        set_icon_name: "list-add-symbolic",
+                        set_tooltip_text: Some("Comment on Current Frame"),
+                        #[watch]
+                        set_sensitive: model.path.is_some(),
+                        connect_clicked[sender] => move |_| {
+                            let _ = sender.output(ReviewOutput::AddCurrent);
+                        },
+                    },
+                    pack_end = &gtk::MenuButton {
+                        set_icon_name: "document-send-symbolic",
+                        set_tooltip_text: Some("Import or Export Comments"),
+                        set_menu_model: Some(&file_menu),
+                        #[watch]
+                        set_sensitive: model.path.is_some(),
+                    },
+                },
+                adw::PreferencesGroup {
+                    set_margin_start: 12,
+                    set_margin_end: 12,
+                    set_margin_top: 12,
+                    adw::EntryRow {
+                        set_title: "Author",
+                        set_text: &model.author,
+                        connect_changed[sender] => move |entry| {
+                            sender.input(ReviewMsg::SetAuthor(entry.text().into()));
+                        },
+                    },
+                },
+                adw::StatusPage {
+                    set_icon_name: Some("user-available-symbolic"),
+                    set_title: "No Comments",
+                    set_description: Some(
+                        "Press I and O to mark a range and Ctrl+M to comment on it, \
+                         or on the current frame"
+                    ),
+                    set_vexpand: true,
+                    #[watch]
+                    set_visible: model.comments.is_empty(),
+                },
+                gtk::ScrolledWindow {
+                    set_hscrollbar_policy: gtk::PolicyType::Never,
+                    set_vexpand: true,
+                    #[watch]
+                    set_visible: !model.comments.is_empty(),
+                    adw::Clamp {
+                        set_margin_all: 12,
+                        #[local_ref]
+                        list -> gtk::ListBox {
+                            set_selection_mode: gtk::SelectionMode::None,
+                            set_valign: gtk::Align::Start,
+                            add_css_class: "boxed-list",
+                        },
+                    },
+                },
+            },
+            connect_close_request[sender] => move |_| {
+                sender.input(ReviewMsg::Hide);
+                gtk::glib::Propagation::Proceed
+            }
+        }
+    }
+
+    async fn init(
+        init: Self::Init,
+        root: Self::Root,
+        sender: AsyncComponentSender<Self>,
+    ) -> AsyncComponentParts<Self> {
+        let (parent, author) = init;
+        let model = Self {
+            visible: false,
+            path: None,
+            duration: 0,
+            frame_rate: None,
+            author,
+            comments: Vec::new(),
+            in_point: None,
+            out_point: None,
+            list: gtk::ListBox::new(),
+        };
+
+        let list = &model.list;
+        let widgets = view_output!();
+        widgets.window.set_transient_for(Some(&parent));
+
+        let mut group = RelmActionGroup::<ReviewActionGroup>::new();
+        group.add_action::<ImportComments>(RelmAction::new_stateless(clone!(
+            #[strong]
+            sender,
+            move |_| {
+                sender.input(ReviewMsg::Import);
+            }
+        )));
+        group.add_action::<ExportComments>(RelmAction::new_stateless(clone!(
+            #[strong]
+            sender,
+            move |_| {
+                sender.input(ReviewMsg::Export);
+            }
+        )));
+        group.register_for_widget(&widgets.window);
+
+        AsyncComponentParts { model, widgets }
+    }
+
+    async fn update(
+        &mut self,
+        msg: Self::Input,
+        sender: AsyncComponentSender<Self>,
+        _root: &Self::Root,
+    ) {
+        match msg {
+            ReviewMsg::SetFile(path) => {
+                self.comments = review::load(&path);
+                self.path = Some(path);
+                self.duration = 0;
+                self.frame_rate = None;
+                self.in_point = None;
+                self.out_point = None;
+                self.render(&sender, None);
+            }
+            ReviewMsg::SetTiming(duration, frame_rate) => {
+                self.duration = duration;
+                self.frame_rate = frame_rate;
+                self.render(&sender, None);
+            }
+            ReviewMsg::Point(point, position) => {
+                if self.path.is_none() {
+                    return;
+                }
+                self.visible = true;
+                match point {
+                    ReviewPoint::In => {
+                        self.in_point = Some(position);
+                        if self.out_point.is_some_and(|out| out <= position) {
+                            self.out_point = None;
+                        }
+                    }
+                    // The marked frame is part of the range, so it ends where the next begins
+                    ReviewPoint::Out => {
+                        let rate = self.rate();
+                        let end = rate.frame_start(rate.frame_at(position) + 1);
+                        self.out_point = Some(end);
+                        if self.in_point.is_some_and(|start| start >= end) {
+                            self.in_point = None;
+                        }
+                    }
+                    ReviewPoint::Comment => {
+                        let start = self.in_point.take().unwrap_or(position);
+                        let end = self.out_point.take().filter(|end| *end > start);
+                        let index = self.comments.partition_point(|c| c.start <= start);
+                        self.comments.insert(
+                            index,
+                            Comment {
+                                start,
+                                end,
+                                author: self.author.clone(),
+                                text: String::new(),
+                                color: Color::default(),
+                            },
+                        );
+                        self.save();
+                        self.render(&sender, Some(index));
+                    }
+                }
+            }
+            ReviewMsg::SetAuthor(author) => {
+                self.author = author.trim().to_string();
+                let _ = sender.output(ReviewOutput::Author(self.author.clone()));
+            }
+            // Edits keep the rows, so the entry being typed in keeps its focus
+            ReviewMsg::SetText(index, text) => {
+                if let Some(comment) = self.comments.get_mut(index) {
+                    comment.text = text;
+                    self.save();
+                }
+            }
+            ReviewMsg::SetColor(index, color) => {
+                if let Some(comment) = self.comments.get_mut(index) {
+                    comment.color = color;
+                    self.save();
+                }
+            }
+            ReviewMsg::Remove(index) => {
+                if index < self.comments.len() {
+                    self.comments.remove(index);
+                    self.save();
+                    self.render(&sender, None);
+                }
+            }
+            ReviewMsg::Jump(index) => {
+                if let Some(comment) = self.comments.get(index) {
+                    let _ = sender.output(ReviewOutput::SeekTo(comment.start));
+                }
+            }
+            ReviewMsg::Import => {
+                let dialog = rfd::AsyncFileDialog::new()
+                    .set_title("Import Comments")
+                    .add_filter("Comments", &["edl", "xml", "csv"])
+                    .pick_file();
+                let Some(file) = dialog.await else {
+                    return;
+                };
+                let path = file.path();
+                let imported = std::fs::read_to_string(path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|text| {
+                        review::import(&text, ReviewFormat::from_path(path), self.rate())
+                    });
+                match imported {
+                    Ok(imported) => {
+                        for comment in imported {
+                            if !self.comments.contains(&comment) {
+                                self.comments.push(comment);
+                            }
+                        }
+                        self.comments.sort_by_key(|c| c.start);
+                        self.save();
+                        self.render(&sender, None);
+                    }
+                    Err(e) => eprintln!("Error: Could not import {} ({e})", path.display()),
+                }
+            }
+            ReviewMsg::Export => {
+                let Some(path) = &self.path else {
+                    return;
+                };
+                // Editors see the file name as the clip name
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+                let dialog = rfd::AsyncFileDialog::new()
+                    .set_title("Export Comments")
+                    .set_file_name(format!("{stem}.{}", ReviewFormat::Edl.extension()))
+                    .add_filter("CMX3600 EDL", &[ReviewFormat::Edl.extension()])
+                    .add_filter("Final Cut Pro XML", &[ReviewFormat::FcpXml.extension()])
+                    .add_filter("CSV", &[ReviewFormat::Csv.extension()])
+                    .save_file();
+                if let Some(file) = dialog.await {
+                    let path = file.path();
+                    let text = review::export(
+                        &self.comments,
+                        ReviewFormat::from_path(path),
+                        self.rate(),
+                        &name,
+                        self.duration,
+                    );
+                    if let Err(e) = std::fs::write(path, text) {
+                        eprintln!("Error: Could not export {} ({e})", path.display());
+                    }
+                }
+            }
+            ReviewMsg::Show => {
+                self.visible = true;
+            }
+            ReviewMsg::Hide => {
+                self.visible = false;
+            }
+        }
+    }
+}
+
+impl ReviewWindow {
+    /// Frame rate used for timecode. Files without video are counted at 25 fps.
+    fn rate(&self) -> FrameRate {
+        self.frame_rate
+            .unwrap_or_else(|| FrameRate::new(25, 1).unwrap())
+    }
+
+    /// Describes the in and out points marked for the next comment.
+    fn marked_range(&self) -> String {
+        let rate = self.rate();
+        match (self.in_point, self.out_point) {
+            (None, None) => String::new(),
+            (Some(start), None) => format!("In {}", timecode::format_smpte(start, rate)),
+            (None, Some(end)) => format!("Out {}", timecode::format_smpte(end, rate)),
+            (Some(start), Some(end)) => format!(
+                "{} – {}",
+                timecode::format_smpte(start, rate),
+                timecode::format_smpte(end, rate)
+            ),
+        }
+    }
+
+    fn save(&self) {
+        if let Some(path) = &self.path {
+            review::store(path, &self.comments);
+        }
+    }
+
+    /// Rebuilds a row for every comment, with its text and color editable in place. The row
+    /// of a new comment at `added` is expanded so it can be typed right away.
+    fn render(&self, sender: &AsyncComponentSender<Self>, added: Option<usize>) {
+        let rate = self.rate();
+        let colors = Color::ALL.map(|color| color.label());
+        self.list.remove_all();
+        for (index, comment) in self.comments.iter().enumerate() {
+            let mut title = timecode::format_smpte(comment.start, rate);
+            if let Some(end) = comment.end {
+                title += &format!(" – {}", timecode::format_smpte(end, rate));
+            }
+            if !comment.author.is_empty() {
+                title += &format!(" · {}", comment.author);
+            }
+            let row = adw::ExpanderRow::builder()
+                .title(title)
+                .subtitle(comment.text.as_str())
+                // Comments are shown as typed rather than as markup
+                .use_markup(false)
+                .build();
+
+            let jump = gtk::Button::builder()
+                .icon_name("media-playback-start-symbolic")
+                .tooltip_text("Go to Comment")
+                .valign(gtk::Align::Center)
+                .css_classes(["flat"])
+                .build();
+            jump.connect_clicked(clone!(
+                #[strong]
+                sender,
+                move |_| sender.input(ReviewMsg::Jump(index))
+            ));
+            row.add_prefix(&jump);
+
+            let remove = gtk::Button::builder()
+                .icon_name("user-trash-symbolic")
+                .tooltip_text("Remove Comment")
+                .valign(gtk::Align::Center)
+                .css_classes(["flat"])
+                .build();
+            remove.connect_clicked(clone!(
+                #[strong]
+                sender,
+                move |_| sender.input(ReviewMsg::Remove(index))
+            ));
+            row.add_suffix(&remove);
+
+            let text = adw::EntryRow::builder()
+                .title("Comment")
+                .text(comment.text.as_str())
+                .build();
+            text.connect_changed(clone!(
+                #[strong]
+                sender,
+                #[weak]
+                row,
+                move |entry| {
+                    row.set_subtitle(&entry.text());
+                    sender.input(ReviewMsg::SetText(index, entry.text().into()));
+                }
+            ));
+            row.add_row(&text);
+
+            let color = adw::ComboRow::builder()
+                .title("Color")
+                .model(&gtk::StringList::new(&colors))
+                .selected(Color::ALL.iter().position(|c| *c == comment.color).unwrap() as u32)
+                .build();
+            color.connect_selected_notify(clone!(
+                #[strong]
+                sender,
+                move |combo| {
+                    if let Some(color) = Color::ALL.get(combo.selected() as usize) {
+                        sender.input(ReviewMsg::SetColor(index, *color));
+                    }
+                }
+            ));
+            row.add_row(&color);
+
+            self.list.append(&row);
+            if added == Some(index) {
+                row.set_expanded(true);
+                text.grab_focus();
+            }
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use std::path::PathBuf;
+
+use gtk::glib;
+
+use crate::decoder::{AudioEqualizer, ChannelMode, ChannelRouting, OutputLayout, SeekMode};
+use crate::loudness::Normalization;
+use crate::player::Volume;
+use crate::storage;
+use crate::visualizer::Visualization;
+
+const FILE: &str = "settings.ini";
+
+/// User preferences and window state restored between runs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settings {
+    pub width: i32,
+    pub height: i32,
+    pub maximized: bool,
+    pub fullscreen: bool,
+    /// Resize the main window to the video's display size when a file loads
+    pub auto_resize: bool,
+    pub media_info_width: i32,
+    pub media_info_height: i32,
+    /// Seconds skipped with Shift and Ctrl plus the arrow keys
+    pub short_seek: i64,
+    pub long_seek: i64,
+    /// Used for every seek except while dragging the seek bar, which always seeks to keyframes
+    pub seek_mode: SeekMode,
+    pub volume: Volume,
+    pub normalization: Normalization,
+    pub equalizer: AudioEqualizer,
+    pub routing: ChannelRouting,
+    /// Shown in place of the video for audio files
+    pub visualization: Visualization,
+    /// Folders scanned for the media library
+    pub library_folders: Vec<PathBuf>,
+    /// Custom video filter chain, in `-vf` syntax
+    pub video_filter: String,
+    /// Custom audio filter chain, in `-af` syntax
+    pub audio_filter: String,
+    /// Name signing review comments
+    pub review_author: String,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            width: 800,
+            height: 450,
+            maximized: false,
+            fullscreen: false,
+            auto_resize: false,
+            media_info_width: 600,
+            media_info_height: 450,
+            short_seek: 5,
+            long_seek: 60,
+            seek_mode: SeekMode::default(),
+            volume: Volume::default(),
+            normalization: Normalization::Off,
+            equalizer: AudioEqualizer::default(),
+            routing: ChannelRouting::default(),
+            visualization: Visualization::default(),
+            library_folders: Vec::new(),
+            video_filter: String::new(),
+            audio_filter: String::new(),
+            review_author: String::new(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn load() -> Self {
+        let file = storage::load(&storage::config_file(FILE));
+        let default = Self::default();
+        let int = |group, key, default: i32| file.integer(group, key).unwrap_or(default);
+        let boolean = |group, key, default: bool| file.boolean(group, key).unwrap_or(default);
+        let double = |group, key, default: f64| file.double(group, key).unwrap_or(default);
+        let string = |group, key| {
+            file.string(group, key)
+                .map(String::from)
+                .unwrap_or_default()
+        };
+
+        Self {
+            width: int("Window", "width", default.width),
+            height: int("Window", "height", default.height),
+            maximized: boolean("Window", "maximized", default.maximized),
+            fullscreen: boolean("Window", "fullscreen", default.fullscreen),
+            auto_resize: boolean("Window", "auto-resize", default.auto_resize),
+            media_info_width: int("MediaInfo", "width", default.media_info_width),
+            media_info_height: int("MediaInfo", "height", default.media_info_height),
+            short_seek: file
+                .int64("Playback", "short-seek")
+                .unwrap_or(default.short_seek),
+            long_seek: file
+                .int64("Playback", "long-seek")
+                .unwrap_or(default.long_seek),
+            seek_mode: SeekMode::parse(&string("Playback", "seek-mode")),
+            volume: Volume {
+                level: double("Audio", "volume", default.volume.level),
+                muted: boolean("Audio", "muted", default.volume.muted),
+                step: double("Audio", "volume-step", default.volume.step),
+                boost: boolean("Audio", "boost", default.volume.boost),
+            },
+            normalization: file
+                .string("Audio", "normalization")
+                .map_or(default.normalization, |id| Normalization::parse(&id)),
+            equalizer: AudioEqualizer {
+                enabled: boolean("Equalizer", "enabled", default.equalizer.enabled),
+                gains: file
+                    .double_list("Equalizer", "gains")
+                    .ok()
+                    .and_then(|gains| gains.as_slice().try_into().ok())
+                    .unwrap_or(default.equalizer.gains),
+                night_mode: boolean("Equalizer", "night-mode", default.equalizer.night_mode),
+            },
+            routing: ChannelRouting {
+                layout: OutputLayout::parse(&string("Audio", "layout")),
+                center_boost: double("Audio", "center-boost", default.routing.center_boost),
+                mode: ChannelMode::parse(&string("Audio", "channel-mode")),
+            },
+            visualization: file
+                .string("Audio", "visualizer")
+                .map_or(default.visualization, |id| Visualization::parse(&id)),
+            library_folders: file
+                .string_list("Library", "folders")
+                .map(|folders| folders.iter().map(PathBuf::from).collect())
+                .unwrap_or_default(),
+            video_filter: string("Filters", "video"),
+            audio_filter: string("Filters", "audio"),
+            review_author: string("Review", "author"),
+        }
+    }
+
+    pub fn save(&self) {
+        let file = glib::KeyFile::new();
+        file.set_integer("Window", "width", self.width);
+        file.set_integer("Window", "height", self.height);
+        file.set_boolean("Window", "maximized", self.maximized);
+        file.set_boolean("Window", "fullscreen", self.fullscreen);
+        file.set_boolean("Window", "auto-resize", self.auto_resize);
+        file.set_integer("MediaInfo", "width", self.media_info_width);
+        file.set_integer("MediaInfo", "height", self.media_info_height);
+        file.set_int64("Playback", "short-seek", self.short_seek);
+        file.set_int64("Playback", "long-seek", self.long_seek);
+        file.set_string("Playback", "seek-mode", self.seek_mode.id());
+        file.set_double("Audio", "volume", self.volume.level);
+        file.set_boolean("Audio", "muted", self.volume.muted);
+        file.set_double("Audio", "volume-step", self.volume.step);
+        file.set_boolean("Audio", "boost", self.volume.boost);
+        file.set_string("Audio", "normalization", &self.normalization.id());
+        file.set_string("Audio", "layout", self.routing.layout.id());
+        file.set_double("Audio", "center-boost", self.routing.center_boost);
+        file.set_string("Audio", "channel-mode", self.routing.mode.id());
+        file.set_string("Audio", "visualizer", self.visualization.id());
+        file.set_boolean("Equalizer", "enabled", self.equalizer.enabled);
+        file.set_double_list("Equalizer", "gains", &self.equalizer.gains);
+        file.set_boolean("Equalizer", "night-mode", self.equalizer.night_mode);
+        let folders = self
+            .library_folders
+            .iter()
+            .map(|f| f.to_string_lossy())
+            .collect::<Vec<_>>();
+        file.set_string_list(
+            "Library",
+            "folders",
+            &folders.iter().map(|f| f.as_ref()).collect::<Vec<_>>(),
+        );
+        file.set_string("Filters", "video", &self.video_filter);
+        file.set_string("Filters", "audio", &self.audio_filter);
+        file.set_string("Review", "author", &self.review_author);
+        storage::save(&file, &storage::config_file(FILE));
+    }
+}
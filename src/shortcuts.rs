@@ -69,7 +69,84 @@ impl SimpleComponent for Shortcuts {
         );
         player.append(
             &gtk::ShortcutsShortcut::builder()
-                .title("Volume +10%")
+                .title("Seek Short Step")
+                .name("seekshort")
+                .accelerator("<Shift>Left <Shift>Right")
+                .build(),
+        );
+        player.append(
+            &gtk::ShortcutsShortcut::builder()
+                .title("Seek Long Step")
+                .name("seeklong")
+                .accelerator("<Ctrl>Left <Ctrl>Right")
+                .build(),
+        );
+        player.append(
+            &gtk::ShortcutsShortcut::builder()
+                .title("Jump to 0–90%")
+                .name("seekpercent")
+                .accelerator("0...9")
+                .build(),
+        );
+        player.append(
+            &gtk::ShortcutsShortcut::builder()
+                .title("Go to Time")
+                .name("goto")
+                .action_name("goto")
+                .accelerator("<Ctrl>G")
+                .build(),
+        );
+        player.append(
+            &gtk::ShortcutsShortcut::builder()
+                .title("Add Bookmark")
+                .name("addbookmark")
+                .action_name("addbookmark")
+                .accelerator("<Ctrl>B")
+                .build(),
+        );
+        player.append(
+            &gtk::ShortcutsShortcut::builder()
+                .title("Next Bookmark")
+                .name("nextbookmark")
+                .action_name("nextbookmark")
+                .accelerator("bracketright")
+                .build(),
+        );
+        player.append(
+            &gtk::ShortcutsShortcut::builder()
+                .title("Previous Bookmark")
+                .name("previousbookmark")
+                .action_name("previousbookmark")
+                .accelerator("bracketleft")
+                .build(),
+        );
+        player.append(
+            &gtk::ShortcutsShortcut::builder()
+                .title("Mark In")
+                .name("markin")
+                .action_name("markin")
+                .accelerator("i")
+                .build(),
+        );
+        player.append(
+            &gtk::ShortcutsShortcut::builder()
+                .title("Mark Out")
+                .name("markout")
+                .action_name("markout")
+                .accelerator("o")
+                .build(),
+        );
+        player.append(
+            &gtk::ShortcutsShortcut::builder()
+                .title("Add Comment")
+                .name("addcomment")
+                .action_name("addcomment")
+                .accelerator("<Ctrl>M")
+                .build(),
+        );
+        player.append(
+            &gtk::ShortcutsShortcut::builder()
+                .title("Volume Up")
                 .name("volumeup")
                 .action_name("volumeup")
                 .accelerator("Up")
@@ -77,12 +154,136 @@ impl SimpleComponent for Shortcuts {
         );
         player.append(
             &gtk::ShortcutsShortcut::builder()
-                .title("Volume -10%")
+                .title("Volume Down")
                 .name("volumedown")
                 .action_name("volumedown")
                 .accelerator("Down")
                 .build(),
         );
+        player.append(
+            &gtk::ShortcutsShortcut::builder()
+                .title("Mute")
+                .name("mute")
+                .action_name("mute")
+                .accelerator("m")
+                .build(),
+        );
+
+        let video = gtk::ShortcutsGroup::builder()
+            .title("Video")
+            .name("video")
+            .build();
+
+        video.append(
+            &gtk::ShortcutsShortcut::builder()
+                .title("Zoom In")
+                .name("zoomin")
+                .action_name("zoomin")
+                .accelerator("<Ctrl>plus")
+                .build(),
+        );
+        video.append(
+            &gtk::ShortcutsShortcut::builder()
+                .title("Zoom Out")
+                .name("zoomout")
+                .action_name("zoomout")
+                .accelerator("<Ctrl>minus")
+                .build(),
+        );
+        video.append(
+            &gtk::ShortcutsShortcut::builder()
+                .title("Reset Zoom")
+                .name("zoomreset")
+                .action_name("zoomreset")
+                .accelerator("<Ctrl>0")
+                .build(),
+        );
+        video.append(
+            &gtk::ShortcutsShortcut::builder()
+                .title("Pan Left")
+                .name("panleft")
+                .action_name("panleft")
+                .accelerator("<Alt>Left")
+                .build(),
+        );
+        video.append(
+            &gtk::ShortcutsShortcut::builder()
+                .title("Pan Right")
+                .name("panright")
+                .action_name("panright")
+                .accelerator("<Alt>Right")
+                .build(),
+        );
+        video.append(
+            &gtk::ShortcutsShortcut::builder()
+                .title("Pan Up")
+                .name("panup")
+                .action_name("panup")
+                .accelerator("<Alt>Up")
+                .build(),
+        );
+        video.append(
+            &gtk::ShortcutsShortcut::builder()
+                .title("Pan Down")
+                .name("pandown")
+                .action_name("pandown")
+                .accelerator("<Alt>Down")
+                .build(),
+        );
+        video.append(
+            &gtk::ShortcutsShortcut::builder()
+                .title("Rotate 90°")
+                .name("rotate90")
+                .action_name("rotate90")
+                .accelerator("<Ctrl>R")
+                .build(),
+        );
+        video.append(
+            &gtk::ShortcutsShortcut::builder()
+                .title("Rotate 270°")
+                .name("rotate270")
+                .action_name("rotate270")
+                .accelerator("<Ctrl><Shift>R")
+                .build(),
+        );
+
+        let sync = gtk::ShortcutsGroup::builder()
+            .title("Synchronization")
+            .name("sync")
+            .build();
+
+        sync.append(
+            &gtk::ShortcutsShortcut::builder()
+                .title("Audio Delay +50 ms")
+                .name("audiodelayup")
+                .action_name("audiodelayup")
+                .accelerator("k")
+                .build(),
+        );
+        sync.append(
+            &gtk::ShortcutsShortcut::builder()
+                .title("Audio Delay -50 ms")
+                .name("audiodelaydown")
+                .action_name("audiodelaydown")
+                .accelerator("j")
+                .build(),
+        );
+        sync.append(
+            &gtk::ShortcutsShortcut::builder()
+                .title("Subtitle Delay +50 ms")
+                .name("subtitledelayup")
+                .action_name("subtitledelayup")
+                .accelerator("h")
+                .build(),
+        );
+        sync.append(
+            &gtk::ShortcutsShortcut::builder()
+                .title("Subtitle Delay -50 ms")
+                .name("subtitledelaydown")
+                .action_name("subtitledelaydown")
+                .accelerator("g")
+                .build(),
+        );
 
         let general = gtk::ShortcutsGroup::builder()
             .title("General")
@@ -97,6 +298,30 @@ impl SimpleComponent for Shortcuts {
                 .accelerator("<Ctrl>O")
                 .build(),
         );
+        general.append(
+            &gtk::ShortcutsShortcut::builder()
+                .title("Open URL")
+                .name("openurl")
+                .action_name("openurl")
+                .accelerator("<Ctrl>L")
+                .build(),
+        );
+        general.append(
+            &gtk::ShortcutsShortcut::builder()
+                .title("Open Path or URL from Clipboard")
+                .name("paste")
+                .action_name("paste")
+                .accelerator("<Ctrl>V")
+                .build(),
+        );
+        general.append(
+            &gtk::ShortcutsShortcut::builder()
+                .title("Library")
+                .name("library")
+                .action_name("library")
+                .accelerator("<Ctrl><Shift>L")
+                .build(),
+        );
         general.append(
             &gtk::ShortcutsShortcut::builder()
                 .title("Media Info")
@@ -105,6 +330,30 @@ impl SimpleComponent for Shortcuts {
                 .accelerator("<Ctrl>I")
                 .build(),
         );
+        general.append(
+            &gtk::ShortcutsShortcut::builder()
+                .title("Bookmarks")
+                .name("bookmarks")
+                .action_name("bookmarks")
+                .accelerator("<Ctrl><Shift>B")
+                .build(),
+        );
+        general.append(
+            &gtk::ShortcutsShortcut::builder()
+                .title("Review")
+                .name("review")
+                .action_name("review")
+                .accelerator("<Ctrl>E")
+                .build(),
+        );
+        general.append(
+            &gtk::ShortcutsShortcut::builder()
+                .title("Chapters")
+                .name("chapters")
+                .action_name("chapters")
+                .accelerator("<Ctrl><Shift>C")
+                .build(),
+        );
         general.append(
             &gtk::ShortcutsShortcut::builder()
                 .title("About")
@@ -136,6 +385,8 @@ impl SimpleComponent for Shortcuts {
             .build();
 
         container.append(&player);
+        container.append(&video);
+        container.append(&sync);
         container.append(&general);
 
         root.set_child(Some(&container));
@@ -0,0 +1,72 @@
+use std::path::{Path, PathBuf};
+
+use gtk::glib;
+
+const APP_DIR: &str = "simplevideoplayer";
+
+fn ensure_dir(dir: PathBuf) -> PathBuf {
+    if let Err(e) = std::fs::create_dir_all(&dir) {
+        eprintln!("Error: Could not create {} ({e})", dir.display());
+    }
+    dir
+}
+
+/// Returns the path of `name` in the user config directory, creating the directory if needed.
+pub fn config_file(name: &str) -> PathBuf {
+    ensure_dir(glib::user_config_dir().join(APP_DIR)).join(name)
+}
+
+/// Returns the path of `name` in the user data directory, creating the directory if needed.
+pub fn data_file(name: &str) -> PathBuf {
+    ensure_dir(glib::user_data_dir().join(APP_DIR)).join(name)
+}
+
+/// Returns the cache subdirectory `name`, creating it if needed.
+pub fn cache_dir(name: &str) -> PathBuf {
+    ensure_dir(glib::user_cache_dir().join(APP_DIR).join(name))
+}
+
+/// Returns a name for `location` that stays the same across runs and builds, for per-file
+/// groups and cache files. This is the 64-bit FNV-1a hash of the path.
+pub fn key_for(location: &Path) -> String {
+    let hash = location
+        .as_os_str()
+        .as_encoded_bytes()
+        .iter()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+        });
+    format!("{hash:016x}")
+}
+
+/// Loads a key file, returning an empty one if it does not exist yet.
+pub fn load(path: &Path) -> glib::KeyFile {
+    let file = glib::KeyFile::new();
+    if path.exists() {
+        if let Err(e) = file.load_from_file(path, glib::KeyFileFlags::NONE) {
+            eprintln!("Error: Could not load {} ({e})", path.display());
+        }
+    }
+    file
+}
+
+pub fn save(file: &glib::KeyFile, path: &Path) {
+    if let Err(e) = file.save_to_file(path) {
+        eprintln!("Error: Could not save {} ({e})", path.display());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_are_stable() {
+        assert_eq!(key_for(Path::new("")), "cbf29ce484222325");
+        assert_eq!(key_for(Path::new("a")), "af63dc4c8601ec8c");
+        assert_ne!(
+            key_for(Path::new("/a/b.mkv")),
+            key_for(Path::new("/a/c.mkv"))
+        );
+    }
+}
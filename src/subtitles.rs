@@ -0,0 +1,88 @@
+use std::path::Path;
+
+#[derive(Debug)]
+struct Cue {
+    start: i64,
+    end: i64,
+    text: String,
+}
+
+/// A parsed SubRip or WebVTT subtitle track, with cue times in microseconds.
+#[derive(Debug, Default)]
+pub struct Subtitles {
+    cues: Vec<Cue>,
+}
+
+/// Parses `[HH:]MM:SS(,|.)mmm` into microseconds.
+fn parse_timestamp(s: &str) -> Option<i64> {
+    let (clock, millis) = s.trim().split_once([',', '.'])?;
+    let mut seconds = 0;
+    for part in clock.split(':') {
+        seconds = seconds * 60 + part.parse::<i64>().ok()?;
+    }
+    Some(seconds * 1_000_000 + millis.parse::<i64>().ok()? * 1_000)
+}
+
+/// Removes markup such as `<i>` and `{\an8}` from a cue line.
+fn strip_tags(line: &str) -> String {
+    let mut text = String::with_capacity(line.len());
+    let mut depth = 0;
+    for c in line.chars() {
+        match c {
+            '<' | '{' => depth += 1,
+            '>' | '}' if depth > 0 => depth -= 1,
+            _ if depth == 0 => text.push(c),
+            _ => {}
+        }
+    }
+    text
+}
+
+impl Subtitles {
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        Ok(Self::parse(&std::fs::read_to_string(path)?))
+    }
+
+    pub fn parse(data: &str) -> Self {
+        let mut cues = Vec::new();
+        let data = data.replace("\r\n", "\n");
+
+        for block in data.split("\n\n") {
+            let mut lines = block.lines().skip_while(|l| !l.contains("-->"));
+            let Some(timing) = lines.next() else {
+                continue;
+            };
+            let Some((start, end)) = timing.split_once("-->") else {
+                continue;
+            };
+            // WebVTT allows cue settings after the end timestamp
+            let end = end.split_whitespace().next().unwrap_or_default();
+            let (Some(start), Some(end)) = (parse_timestamp(start), parse_timestamp(end)) else {
+                continue;
+            };
+
+            let text = lines.map(strip_tags).collect::<Vec<_>>().join("\n");
+            cues.push(Cue { start, end, text });
+        }
+
+        cues.sort_by_key(|c| c.start);
+        Self { cues }
+    }
+
+    /// Returns the text shown at `timestamp` microseconds, if any.
+    pub fn text_at(&self, timestamp: i64) -> Option<String> {
+        let text = self
+            .cues
+            .iter()
+            .take_while(|c| c.start <= timestamp)
+            .filter(|c| timestamp < c.end)
+            .map(|c| c.text.as_str())
+            .collect::<Vec<_>>();
+
+        if text.is_empty() {
+            None
+        } else {
+            Some(text.join("\n"))
+        }
+    }
+}
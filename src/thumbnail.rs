@@ -0,0 +1,101 @@
+use std::path::{Path, PathBuf};
+
+use ffmpeg_next::{codec, format, frame, media, software::scaling};
+use gtk::{gdk, glib, prelude::*};
+
+use crate::storage;
+
+const WIDTH: u32 = 160;
+
+/// A small RGBA still taken from a video.
+#[derive(Debug)]
+pub struct Thumbnail {
+    width: u32,
+    height: u32,
+    stride: usize,
+    data: Vec<u8>,
+}
+
+/// Returns where the thumbnail for `location` is cached.
+pub fn path_for(location: &Path) -> PathBuf {
+    storage::cache_dir("thumbnails").join(format!("{}.png", storage::key_for(location)))
+}
+
+/// Decodes the first frame after a tenth of the duration. This blocks, so it should be run
+/// off the main thread.
+pub fn capture(location: &Path) -> Option<Thumbnail> {
+    let mut input = format::input(location)
+        .inspect_err(|e| eprintln!("Error: Could not open {} ({e})", location.display()))
+        .ok()?;
+
+    let (index, parameters) = {
+        let stream = input.streams().best(media::Type::Video)?;
+        (stream.index(), stream.parameters())
+    };
+    let mut decoder = codec::Context::from_parameters(parameters)
+        .and_then(|c| c.decoder().video())
+        .inspect_err(|e| eprintln!("Error: Could not open video decoder ({e})"))
+        .ok()?;
+
+    let duration = input.duration();
+    if duration > 0 {
+        let target = duration / 10;
+        let _ = input.seek(target, ..target);
+    }
+
+    let mut decoded = frame::Video::empty();
+    for (stream, packet) in input.packets() {
+        if stream.index() == index
+            && decoder.send_packet(&packet).is_ok()
+            && decoder.receive_frame(&mut decoded).is_ok()
+        {
+            return scale(&decoded);
+        }
+    }
+
+    decoder.send_eof().ok()?;
+    decoder.receive_frame(&mut decoded).ok()?;
+    scale(&decoded)
+}
+
+fn scale(frame: &frame::Video) -> Option<Thumbnail> {
+    let width = WIDTH.min(frame.width());
+    let height = (frame.height() * width / frame.width()).max(1);
+
+    let mut scaler = scaling::Context::get(
+        frame.format(),
+        frame.width(),
+        frame.height(),
+        format::Pixel::RGBA,
+        width,
+        height,
+        scaling::Flags::BILINEAR,
+    )
+    .ok()?;
+    let mut rgba = frame::Video::empty();
+    scaler.run(frame, &mut rgba).ok()?;
+
+    Some(Thumbnail {
+        width,
+        height,
+        stride: rgba.stride(0),
+        data: rgba.data(0).to_vec(),
+    })
+}
+
+impl Thumbnail {
+    /// Writes the thumbnail as a PNG. Textures can only be created on the main thread.
+    pub fn save(self, path: &Path) -> bool {
+        let texture = gdk::MemoryTexture::new(
+            self.width as i32,
+            self.height as i32,
+            gdk::MemoryFormat::R8g8b8a8,
+            &glib::Bytes::from_owned(self.data),
+            self.stride,
+        );
+        texture
+            .save_to_png(path)
+            .inspect_err(|e| eprintln!("Error: Could not save thumbnail ({e})"))
+            .is_ok()
+    }
+}
@@ -0,0 +1,390 @@
+/// Microseconds per second, the unit of all positions and durations
+const SECOND: i64 = 1_000_000;
+/// Largest position `parse` and imports accept either way, a million hours, so typed and
+/// imported values stay far from the limits of i64
+pub const MAX_POSITION: i64 = 1_000_000 * 3600 * SECOND;
+
+/// Frame rate of a video as a fraction, such as 30000/1001 for 29.97 fps.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameRate {
+    numerator: i64,
+    denominator: i64,
+}
+
+impl FrameRate {
+    /// Returns `None` unless both parts are positive, as for streams without a known rate.
+    pub fn new(numerator: i64, denominator: i64) -> Option<Self> {
+        (numerator > 0 && denominator > 0).then_some(Self {
+            numerator,
+            denominator,
+        })
+    }
+
+    pub fn fps(&self) -> f64 {
+        self.numerator as f64 / self.denominator as f64
+    }
+
+    /// Frames counted per second of timecode, such as 30 for 29.97 fps.
+    pub fn nominal(&self) -> i64 {
+        (self.numerator + self.denominator - 1) / self.denominator
+    }
+
+    /// Frame numbers skipped at the start of every minute except each tenth, so timecode at
+    /// 29.97 and 59.94 fps keeps up with the clock. Other rates count every frame.
+    fn dropped(&self) -> i64 {
+        match (self.numerator, self.denominator) {
+            (30000 | 60000, 1001) => self.nominal() / 15,
+            _ => 0,
+        }
+    }
+
+    /// Whether this is an NTSC rate, such as 23.976 or 29.97 fps, which runs 0.1% slower than
+    /// its nominal rate.
+    pub fn is_ntsc(&self) -> bool {
+        self.denominator == 1001
+    }
+
+    pub fn is_drop_frame(&self) -> bool {
+        self.dropped() > 0
+    }
+
+    /// Returns the number of the frame shown at `position`. Timestamps are truncated to whole
+    /// microseconds, so this rounds to the nearest frame rather than down.
+    pub fn frame_at(&self, position: i64) -> i64 {
+        let scale = i128::from(self.denominator) * i128::from(SECOND);
+        let frames = i128::from(position) * i128::from(self.numerator);
+        saturate((frames + scale / 2).div_euclid(scale))
+    }
+
+    /// Returns the position where frame `frame` starts.
+    pub fn frame_start(&self, frame: i64) -> i64 {
+        let micros = i128::from(frame) * i128::from(self.denominator) * i128::from(SECOND);
+        saturate(micros.div_euclid(i128::from(self.numerator)))
+    }
+}
+
+/// Converts to i64, clamping values out of its range, as rates with huge parts can produce.
+fn saturate(value: i128) -> i64 {
+    i64::try_from(value).unwrap_or(if value < 0 { i64::MIN } else { i64::MAX })
+}
+
+/// Splits the magnitude of a position into hours, minutes, seconds and microseconds. This is
+/// unsigned, as the magnitude of `i64::MIN` does not fit in an i64.
+fn split(magnitude: u64) -> (u64, u64, u64, u64) {
+    let second = SECOND as u64;
+    let seconds = magnitude / second;
+    (
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60,
+        magnitude % second,
+    )
+}
+
+/// Formats a position as H:MM:SS, or M:SS below an hour, for the OSD and menus.
+pub fn format_clock(position: i64) -> String {
+    let sign = if position < 0 { "-" } else { "" };
+    let (hours, minutes, seconds, _) = split(position.unsigned_abs());
+    if hours > 0 {
+        format!("{sign}{hours}:{minutes:02}:{seconds:02}")
+    } else {
+        format!("{sign}{minutes}:{seconds:02}")
+    }
+}
+
+/// Formats a position as HH:MM:SS.mmm, with hours going past 99 for very long streams.
+pub fn format_precise(position: i64) -> String {
+    // Round to the millisecond first, so 59.9996 s carries into the next minute
+    let rounded = (position.unsigned_abs() + 500) / 1000 * 1000;
+    let (hours, minutes, seconds, micros) = split(rounded);
+    let sign = if position < 0 && rounded > 0 { "-" } else { "" };
+    format!(
+        "{sign}{hours:02}:{minutes:02}:{seconds:02}.{:03}",
+        micros / 1000
+    )
+}
+
+/// Formats a position as seconds with three decimals, such as 3723.500.
+pub fn format_seconds(position: i64) -> String {
+    let milliseconds = (position.unsigned_abs() + 500) / 1000;
+    let sign = if position < 0 && milliseconds > 0 {
+        "-"
+    } else {
+        ""
+    };
+    format!("{sign}{}.{:03}", milliseconds / 1000, milliseconds % 1000)
+}
+
+/// Formats a position as the number of the frame shown there.
+pub fn format_frames(position: i64, rate: FrameRate) -> String {
+    rate.frame_at(position).to_string()
+}
+
+/// Formats a position as SMPTE timecode, HH:MM:SS:FF, or HH:MM:SS;FF with drop-frame counting
+/// at 29.97 and 59.94 fps.
+pub fn format_smpte(position: i64, rate: FrameRate) -> String {
+    let frame = rate.frame_at(position);
+    let sign = if frame < 0 { "-" } else { "" };
+    // Unsigned, so the magnitude of i64::MIN fits and adding back dropped labels cannot
+    // overflow, as they are fewer than a thousandth of the frames
+    let mut frame = frame.unsigned_abs();
+    let nominal = rate.nominal() as u64;
+    let dropped = rate.dropped() as u64;
+
+    if dropped > 0 {
+        // Add back the labels skipped so far, so the count can be split like non-drop timecode
+        let per_minute = nominal * 60 - dropped;
+        let per_ten_minutes = per_minute * 10 + dropped;
+        let (tens, rest) = (frame / per_ten_minutes, frame % per_ten_minutes);
+        frame += dropped * 9 * tens;
+        if rest > dropped {
+            frame += dropped * ((rest - dropped) / per_minute);
+        }
+    }
+
+    let separator = if dropped > 0 { ';' } else { ':' };
+    let seconds = frame / nominal;
+    format!(
+        "{sign}{:02}:{:02}:{:02}{separator}{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60,
+        frame % nominal
+    )
+}
+
+/// Parses whole and fractional seconds, such as 12 or 12.25, into microseconds.
+fn parse_seconds(text: &str) -> Option<i64> {
+    let (whole, fraction) = text.split_once('.').unwrap_or((text, ""));
+    let digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    if (whole.is_empty() && fraction.is_empty()) || !digits(whole) || !digits(fraction) {
+        return None;
+    }
+    let whole = if whole.is_empty() {
+        0
+    } else {
+        whole.parse::<i64>().ok()?
+    };
+    // Digits past the microsecond are ignored
+    let fraction = format!("{:0<6}", &fraction[..fraction.len().min(6)]);
+    whole
+        .checked_mul(SECOND)?
+        .checked_add(fraction.parse::<i64>().ok()?)
+}
+
+fn parse_number(text: &str) -> Option<i64> {
+    if text.is_empty() || !text.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    text.parse().ok()
+}
+
+/// Parses HH:MM:SS:FF or HH:MM:SS;FF into the number of the frame it labels. Labels skipped
+/// by drop-frame counting are rejected.
+fn parse_smpte(parts: &[&str], rate: FrameRate) -> Option<i64> {
+    let [hours, minutes, seconds, frames] = parts else {
+        return None;
+    };
+    let (hours, minutes, seconds, frames) = (
+        parse_number(hours)?,
+        parse_number(minutes)?,
+        parse_number(seconds)?,
+        parse_number(frames)?,
+    );
+    let nominal = rate.nominal();
+    let dropped = rate.dropped();
+    if minutes >= 60 || seconds >= 60 || frames >= nominal {
+        return None;
+    }
+    if dropped > 0 && seconds == 0 && minutes % 10 != 0 && frames < dropped {
+        return None;
+    }
+    let total_minutes = hours.checked_mul(60)?.checked_add(minutes)?;
+    let frame = total_minutes
+        .checked_mul(60)?
+        .checked_add(seconds)?
+        .checked_mul(nominal)?
+        .checked_add(frames)?;
+    frame.checked_sub(dropped.checked_mul(total_minutes - total_minutes / 10)?)
+}
+
+/// Parses a position typed by the user, in any format the functions above write:
+///
+/// - `[[H:]M:]S[.fff]`, such as `1:02:03.5`, `62:03` or `3723.5`, optionally ending in `s`
+/// - SMPTE timecode, `HH:MM:SS:FF` or `HH:MM:SS;FF`, which needs the frame rate
+/// - a frame number ending in `f`, such as `1500f`, which needs the frame rate
+///
+/// A leading `-` makes the position negative, for relative seeks. Positions beyond a million
+/// hours either way are rejected.
+pub fn parse(text: &str, rate: Option<FrameRate>) -> Option<i64> {
+    parse_unbounded(text, rate).filter(|position| position.abs() <= MAX_POSITION)
+}
+
+fn parse_unbounded(text: &str, rate: Option<FrameRate>) -> Option<i64> {
+    let text = text.trim();
+    let (sign, text) = match text.strip_prefix('-') {
+        Some(rest) => (-1, rest.trim_start()),
+        None => (1, text),
+    };
+
+    if let Some(frames) = text.strip_suffix(['f', 'F']) {
+        let rate = rate?;
+        return Some(sign * rate.frame_start(parse_number(frames.trim_end())?));
+    }
+    if let Some(seconds) = text.strip_suffix(['s', 'S']) {
+        return Some(sign * parse_seconds(seconds.trim_end())?);
+    }
+
+    let parts = text.split([':', ';']).collect::<Vec<_>>();
+    if parts.len() == 4 {
+        let rate = rate?;
+        return Some(sign * rate.frame_start(parse_smpte(&parts, rate)?));
+    }
+    if text.contains(';') || parts.len() > 3 {
+        return None;
+    }
+
+    // The last part holds seconds and any fraction, the ones before it minutes and hours
+    let (last, units) = parts.split_last()?;
+    let mut position = parse_seconds(last)?;
+    if !units.is_empty() && position >= 60 * SECOND {
+        return None;
+    }
+    for (unit, part) in units.iter().rev().enumerate() {
+        let value = parse_number(part)?;
+        // Minutes are limited to 59 only when hours are given
+        if unit == 0 && units.len() == 2 && value >= 60 {
+            return None;
+        }
+        let scale = if unit == 0 {
+            60 * SECOND
+        } else {
+            3600 * SECOND
+        };
+        position = position.checked_add(value.checked_mul(scale)?)?;
+    }
+    Some(sign * position)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame_rate(numerator: i64, denominator: i64) -> FrameRate {
+        FrameRate::new(numerator, denominator).unwrap()
+    }
+
+    #[test]
+    fn smpte_round_trips() {
+        for rate in [
+            frame_rate(24000, 1001),
+            frame_rate(25, 1),
+            frame_rate(30000, 1001),
+            frame_rate(60000, 1001),
+        ] {
+            for frame in (0..40_000)
+                .step_by(7)
+                .chain([1798, 1799, 1800, 17981, 17982])
+            {
+                let position = rate.frame_start(frame);
+                let text = format_smpte(position, rate);
+                assert_eq!(
+                    parse(&text, Some(rate)),
+                    Some(position),
+                    "{text} at {rate:?}"
+                );
+                assert_eq!(rate.frame_at(position), frame);
+            }
+        }
+    }
+
+    #[test]
+    fn drop_frame_boundaries() {
+        let rate = frame_rate(30000, 1001);
+        let last = parse("00:00:59;29", Some(rate)).unwrap();
+        assert_eq!(rate.frame_at(last), 1799);
+        let next = rate.frame_start(rate.frame_at(last) + 1);
+        assert_eq!(format_smpte(next, rate), "00:01:00;02");
+        assert_eq!(parse("00:01:00;00", Some(rate)), None);
+        assert_eq!(parse("00:01:00;01", Some(rate)), None);
+        let tenth = parse("00:10:00;00", Some(rate)).unwrap();
+        assert_eq!(rate.frame_at(tenth), 17982);
+        assert_eq!(format_smpte(tenth, rate), "00:10:00;00");
+
+        let rate = frame_rate(60000, 1001);
+        assert_eq!(parse("00:01:00;03", Some(rate)), None);
+        assert!(parse("00:01:00;04", Some(rate)).is_some());
+    }
+
+    #[test]
+    fn negative_positions() {
+        let rate = frame_rate(25, 1);
+        assert_eq!(format_clock(-90_000_000), "-1:30");
+        assert_eq!(format_precise(-1_500_000), "-00:00:01.500");
+        assert_eq!(format_precise(-400), "00:00:00.000");
+        assert_eq!(format_seconds(-2_250_000), "-2.250");
+        assert_eq!(format_smpte(-1_000_000, rate), "-00:00:01:00");
+        assert_eq!(parse("-5", None), Some(-5_000_000));
+        assert_eq!(parse("-1:30", None), Some(-90_000_000));
+        assert_eq!(parse("-25f", Some(rate)), Some(-1_000_000));
+    }
+
+    #[test]
+    fn precise_carries_into_next_minute() {
+        assert_eq!(format_precise(59_999_600), "00:01:00.000");
+        assert_eq!(format_precise(59_999_400), "00:00:59.999");
+        assert_eq!(format_precise(3_599_999_999), "01:00:00.000");
+    }
+
+    #[test]
+    fn parses_frames_seconds_and_clock() {
+        let rate = frame_rate(25, 1);
+        assert_eq!(parse("1500f", Some(rate)), Some(60_000_000));
+        assert_eq!(parse("1500f", None), None);
+        assert_eq!(parse("3723.5s", None), Some(3_723_500_000));
+        assert_eq!(parse("62:03", None), Some(3_723_000_000));
+        assert_eq!(parse("1:02:03", None), Some(3_723_000_000));
+        assert_eq!(parse("1:62:03", None), None);
+        assert_eq!(parse("00:00:01:00", None), None);
+    }
+
+    #[test]
+    fn huge_values_do_not_overflow() {
+        let rate = frame_rate(30000, 1001);
+        assert_eq!(parse("99999999999999999:00:00;00", Some(rate)), None);
+        assert_eq!(parse("9223372036854775807f", Some(rate)), None);
+        assert_eq!(parse("-9223372036854775807f", Some(rate)), None);
+        assert_eq!(parse("1000000:00:00", None), Some(MAX_POSITION));
+        assert_eq!(parse("1000000:00:00.000001", None), None);
+        assert_eq!(rate.frame_start(i64::MIN), i64::MIN);
+        let slow = FrameRate::new(1, 1_000_000).unwrap();
+        assert_eq!(slow.frame_start(i64::MAX), i64::MAX);
+        assert_eq!(slow.frame_start(i64::MIN), i64::MIN);
+        let fast = FrameRate::new(i64::from(i32::MAX), 1).unwrap();
+        assert_eq!(fast.frame_at(i64::MAX), i64::MAX);
+    }
+
+    #[test]
+    fn formats_the_limits_of_i64() {
+        let rate = frame_rate(30000, 1001);
+        assert_eq!(format_clock(i64::MAX), "2562047788:00:54");
+        assert_eq!(format_clock(i64::MIN), "-2562047788:00:54");
+        assert_eq!(format_precise(i64::MAX), "2562047788:00:54.776");
+        assert_eq!(format_precise(i64::MIN), "-2562047788:00:54.776");
+        assert_eq!(format_seconds(i64::MAX), "9223372036854.776");
+        assert_eq!(format_seconds(i64::MIN), "-9223372036854.776");
+        assert_eq!(
+            format_smpte(i64::MAX, frame_rate(25, 1)),
+            "2562047788:00:54:19"
+        );
+        assert_eq!(
+            format_smpte(i64::MIN, frame_rate(25, 1)),
+            "-2562047788:00:54:19"
+        );
+        assert_eq!(format_smpte(i64::MAX, rate), "2562050350:03:56;00");
+        assert_eq!(format_smpte(i64::MIN, rate), "-2562050350:03:56;00");
+        let largest = parse("1000000:00:00", None).unwrap();
+        assert_eq!(format_precise(largest), "1000000:00:00.000");
+        assert_eq!(format_precise(-largest), "-1000000:00:00.000");
+    }
+}
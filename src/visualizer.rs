@@ -0,0 +1,138 @@
+use std::f32::consts::PI;
+
+use gtk::cairo;
+
+/// Number of bars in the spectrum
+const BANDS: usize = 48;
+/// Lowest and highest band frequencies in Hz
+const LOWEST: f32 = 40.0;
+const HIGHEST: f32 = 16_000.0;
+/// Samples analysed per frame
+const WINDOW: usize = 1024;
+/// Quietest level shown, in dB
+const FLOOR: f32 = -60.0;
+/// Fraction of its height a bar keeps per frame when the level drops
+const DECAY: f32 = 0.85;
+const GAP: f64 = 2.0;
+
+/// What is drawn in place of the video for audio files.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Visualization {
+    Off,
+    #[default]
+    Spectrum,
+    Waveform,
+}
+
+impl Visualization {
+    pub fn parse(id: &str) -> Self {
+        match id {
+            "off" => Self::Off,
+            "waveform" => Self::Waveform,
+            _ => Self::Spectrum,
+        }
+    }
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::Spectrum => "spectrum",
+            Self::Waveform => "waveform",
+        }
+    }
+}
+
+/// Draws the audio that was just played, keeping bar heights between frames so they fall
+/// smoothly.
+#[derive(Debug, Default)]
+pub struct Visualizer {
+    levels: Vec<f32>,
+}
+
+impl Visualizer {
+    pub fn draw(
+        &mut self,
+        cr: &cairo::Context,
+        visualization: Visualization,
+        (samples, rate): (&[f32], u32),
+        width: f64,
+        height: f64,
+    ) {
+        cr.set_source_rgba(0.21, 0.52, 0.89, 0.9);
+        match visualization {
+            Visualization::Off => {}
+            Visualization::Spectrum => self.draw_spectrum(cr, samples, rate, width, height),
+            Visualization::Waveform => draw_waveform(cr, samples, width, height),
+        }
+    }
+
+    fn draw_spectrum(
+        &mut self,
+        cr: &cairo::Context,
+        samples: &[f32],
+        rate: u32,
+        width: f64,
+        height: f64,
+    ) {
+        let spectrum = spectrum(samples, rate);
+        self.levels.resize(BANDS, 0.0);
+        for (level, target) in self.levels.iter_mut().zip(spectrum) {
+            *level = target.max(*level * DECAY);
+        }
+
+        let bar = width / BANDS as f64;
+        for (i, level) in self.levels.iter().enumerate() {
+            let bar_height = (*level as f64 * height).max(1.0);
+            cr.rectangle(
+                i as f64 * bar + GAP / 2.0,
+                height - bar_height,
+                (bar - GAP).max(1.0),
+                bar_height,
+            );
+        }
+        let _ = cr.fill();
+    }
+}
+
+fn draw_waveform(cr: &cairo::Context, samples: &[f32], width: f64, height: f64) {
+    let samples = &samples[samples.len().saturating_sub(WINDOW)..];
+    let middle = height / 2.0;
+    cr.move_to(0.0, middle);
+    for (i, sample) in samples.iter().enumerate() {
+        let x = i as f64 / samples.len().max(2).saturating_sub(1) as f64 * width;
+        cr.line_to(x, middle - sample.clamp(-1.0, 1.0) as f64 * middle);
+    }
+    cr.set_line_width(2.0);
+    let _ = cr.stroke();
+}
+
+/// Returns the level of each band from 0 to 1, measured with the Goertzel algorithm at
+/// logarithmically spaced frequencies.
+fn spectrum(samples: &[f32], rate: u32) -> Vec<f32> {
+    let samples = &samples[samples.len().saturating_sub(WINDOW)..];
+    if samples.len() < 2 || rate == 0 {
+        return vec![0.0; BANDS];
+    }
+    let n = samples.len() as f32;
+    let highest = HIGHEST.min(rate as f32 / 2.0);
+
+    (0..BANDS)
+        .map(|band| {
+            let frequency = LOWEST * (highest / LOWEST).powf(band as f32 / (BANDS - 1) as f32);
+            let coefficient = 2.0 * (2.0 * PI * frequency / rate as f32).cos();
+            let (mut s1, mut s2) = (0.0, 0.0);
+            for (i, sample) in samples.iter().enumerate() {
+                // Hann window against leakage from neighbouring bands
+                let window = 0.5 - 0.5 * (2.0 * PI * i as f32 / (n - 1.0)).cos();
+                let s = sample * window + coefficient * s1 - s2;
+                s2 = s1;
+                s1 = s;
+            }
+            let power = (s1 * s1 + s2 * s2 - coefficient * s1 * s2).max(0.0);
+            // A full scale sine reaches n / 4 through the window
+            let amplitude = power.sqrt() / (n / 4.0);
+            let db = 20.0 * amplitude.max(1e-6).log10();
+            ((db - FLOOR) / -FLOOR).clamp(0.0, 1.0)
+        })
+        .collect()
+}